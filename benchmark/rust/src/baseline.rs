@@ -0,0 +1,65 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::report::Metrics;
+
+/// The POC ON numbers a regression check can compare, under their
+/// `report::Metrics` names. Every field is required: a baseline missing
+/// the metric must fail the check, not read as 0 and pass it.
+#[derive(Deserialize)]
+pub struct Baseline {
+    pub mean: f64,
+    pub trimmed_mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub p999: f64,
+}
+
+impl From<&Metrics> for Baseline {
+    fn from(m: &Metrics) -> Self {
+        Self {
+            mean: m.mean,
+            trimmed_mean: m.trimmed_mean,
+            p50: m.p50,
+            p90: m.p90,
+            p95: m.p95,
+            p99: m.p99,
+            p999: m.p999,
+        }
+    }
+}
+
+/// Load the POC ON results of a `--format json` report written by an
+/// earlier run.
+pub fn load(path: &Path) -> Result<Baseline, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("read({}): {e}", path.display()))?;
+    let mut report: serde_json::Value =
+        serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+    match report.get_mut("poc_on").map(serde_json::Value::take) {
+        Some(v) if !v.is_null() => {
+            serde_json::from_value(v).map_err(|e| format!("{}: poc_on: {e}", path.display()))
+        }
+        _ => Err(format!("{}: no poc_on results", path.display())),
+    }
+}
+
+/// Change of one metric against the baseline; positive is slower.
+pub struct Regression {
+    pub metric: &'static str,
+    pub baseline_ns: f64,
+    pub current_ns: f64,
+}
+
+impl Regression {
+    pub fn delta_pct(&self) -> f64 {
+        if self.baseline_ns > 0.0 {
+            (self.current_ns - self.baseline_ns) / self.baseline_ns * 100.0
+        } else {
+            0.0
+        }
+    }
+}
@@ -1,9 +1,35 @@
-use crate::system::BenchParams;
-use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering};
+use crate::cpuidle::{IdleResidency, IdleSnapshot};
+use crate::perf::{PerfCounters, PerfCounts};
+use crate::schedstat::{SchedDelta, SchedSnapshot};
+use crate::system::{self, BenchParams, BgWorkload, WakeupMechanism, WarmupStrategy};
+use crate::timer::{self, Timer};
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicUsize, Ordering};
 use std::sync::mpsc::{self, Receiver};
 use std::sync::Arc;
 use std::thread;
 
+/// How often (in iterations) the dispatcher re-reads the online CPU count.
+/// The read goes through sysfs, so it stays out of every single iteration.
+const HOTPLUG_CHECK_INTERVAL: usize = 1024;
+
+/// Iterations per sliding window for the `stable` warmup strategy.
+const STABLE_WINDOW: usize = 256;
+/// Upper bound on the `stable` warmup, as a multiple of the fixed warmup.
+const STABLE_MAX_WARMUP_FACTOR: usize = 4;
+
+/// Default pause after each batch so shadows settle and workers re-enter
+/// read() (`--settle-ns`). It also bounds the dispatch rate, which sizes
+/// `--duration` buffers.
+pub const DEFAULT_SETTLE_NS: u64 = 10_000;
+/// Shortest a batch is assumed to take when sizing `--duration` buffers:
+/// even without a pause, each wakeup costs at least this much.
+const MIN_BATCH_NS: u64 = 1_000;
+/// Buffer each `--bg-workload memory` thread streams through; well past
+/// the per-core share of any LLC.
+const BG_MEMORY_BYTES: usize = 64 << 20;
+
 // ---------------------------------------------------------------------------
 // Shadow thread context
 // ---------------------------------------------------------------------------
@@ -24,6 +50,36 @@ impl ShadowCtx {
     }
 }
 
+/// Body of a background thread: `workload` until `stop`.
+fn bg_burn(workload: BgWorkload, stop: &AtomicBool) {
+    match workload {
+        BgWorkload::Spin => {
+            while !stop.load(Ordering::Relaxed) {
+                for _ in 0..10000u32 {
+                    core::hint::spin_loop();
+                }
+            }
+        }
+        BgWorkload::Memory => {
+            let mut buf = vec![0u8; BG_MEMORY_BYTES];
+            while !stop.load(Ordering::Relaxed) {
+                // One touch per cache line, read-modify-write.
+                for i in (0..buf.len()).step_by(64) {
+                    buf[i] = buf[i].wrapping_add(1);
+                }
+                std::hint::black_box(&mut buf);
+            }
+        }
+        BgWorkload::Syscall => {
+            while !stop.load(Ordering::Relaxed) {
+                for _ in 0..1000u32 {
+                    std::hint::black_box(unsafe { libc::getuid() });
+                }
+            }
+        }
+    }
+}
+
 fn shadow_thread(ctx: &ShadowCtx) {
     let mut cur_cpu: i32 = -1;
     while !ctx.stop.load(Ordering::Relaxed) {
@@ -45,18 +101,167 @@ fn shadow_thread(ctx: &ShadowCtx) {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Wakeup channel
+// ---------------------------------------------------------------------------
+
+/// How the dispatcher wakes one worker; all three count wakeups like a
+/// semaphore, so a queue depth above 1 works the same way.
+enum WakeChannel {
+    /// eventfd in semaphore mode: each read() consumes one wakeup.
+    Eventfd(i32),
+    /// Futex word counting posted wakeups; the worker sleeps in FUTEX_WAIT
+    /// while it equals the count it has already consumed.
+    Futex(AtomicU32),
+    /// pipe(2): one byte per wakeup, the worker reads one at a time.
+    Pipe { rd: i32, wr: i32 },
+}
+
+impl WakeChannel {
+    /// Fails when the kernel refuses the fds (e.g. a seccomp filter that
+    /// denies eventfd, or the fd limit).
+    fn new(mechanism: WakeupMechanism) -> Result<Self, BenchError> {
+        let failed = |call| BenchError::WakeChannel {
+            call,
+            err: io::Error::last_os_error(),
+        };
+        match mechanism {
+            WakeupMechanism::Eventfd => {
+                let efd = unsafe { libc::eventfd(0, libc::EFD_SEMAPHORE) };
+                if efd < 0 {
+                    return Err(failed("eventfd"));
+                }
+                Ok(WakeChannel::Eventfd(efd))
+            }
+            WakeupMechanism::Futex => Ok(WakeChannel::Futex(AtomicU32::new(0))),
+            WakeupMechanism::Pipe => {
+                let mut fds = [0i32; 2];
+                let ret = unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) };
+                if ret != 0 {
+                    return Err(failed("pipe2"));
+                }
+                Ok(WakeChannel::Pipe {
+                    rd: fds[0],
+                    wr: fds[1],
+                })
+            }
+        }
+    }
+
+    /// Block until a wakeup is pending and consume it. `consumed` is the
+    /// worker's own count (futex only). Returns false if waiting failed.
+    fn wait(&self, consumed: &mut u32) -> bool {
+        match self {
+            WakeChannel::Eventfd(efd) => {
+                let mut buf = [0u8; 8];
+                let n = unsafe { libc::read(*efd, buf.as_mut_ptr() as *mut libc::c_void, 8) };
+                n == 8
+            }
+            WakeChannel::Pipe { rd, .. } => {
+                let mut byte = 0u8;
+                let n = unsafe { libc::read(*rd, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+                n == 1
+            }
+            WakeChannel::Futex(word) => loop {
+                let posted = word.load(Ordering::Acquire);
+                if posted != *consumed {
+                    *consumed = consumed.wrapping_add(1);
+                    return true;
+                }
+                // Returns at once (EAGAIN) if a wakeup was posted meanwhile.
+                unsafe {
+                    libc::syscall(
+                        libc::SYS_futex,
+                        word.as_ptr(),
+                        libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+                        posted,
+                        std::ptr::null::<libc::timespec>(),
+                    );
+                }
+            },
+        }
+    }
+
+    /// Post `n` wakeups.
+    fn post(&self, n: u64) {
+        match self {
+            WakeChannel::Eventfd(efd) => unsafe {
+                libc::write(*efd, &n as *const u64 as *const libc::c_void, 8);
+            },
+            WakeChannel::Pipe { wr, .. } => {
+                // More than a pipe buffer's worth blocks until the worker
+                // drains it, which only happens on the abort paths.
+                let bytes = vec![0u8; n as usize];
+                let mut off = 0;
+                while off < bytes.len() {
+                    let w = unsafe {
+                        libc::write(
+                            *wr,
+                            bytes[off..].as_ptr() as *const libc::c_void,
+                            bytes.len() - off,
+                        )
+                    };
+                    if w <= 0 {
+                        break;
+                    }
+                    off += w as usize;
+                }
+            }
+            WakeChannel::Futex(word) => {
+                word.fetch_add(n as u32, Ordering::Release);
+                unsafe {
+                    libc::syscall(
+                        libc::SYS_futex,
+                        word.as_ptr(),
+                        libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+                        1,
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl Drop for WakeChannel {
+    fn drop(&mut self) {
+        let fds = match *self {
+            WakeChannel::Eventfd(efd) => vec![efd],
+            WakeChannel::Pipe { rd, wr } => vec![rd, wr],
+            WakeChannel::Futex(_) => Vec::new(),
+        };
+        for fd in fds {
+            unsafe {
+                libc::close(fd);
+            }
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Worker thread context
 // ---------------------------------------------------------------------------
 
 struct WorkerCtx {
-    efd: i32,
-    warmup: usize,
-    total: usize,
+    wake: WakeChannel,
+    timer: Timer,
+    rt_priority: i32, // SCHED_FIFO priority, 0 = leave at SCHED_OTHER
+    nice: i32,        // SCHED_OTHER niceness, 0 = leave as is
+    /// Set when `rt_priority` or `nice` could not be applied.
+    prio_denied: Arc<AtomicBool>,
+    record_from: usize, // first iteration stored in `latencies`
+    work_ns: u64,       // busy-spin after each wakeup, 0 = the fixed loop
+    end: Arc<AtomicUsize>,
     shadows: Vec<Arc<ShadowCtx>>,
     sync_done: Arc<AtomicU32>,
     ts_wake: Vec<AtomicU64>,
     latencies: Vec<AtomicU64>,
+    /// Shadow request-to-ack round trips, like `latencies`; empty unless
+    /// `--measure-shadow`.
+    shadow_rtt: Vec<AtomicU64>,
+    /// CPU of the latest wakeup, -1 before the first; kept only with
+    /// `--cross-cpu`.
+    last_cpu: AtomicI32,
+    track_cpu: bool,
 }
 
 // AtomicU64 wrapper (stable since 1.34)
@@ -66,48 +271,84 @@ fn worker_thread(ctx: &WorkerCtx) {
     let n_shadows = ctx.shadows.len();
     let mut sidx: usize = 0;
 
+    // Set here rather than inherited: the dispatcher is not yet SCHED_FIFO
+    // when it spawns workers, and each worker may want a different class.
+    // Without CAP_SYS_NICE the worker keeps running as it was.
+    if ctx.rt_priority > 0 {
+        let param = libc::sched_param {
+            sched_priority: ctx.rt_priority,
+        };
+        if unsafe { libc::sched_setscheduler(0, libc::SCHED_FIFO, &param) } != 0 {
+            ctx.prio_denied.store(true, Ordering::Relaxed);
+        }
+    } else if ctx.nice != 0 {
+        // On Linux PRIO_PROCESS with who = 0 sets the calling thread only.
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, ctx.nice) } != 0 {
+            ctx.prio_denied.store(true, Ordering::Relaxed);
+        }
+    }
+
     // Initial shadow setup
-    let cpu = sched_getcpu();
-    ctx.shadows[0].ack.store(0, Ordering::Release);
-    ctx.shadows[0]
-        .target_cpu
-        .store(cpu as i32, Ordering::Release);
-    bounded_spin_wait(&ctx.shadows[0].ack);
+    if n_shadows > 0 {
+        let cpu = sched_getcpu();
+        ctx.shadows[0].ack.store(0, Ordering::Release);
+        ctx.shadows[0]
+            .target_cpu
+            .store(cpu as i32, Ordering::Release);
+        bounded_spin_wait(&ctx.shadows[0].ack);
+    }
     ctx.sync_done.fetch_add(1, Ordering::Release);
 
-    let mut buf = [0u8; 8];
-    for i in 0..ctx.total {
-        // Block on eventfd
-        let n = unsafe { libc::read(ctx.efd, buf.as_mut_ptr() as *mut libc::c_void, 8) };
-        if n != 8 {
+    let mut consumed = 0;
+    let mut i = 0;
+    while i < ctx.end.load(Ordering::Acquire) {
+        // Block on the eventfd, futex or pipe
+        if !ctx.wake.wait(&mut consumed) {
             break;
         }
 
-        let t1 = now_ns();
+        let t1 = ctx.timer.now_ns();
         let t0 = ctx.ts_wake[i].load(Ordering::Acquire);
-        if i >= ctx.warmup {
-            ctx.latencies[i - ctx.warmup].store(t1.wrapping_sub(t0), Ordering::Relaxed);
+        if i >= ctx.record_from {
+            ctx.latencies[i - ctx.record_from].store(t1.wrapping_sub(t0), Ordering::Relaxed);
+        }
+
+        // Payload; t1 is already taken, so it never counts as latency.
+        if ctx.work_ns > 0 {
+            busy_wait_ns(ctx.work_ns);
+        } else {
+            let mut x: u32 = 0;
+            for j in 0..100u32 {
+                x = x.wrapping_add(j);
+            }
+            std::hint::black_box(x);
         }
 
-        // Brief compute
-        let mut x: u32 = 0;
-        for j in 0..100u32 {
-            x = x.wrapping_add(j);
+        if ctx.track_cpu {
+            ctx.last_cpu.store(sched_getcpu() as i32, Ordering::Relaxed);
         }
-        std::hint::black_box(x);
 
         // Tell shadow to pin to our current CPU
-        let cpu = sched_getcpu();
-        ctx.shadows[sidx].ack.store(0, Ordering::Release);
-        ctx.shadows[sidx]
-            .target_cpu
-            .store(cpu as i32, Ordering::Release);
-        bounded_spin_wait(&ctx.shadows[sidx].ack);
+        if n_shadows > 0 {
+            let measure = i >= ctx.record_from && !ctx.shadow_rtt.is_empty();
+            let t_req = if measure { ctx.timer.now_ns() } else { 0 };
+            let cpu = sched_getcpu();
+            ctx.shadows[sidx].ack.store(0, Ordering::Release);
+            ctx.shadows[sidx]
+                .target_cpu
+                .store(cpu as i32, Ordering::Release);
+            bounded_spin_wait(&ctx.shadows[sidx].ack);
+            if measure {
+                let rtt = ctx.timer.now_ns().wrapping_sub(t_req);
+                ctx.shadow_rtt[i - ctx.record_from].store(rtt, Ordering::Relaxed);
+            }
+        }
 
         if n_shadows > 1 {
             sidx ^= 1;
         }
         ctx.sync_done.fetch_add(1, Ordering::Release);
+        i += 1;
     }
 }
 
@@ -120,49 +361,264 @@ fn bounded_spin_wait(ack: &AtomicI32) {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Errors
+// ---------------------------------------------------------------------------
+
+#[derive(Debug)]
+pub enum BenchError {
+    /// The online CPU count changed mid-run, so the placement the run was
+    /// planned around no longer holds.
+    CpuHotplug { before: usize, after: usize },
+    /// Not enough online CPUs to keep the dispatcher, workers and shadows
+    /// apart; the numbers would only measure them preempting each other.
+    TooFewCpus { online: usize, needed: usize },
+    /// A worker's wakeup channel could not be created.
+    WakeChannel { call: &'static str, err: io::Error },
+    /// Calibration ran past `--calib-timeout`; its probe was abandoned.
+    CalibrationTimeout { secs: f64 },
+    /// The run was abandoned on a quit request.
+    Interrupted,
+}
+
+impl std::error::Error for BenchError {}
+
+impl fmt::Display for BenchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchError::CpuHotplug { before, after } => write!(
+                f,
+                "online CPU count changed during the run ({before} -> {after}), aborting"
+            ),
+            BenchError::TooFewCpus { online, needed } => write!(
+                f,
+                "{online} online CPU(s), the benchmark needs at least {needed} \
+                 (dispatcher, worker and shadow on separate CPUs)"
+            ),
+            BenchError::WakeChannel { call, err } => write!(f, "{call}() failed: {err}"),
+            BenchError::CalibrationTimeout { secs } => write!(
+                f,
+                "calibration did not finish within {secs}s (--calib-timeout); \
+                 the system is overloaded or a worker is never woken"
+            ),
+            BenchError::Interrupted => write!(f, "interrupted"),
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Async benchmark handle
 // ---------------------------------------------------------------------------
 
+/// Samples from one measured phase.
+#[derive(Default)]
+pub struct BenchRun {
+    pub samples: Vec<u64>,
+    /// Iterations dropped as warmup before recording started.
+    pub warmup_discarded: usize,
+    /// perf counters over the recorded iterations (`--perf` only).
+    pub perf: Option<PerfCounts>,
+    /// cpuidle residency over the recorded iterations, when available.
+    pub idle: Option<IdleResidency>,
+    /// /proc/schedstat activity over the recorded iterations, when
+    /// available.
+    pub sched: Option<SchedDelta>,
+    /// Some worker could not switch to its SCHED_FIFO priority or nice
+    /// value and ran at the default.
+    pub worker_prio_denied: bool,
+    /// Samples per worker; `samples` holds each worker's run back to back.
+    pub per_worker: usize,
+    /// Shadow ack round trips (ns), laid out like `samples`; empty unless
+    /// `--measure-shadow` with shadows.
+    pub shadow_samples: Vec<u64>,
+    /// Times `--cross-cpu` moved the dispatcher off a worker's core.
+    pub dispatcher_moves: usize,
+}
+
+impl BenchRun {
+    pub fn worker_samples(&self, worker: usize) -> &[u64] {
+        let start = worker * self.per_worker;
+        &self.samples[start..start + self.per_worker]
+    }
+}
+
+pub type BenchResult = Result<BenchRun, BenchError>;
+
+/// Latencies kept for the live view.
+pub const RECENT_SAMPLES: usize = 200;
+
+/// Fixed-size ring of the most recent latencies (ns): the worst worker's
+/// latency per iteration, written by the dispatcher between batches.
+pub struct RecentSamples {
+    slots: Vec<AtomicU64>,
+    written: AtomicUsize,
+}
+
+impl RecentSamples {
+    fn new() -> Self {
+        Self {
+            slots: (0..RECENT_SAMPLES).map(|_| AtomicU64::new(0)).collect(),
+            written: AtomicUsize::new(0),
+        }
+    }
+
+    fn push(&self, ns: u64) {
+        let n = self.written.load(Ordering::Relaxed);
+        self.slots[n % RECENT_SAMPLES].store(ns, Ordering::Relaxed);
+        self.written.store(n + 1, Ordering::Release);
+    }
+
+    /// Samples in the ring, oldest first.
+    pub fn snapshot(&self) -> Vec<u64> {
+        let n = self.written.load(Ordering::Acquire);
+        let first = n.saturating_sub(RECENT_SAMPLES);
+        (first..n)
+            .map(|i| self.slots[i % RECENT_SAMPLES].load(Ordering::Relaxed))
+            .collect()
+    }
+}
+
 pub struct BenchHandle {
+    /// Iterations done out of `total`, or with a phase duration set,
+    /// elapsed milliseconds of the measured phase out of `total`.
     pub progress: Arc<AtomicU32>,
     pub total: u32,
-    rx: Receiver<Vec<u64>>,
+    recent: Arc<RecentSamples>,
+    /// While set the dispatcher holds between batches (see `set_paused`).
+    paused: Arc<AtomicBool>,
+    rx: Receiver<BenchResult>,
 }
 
 impl BenchHandle {
-    pub fn try_recv(&self) -> Option<Vec<u64>> {
+    pub fn fraction(&self) -> f64 {
+        if self.total > 0 {
+            (self.progress.load(Ordering::Relaxed) as f64 / self.total as f64).min(1.0)
+        } else {
+            0.0
+        }
+    }
+
+    pub fn try_recv(&self) -> Option<BenchResult> {
         self.rx.try_recv().ok()
     }
+
+    /// Hold the run at the next batch boundary, or let it continue. A timed
+    /// phase doesn't count the paused time.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Up to [`RECENT_SAMPLES`] of the latest latencies, oldest first.
+    pub fn recent(&self) -> Vec<u64> {
+        self.recent.snapshot()
+    }
 }
 
 // ---------------------------------------------------------------------------
 // Public API
 // ---------------------------------------------------------------------------
 
+/// Start a run on a background thread and return at once. Poll
+/// [`BenchHandle::fraction`] for progress and [`BenchHandle::try_recv`]
+/// for the result.
 pub fn bench_burst_async(params: &BenchParams, iterations: usize, warmup: usize) -> BenchHandle {
     let progress = Arc::new(AtomicU32::new(0));
     let (tx, rx) = mpsc::channel();
-    let total_iters = (warmup + iterations) as u32;
+    let total_iters = match params.phase_duration {
+        Some(d) => d.as_millis() as u32,
+        None => (max_warmup(params, warmup) + iterations) as u32,
+    };
 
     let params = params.clone();
     let progress_clone = progress.clone();
+    let paused = Arc::new(AtomicBool::new(false));
+    let paused_clone = paused.clone();
+    let recent = Arc::new(RecentSamples::new());
+    let recent_clone = recent.clone();
 
     thread::spawn(move || {
-        let result = bench_burst_inner(&params, iterations, warmup, &progress_clone);
+        let result = bench_burst_inner(
+            &params,
+            iterations,
+            warmup,
+            &progress_clone,
+            &paused_clone,
+            &recent_clone,
+        );
         let _ = tx.send(result);
     });
 
     BenchHandle {
         progress,
         total: total_iters,
+        recent,
+        paused,
         rx,
     }
 }
 
-pub fn bench_burst_sync(params: &BenchParams, iterations: usize, warmup: usize) -> Vec<u64> {
-    let progress = Arc::new(AtomicU32::new(0));
-    bench_burst_inner(params, iterations, warmup, &progress)
+/// Run on the calling thread and return when the run is done.
+pub fn bench_burst_sync(params: &BenchParams, iterations: usize, warmup: usize) -> BenchResult {
+    let progress = AtomicU32::new(0);
+    let recent = RecentSamples::new();
+    bench_burst_inner(
+        params,
+        iterations,
+        warmup,
+        &progress,
+        &AtomicBool::new(false),
+        &recent,
+    )
+}
+
+/// Longest warmup the run may need: the fixed count, or the cap for the
+/// `stable` strategy (which stops earlier once the window p50 settles).
+fn max_warmup(params: &BenchParams, warmup: usize) -> usize {
+    match params.warmup_strategy {
+        WarmupStrategy::Fixed => warmup,
+        WarmupStrategy::Stable { .. } => (warmup * STABLE_MAX_WARMUP_FACTOR).max(2 * STABLE_WINDOW),
+    }
+}
+
+/// Iterations a phase of `duration` can reach at most: the dispatcher
+/// settles for `settle_ns` after every batch of `queue_depth` wakeups.
+pub fn duration_capacity(params: &BenchParams, duration: std::time::Duration) -> usize {
+    let batch_ns = params.settle_ns.max(MIN_BATCH_NS);
+    (duration.as_nanos() / batch_ns as u128) as usize * params.queue_depth.max(1)
+}
+
+/// Bytes of `ts_wake` + `latencies` (+ `shadow_rtt`) a run allocates
+/// across all workers.
+pub fn sample_buffer_bytes(params: &BenchParams, iterations: usize, warmup: usize) -> usize {
+    let total = max_warmup(params, warmup) + iterations;
+    let recorded = match params.warmup_strategy {
+        WarmupStrategy::Fixed => iterations,
+        WarmupStrategy::Stable { .. } => total,
+    };
+    let series = 1 + usize::from(params.measure_shadow && params.shadows_per_worker > 0);
+    params.n_workers * (total + series * recorded) * std::mem::size_of::<AtomicU64>()
+}
+
+/// Largest iteration count whose sample buffers fit in `budget` bytes.
+pub fn max_iterations_for(params: &BenchParams, warmup: usize, budget: usize) -> usize {
+    let base = sample_buffer_bytes(params, 0, warmup);
+    let per_iter = sample_buffer_bytes(params, 1, warmup) - base;
+    budget.saturating_sub(base) / per_iter
+}
+
+/// Median latency of iterations `range` across all workers.
+fn window_p50(worker_ctxs: &[Arc<WorkerCtx>], range: std::ops::Range<usize>) -> u64 {
+    let mut window: Vec<u64> = worker_ctxs
+        .iter()
+        .flat_map(|ctx| ctx.latencies[range.clone()].iter())
+        .map(|v| v.load(Ordering::Relaxed))
+        .collect();
+    let mid = window.len() / 2;
+    *window.select_nth_unstable(mid).1
 }
 
 fn bench_burst_inner(
@@ -170,16 +626,58 @@ fn bench_burst_inner(
     iterations: usize,
     warmup: usize,
     progress: &AtomicU32,
-) -> Vec<u64> {
-    let ncpus = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) as usize };
-    let total = warmup + iterations;
+    paused: &AtomicBool,
+    recent: &RecentSamples,
+) -> BenchResult {
+    let ncpus = system::online_cpus();
+    if ncpus < params.min_cpus() {
+        return Err(BenchError::TooFewCpus {
+            online: ncpus,
+            needed: params.min_cpus(),
+        });
+    }
+    let max_warmup = max_warmup(params, warmup);
+    let total = max_warmup + iterations;
+    // The stable strategy has to look at warmup samples, so it records all.
+    let record_from = match params.warmup_strategy {
+        WarmupStrategy::Fixed => warmup,
+        WarmupStrategy::Stable { .. } => 0,
+    };
     let n_workers = params.n_workers;
-    let n_background = params.n_background.min(ncpus - 1);
+    let n_background = params.n_background.min(ncpus.saturating_sub(1));
     let spw = params.shadows_per_worker;
     let total_shadows = n_workers * spw;
+    let housekeeping = params.housekeeping_cpus(ncpus);
+    // Workers start inside their NUMA node and/or the isolated set;
+    // shadows follow them there.
+    let worker_masks: Vec<Option<CpuSet>> = (0..n_workers)
+        .map(|w| {
+            params
+                .worker_cpus(w, housekeeping[0])
+                .map(|cpus| CpuSet::from_cpus(&cpus))
+        })
+        .collect();
 
     // Save original affinity
-    let orig_affinity = get_affinity();
+    let orig_affinity = CpuSet::current();
+
+    // Counters must exist before any thread is spawned to be inherited.
+    let perf = if params.perf {
+        PerfCounters::open()
+    } else {
+        None
+    };
+    let mut perf_enabled = false;
+    let mut idle_start = None;
+    let mut sched_start = None;
+    let mut deadline: Option<(u64, u64)> = None; // (start, end) in now_ns()
+
+    // Before any thread exists, so a failure has nothing to tear down;
+    // channels created before it are closed when the Vec drops.
+    let mut wakes = (0..n_workers)
+        .map(|_| WakeChannel::new(params.wakeup))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter();
 
     // --- 1. Create shadow contexts ---
     let shadow_ctxs: Vec<Arc<ShadowCtx>> = (0..total_shadows)
@@ -196,48 +694,86 @@ fn bench_burst_inner(
 
     // --- 2. Create worker contexts ---
     let sync_done = Arc::new(AtomicU32::new(0));
+    let end = Arc::new(AtomicUsize::new(total));
 
-    let mut worker_efds = Vec::with_capacity(n_workers);
     let mut worker_ctxs: Vec<Arc<WorkerCtx>> = Vec::with_capacity(n_workers);
+    let prio_denied = Arc::new(AtomicBool::new(false));
+    let timer = Timer::new(params.timer);
 
     for w in 0..n_workers {
-        let efd = unsafe { libc::eventfd(0, libc::EFD_SEMAPHORE) };
-        assert!(efd >= 0, "eventfd failed");
-        worker_efds.push(efd);
-
         let shadows: Vec<Arc<ShadowCtx>> = (0..spw)
             .map(|s| Arc::clone(&shadow_ctxs[w * spw + s]))
             .collect();
 
         let ts_wake: Vec<AtomicU64> = (0..total).map(|_| AtomicU64::new(0)).collect();
-        let latencies: Vec<AtomicU64> = (0..iterations).map(|_| AtomicU64::new(0)).collect();
+        let latencies: Vec<AtomicU64> = (0..total - record_from)
+            .map(|_| AtomicU64::new(0))
+            .collect();
+        let shadow_rtt: Vec<AtomicU64> = if params.measure_shadow && spw > 0 {
+            (0..total - record_from)
+                .map(|_| AtomicU64::new(0))
+                .collect()
+        } else {
+            Vec::new()
+        };
 
         worker_ctxs.push(Arc::new(WorkerCtx {
-            efd,
-            warmup,
-            total,
+            wake: wakes.next().expect("one wake channel per worker"),
+            timer,
+            rt_priority: params.worker_priority(w),
+            nice: params.worker_nice,
+            prio_denied: Arc::clone(&prio_denied),
+            work_ns: params.work_ns,
+            record_from,
+            end: Arc::clone(&end),
             shadows,
             sync_done: Arc::clone(&sync_done),
             ts_wake,
             latencies,
+            shadow_rtt,
+            last_cpu: AtomicI32::new(-1),
+            track_cpu: params.cross_cpu,
         }));
     }
 
     let worker_handles: Vec<_> = worker_ctxs
         .iter()
-        .map(|ctx| {
+        .zip(worker_masks)
+        .map(|(ctx, mask)| {
             let ctx = Arc::clone(ctx);
-            thread::spawn(move || worker_thread(&ctx))
+            thread::spawn(move || {
+                if let Some(mask) = mask {
+                    mask.apply();
+                }
+                worker_thread(&ctx)
+            })
         })
         .collect();
 
     // --- 3. Background burn threads ---
     let bg_stop = Arc::new(AtomicBool::new(false));
-    let bg_handles: Vec<_> = (0..n_background)
-        .map(|i| {
+    let bg_workload = params.bg_workload;
+    // Skip the dispatcher's CPU.
+    let bg_cpus: Vec<usize> = (0..n_background)
+        .map(|i| housekeeping[(i + 1) % housekeeping.len()])
+        .collect();
+    let bg_handles: Vec<_> = bg_cpus
+        .iter()
+        .map(|&cpu| {
+            let stop = Arc::clone(&bg_stop);
+            thread::spawn(move || {
+                pin_self(cpu);
+                bg_burn(bg_workload, &stop);
+            })
+        })
+        .collect();
+
+    // Oversubscription: unpinned hogs the scheduler has to place alongside
+    // the workers, so wakeups land on CPUs that are already busy.
+    let hog_handles: Vec<_> = (0..params.oversubscribe_threads(ncpus))
+        .map(|_| {
             let stop = Arc::clone(&bg_stop);
             thread::spawn(move || {
-                pin_self(i + 1); // skip CPU 0 (dispatcher)
                 while !stop.load(Ordering::Relaxed) {
                     for _ in 0..10000u32 {
                         core::hint::spin_loop();
@@ -247,8 +783,11 @@ fn bench_burst_inner(
         })
         .collect();
 
-    // --- 4. Pin dispatcher to CPU 0 with SCHED_FIFO ---
-    pin_self(0);
+    // --- 4. Pin dispatcher to the first housekeeping CPU with SCHED_FIFO ---
+    pin_self(housekeeping[0]);
+    let mut far_cpus = params
+        .cross_cpu
+        .then(|| FarCpus::new(ncpus, &bg_cpus, housekeeping[0]));
     let orig_sched = set_fifo_self();
     thread::sleep(std::time::Duration::from_millis(50));
 
@@ -260,33 +799,154 @@ fn bench_burst_inner(
     thread::sleep(std::time::Duration::from_micros(200));
 
     // --- 6. Dispatch ---
-    let wval: u64 = 1;
-    for i in 0..total {
+    let depth = params.queue_depth.max(1);
+    let mut hotplug = None;
+    let mut next_hotplug_check = 0;
+    let mut in_flight = 0;
+    let mut warmup_end = match params.warmup_strategy {
+        WarmupStrategy::Fixed => Some(warmup),
+        WarmupStrategy::Stable { .. } => None,
+    };
+    let mut run_end = total;
+    let mut prev_p50: Option<u64> = None;
+    let mut next_window = 2 * STABLE_WINDOW;
+    let mut i = 0;
+    while i < run_end {
+        if i >= next_hotplug_check {
+            next_hotplug_check = i + HOTPLUG_CHECK_INTERVAL;
+            let now = system::online_cpus();
+            if now != ncpus {
+                hotplug = Some(BenchError::CpuHotplug {
+                    before: ncpus,
+                    after: now,
+                });
+                // Release the workers from read() so they can be joined;
+                // whatever they record from here on is discarded.
+                let rest = (run_end - i) as u64;
+                for ctx in &worker_ctxs {
+                    ctx.wake.post(rest);
+                }
+                break;
+            }
+        }
         if i > 0 {
-            while sync_done.load(Ordering::Acquire) < n_workers as u32 {
+            while sync_done.load(Ordering::Acquire) < (n_workers * in_flight) as u32 {
                 core::hint::spin_loop();
             }
             sync_done.store(0, Ordering::Release);
 
+            // The batch just finished is fully recorded: feed the live view
+            // with the worst worker per iteration.
+            for j in (i - in_flight..i).filter(|&j| j >= record_from) {
+                let worst = worker_ctxs
+                    .iter()
+                    .map(|ctx| ctx.latencies[j - record_from].load(Ordering::Relaxed))
+                    .max()
+                    .unwrap_or(0);
+                recent.push(worst);
+            }
+
             // Let shadows settle + workers enter read()
-            busy_wait_ns(10_000);
+            busy_wait_ns(params.settle_ns);
         }
 
-        for w in 0..n_workers {
-            let t0 = now_ns();
-            worker_ctxs[w].ts_wake[i].store(t0, Ordering::Release);
-            unsafe {
-                libc::write(
-                    worker_efds[w],
-                    &wval as *const u64 as *const libc::c_void,
-                    8,
-                );
+        // Pause between batches: every worker is parked in wait() and the
+        // barrier has been reset, so resuming continues exactly as if the
+        // dispatcher had never stopped. The paused time is moved out of a
+        // timed phase's window.
+        if paused.load(Ordering::Relaxed) {
+            let t = now_ns();
+            while paused.load(Ordering::Relaxed) {
+                thread::sleep(std::time::Duration::from_millis(10));
+            }
+            busy_wait_ns(params.settle_ns);
+            if let Some((start, stop)) = deadline.as_mut() {
+                let held = now_ns() - t;
+                *start += held;
+                *stop += held;
+            }
+        }
+
+        // Timed phase over: end the run at `i`. Workers already blocked in
+        // read() for iteration `i` get one spare wakeup to see the new end.
+        if let Some((start, stop)) = deadline {
+            let now = now_ns();
+            if now >= stop {
+                run_end = i;
+                end.store(run_end, Ordering::Release);
+                for ctx in &worker_ctxs {
+                    ctx.wake.post(1);
+                }
+                break;
+            }
+            progress.store(((now - start) / 1_000_000) as u32, Ordering::Relaxed);
+        }
+
+        // Stable warmup: everything before `i` has been recorded (the
+        // barrier above orders it), so compare the p50 of the last window
+        // with the one before it and start measuring once they agree.
+        if let (None, WarmupStrategy::Stable { tolerance }) = (warmup_end, params.warmup_strategy) {
+            if i >= next_window {
+                next_window = i + STABLE_WINDOW;
+                let p50 = window_p50(&worker_ctxs, i - STABLE_WINDOW..i);
+                if let Some(prev) = prev_p50 {
+                    let change = (p50 as f64 - prev as f64).abs() / (prev as f64).max(1.0);
+                    if change <= tolerance {
+                        warmup_end = Some(i.min(max_warmup));
+                    }
+                }
+                prev_p50 = Some(p50);
+            }
+            if warmup_end.is_none() && i >= max_warmup {
+                warmup_end = Some(max_warmup);
+            }
+            if let Some(w) = warmup_end {
+                run_end = w + iterations;
+                end.store(run_end, Ordering::Release);
+            }
+        }
+
+        if !perf_enabled && warmup_end.is_some_and(|w| i >= w) {
+            if let Some(p) = &perf {
+                p.enable();
+            }
+            idle_start = IdleSnapshot::take(ncpus);
+            sched_start = SchedSnapshot::take();
+            perf_enabled = true;
+            if let Some(d) = params.phase_duration {
+                let start = now_ns();
+                deadline = Some((start, start + d.as_nanos() as u64));
+            }
+        }
+
+        if let Some(far) = far_cpus.as_mut() {
+            far.avoid(&worker_ctxs);
+        }
+
+        // With a queue depth above 1 the later wakeups of a batch are already
+        // pending when the worker returns to read(), so their latency includes
+        // the time spent draining the ones queued ahead of them.
+        let batch = depth.min(run_end - i);
+        for j in i..i + batch {
+            for ctx in &worker_ctxs {
+                let t0 = timer.now_ns();
+                ctx.ts_wake[j].store(t0, Ordering::Release);
+                ctx.wake.post(1);
             }
         }
+        in_flight = batch;
+        i += batch;
 
-        progress.store(i as u32 + 1, Ordering::Relaxed);
+        // Count the skipped part of the warmup cap as done so the
+        // progress bar still ends at 100%.
+        if params.phase_duration.is_none() {
+            progress.store((i + total - run_end) as u32, Ordering::Relaxed);
+        }
     }
 
+    let idle = idle_start.and_then(|s0| Some(IdleSnapshot::take(ncpus)?.since(&s0)));
+    let sched = sched_start.and_then(|s0| SchedSnapshot::take()?.since(&s0));
+
     // Join workers
     for h in worker_handles {
         h.join().ok();
@@ -294,7 +954,7 @@ fn bench_burst_inner(
 
     // Stop background
     bg_stop.store(true, Ordering::Relaxed);
-    for h in bg_handles {
+    for h in bg_handles.into_iter().chain(hog_handles) {
         h.join().ok();
     }
 
@@ -306,30 +966,54 @@ fn bench_burst_inner(
         h.join().ok();
     }
 
-    // Collect latencies
-    let mut all = Vec::with_capacity(iterations * n_workers);
-    for w in 0..n_workers {
-        for i in 0..iterations {
-            all.push(worker_ctxs[w].latencies[i].load(Ordering::Relaxed));
-        }
-    }
+    let perf = perf.map(|p| p.read());
 
-    // Close eventfds
-    for &efd in &worker_efds {
-        unsafe {
-            libc::close(efd);
+    // Collect latencies
+    let warmup_discarded = warmup_end.unwrap_or(max_warmup);
+    let start = warmup_discarded - record_from;
+    // A timed phase may stop short of `iterations`.
+    let recorded = run_end.saturating_sub(warmup_discarded).min(iterations);
+    let collect = |series: fn(&WorkerCtx) -> &[AtomicU64]| {
+        let mut all = Vec::with_capacity(recorded * n_workers);
+        for ctx in &worker_ctxs {
+            let series = series(ctx);
+            if series.is_empty() {
+                continue;
+            }
+            for lat in &series[start..start + recorded] {
+                all.push(
+                    lat.load(Ordering::Relaxed)
+                        .saturating_sub(params.subtract_overhead_ns),
+                );
+            }
         }
-    }
+        all
+    };
+    let all = collect(|ctx| &ctx.latencies);
+    let shadow_samples = collect(|ctx| &ctx.shadow_rtt);
 
     // Restore scheduler policy and affinity
     if let Some(sp) = orig_sched {
         restore_sched_self(&sp);
     }
     if let Some(mask) = orig_affinity {
-        set_affinity_mask(&mask);
+        mask.apply();
     }
 
-    all
+    match hotplug {
+        Some(e) => Err(e),
+        None => Ok(BenchRun {
+            samples: all,
+            warmup_discarded,
+            perf,
+            idle,
+            sched,
+            worker_prio_denied: prio_denied.load(Ordering::Relaxed),
+            per_worker: recorded,
+            shadow_samples,
+            dispatcher_moves: far_cpus.map_or(0, |f| f.moves),
+        }),
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -337,16 +1021,106 @@ fn bench_burst_inner(
 // ---------------------------------------------------------------------------
 
 fn now_ns() -> u64 {
+    timer::clock_ns()
+}
+
+/// vDSO clock reads take tens of ns; above this the read is most likely
+/// falling back to a real syscall.
+pub const CLOCK_SYSCALL_THRESHOLD_NS: f64 = 100.0;
+
+/// Latencies within this multiple of the clock resolution are quantized
+/// too coarsely to compare.
+pub const CLOCK_RES_MULTIPLE: u64 = 10;
+
+/// `clock_getres(CLOCK_MONOTONIC)` in ns (0 if the call fails).
+pub fn clock_resolution_ns() -> u64 {
     let mut ts = libc::timespec {
         tv_sec: 0,
         tv_nsec: 0,
     };
-    unsafe {
-        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+    if unsafe { libc::clock_getres(libc::CLOCK_MONOTONIC, &mut ts) } != 0 {
+        return 0;
     }
     ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
 }
 
+/// Average cost of one `now_ns()` call, best of a few batches so a stray
+/// interrupt doesn't make a vDSO clock look like a syscall.
+pub fn clock_read_cost_ns() -> f64 {
+    const BATCH: u64 = 100_000;
+    let mut best = f64::MAX;
+    for _ in 0..5 {
+        let t0 = now_ns();
+        let mut acc = 0u64;
+        for _ in 0..BATCH {
+            acc = acc.wrapping_add(now_ns());
+        }
+        std::hint::black_box(acc);
+        let per_call = (now_ns() - t0) as f64 / BATCH as f64;
+        best = best.min(per_call);
+    }
+    best
+}
+
+/// `--cross-cpu`: where the dispatcher may move to stay off the workers'
+/// cores. Every online CPU but the background threads' qualifies.
+struct FarCpus {
+    candidates: Vec<usize>,
+    /// (package, core) per CPU, `None` where sysfs has no topology.
+    topology: Vec<Option<(i32, i32)>>,
+    current: usize,
+    moves: usize,
+}
+
+impl FarCpus {
+    fn new(ncpus: usize, bg_cpus: &[usize], current: usize) -> Self {
+        Self {
+            candidates: (0..ncpus).filter(|c| !bg_cpus.contains(c)).collect(),
+            topology: (0..ncpus).map(system::package_core).collect(),
+            current,
+            moves: 0,
+        }
+    }
+
+    fn same_core(&self, a: usize, b: usize) -> bool {
+        match (self.topology.get(a), self.topology.get(b)) {
+            (Some(Some(x)), Some(Some(y))) => x == y,
+            _ => a == b,
+        }
+    }
+
+    fn same_package(&self, a: usize, b: usize) -> bool {
+        match (self.topology.get(a), self.topology.get(b)) {
+            (Some(Some(x)), Some(Some(y))) => x.0 == y.0,
+            _ => false,
+        }
+    }
+
+    /// Move the dispatcher when it shares a core with where any worker
+    /// last ran, preferring a CPU on another package than those workers.
+    /// Stays put when every candidate is that close.
+    fn avoid(&mut self, workers: &[Arc<WorkerCtx>]) {
+        let near: Vec<usize> = workers
+            .iter()
+            .filter_map(|ctx| usize::try_from(ctx.last_cpu.load(Ordering::Relaxed)).ok())
+            .collect();
+        if !near.iter().any(|&c| self.same_core(c, self.current)) {
+            return;
+        }
+        let far = self
+            .candidates
+            .iter()
+            .copied()
+            .filter(|&c| !near.iter().any(|&n| self.same_core(c, n)))
+            .max_by_key(|&c| near.iter().filter(|&&n| !self.same_package(c, n)).count());
+        if let Some(cpu) = far {
+            pin_self(cpu);
+            self.current = cpu;
+            self.moves += 1;
+        }
+    }
+}
+
 fn busy_wait_ns(ns: u64) {
     let deadline = now_ns() + ns;
     while now_ns() < deadline {
@@ -358,24 +1132,93 @@ fn sched_getcpu() -> usize {
     unsafe { libc::sched_getcpu() as usize }
 }
 
-fn pin_self(cpu: usize) {
-    unsafe {
-        let mut set: libc::cpu_set_t = std::mem::zeroed();
-        libc::CPU_ZERO(&mut set);
-        libc::CPU_SET(cpu, &mut set);
-        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set);
-    }
+/// Affinity mask sized for the machine, like glibc's `CPU_ALLOC`: the
+/// fixed `cpu_set_t` only holds CPU_SETSIZE (1024) CPUs and `CPU_SET`
+/// silently ignores anything above that.
+#[derive(Clone)]
+pub struct CpuSet {
+    words: Vec<libc::c_ulong>,
 }
 
-fn get_affinity() -> Option<libc::cpu_set_t> {
-    unsafe {
-        let mut set: libc::cpu_set_t = std::mem::zeroed();
-        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) == 0 {
-            Some(set)
-        } else {
-            None
+const CPUSET_WORD_BITS: usize = libc::c_ulong::BITS as usize;
+
+impl CpuSet {
+    /// Empty set with room for every configured CPU.
+    pub fn new() -> Self {
+        let (conf, onln) = unsafe {
+            (
+                libc::sysconf(libc::_SC_NPROCESSORS_CONF),
+                libc::sysconf(libc::_SC_NPROCESSORS_ONLN),
+            )
+        };
+        let n = conf.max(onln);
+        Self::with_capacity(n.max(1) as usize)
+    }
+
+    fn with_capacity(cpus: usize) -> Self {
+        Self {
+            words: vec![0; cpus.div_ceil(CPUSET_WORD_BITS)],
+        }
+    }
+
+    pub fn from_cpus(cpus: &[usize]) -> Self {
+        let mut set = Self::new();
+        for &cpu in cpus {
+            set.insert(cpu);
+        }
+        set
+    }
+
+    pub fn insert(&mut self, cpu: usize) {
+        let word = cpu / CPUSET_WORD_BITS;
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1 << (cpu % CPUSET_WORD_BITS);
+    }
+
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    fn bytes(&self) -> usize {
+        self.words.len() * std::mem::size_of::<libc::c_ulong>()
+    }
+
+    /// Affinity of the calling thread. The kernel rejects a buffer smaller
+    /// than its own mask with EINVAL, so grow until it fits.
+    pub fn current() -> Option<Self> {
+        let mut set = Self::new();
+        loop {
+            let ptr = set.words.as_mut_ptr() as *mut libc::cpu_set_t;
+            let ret = unsafe { libc::sched_getaffinity(0, set.bytes(), ptr) };
+            if ret == 0 {
+                return Some(set);
+            }
+            if std::io::Error::last_os_error().raw_os_error() != Some(libc::EINVAL)
+                || set.words.len() > 1 << 16
+            {
+                return None;
+            }
+            set = Self::with_capacity(set.words.len() * 2 * CPUSET_WORD_BITS);
         }
     }
+
+    /// Set the calling thread's affinity; false if the kernel refused.
+    pub fn apply(&self) -> bool {
+        let ptr = self.words.as_ptr() as *const libc::cpu_set_t;
+        unsafe { libc::sched_setaffinity(0, self.bytes(), ptr) == 0 }
+    }
+}
+
+impl Default for CpuSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn pin_self(cpu: usize) {
+    CpuSet::from_cpus(&[cpu]).apply();
 }
 
 struct SavedSchedPolicy {
@@ -410,8 +1253,44 @@ fn restore_sched_self(saved: &SavedSchedPolicy) {
     }
 }
 
-fn set_affinity_mask(set: &libc::cpu_set_t) {
-    unsafe {
-        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), set);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn has(set: &CpuSet, cpu: usize) -> bool {
+        set.words
+            .get(cpu / CPUSET_WORD_BITS)
+            .is_some_and(|w| w >> (cpu % CPUSET_WORD_BITS) & 1 == 1)
+    }
+
+    #[test]
+    fn cpu_set_grows_past_cpu_setsize() {
+        let mut set = CpuSet::with_capacity(8);
+        for cpu in [0, 1_500, 4_095] {
+            set.insert(cpu);
+        }
+        assert_eq!(set.count(), 3);
+        assert!(set.bytes() * 8 >= 4_096);
+        assert!(has(&set, 1_500));
+        assert!(!has(&set, 1_499));
+        assert!(has(&set, 4_095));
+    }
+
+    /// Pins to the highest CPU this process may use: past CPU_SETSIZE on
+    /// machines with more than 1024 CPUs, where a fixed `cpu_set_t` fails.
+    #[test]
+    fn pins_to_highest_allowed_cpu() {
+        thread::spawn(|| {
+            let allowed = CpuSet::current().expect("sched_getaffinity");
+            let highest = (0..allowed.words.len() * CPUSET_WORD_BITS)
+                .rev()
+                .find(|&c| has(&allowed, c))
+                .expect("no allowed CPU");
+            pin_self(highest);
+            assert_eq!(CpuSet::current().map(|s| s.count()), Some(1));
+            assert_eq!(sched_getcpu(), highest);
+        })
+        .join()
+        .unwrap();
     }
 }
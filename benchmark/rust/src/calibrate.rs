@@ -1,6 +1,16 @@
-use crate::bench;
-use crate::stats::StatResult;
-use crate::system::BenchParams;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bench::{self, BenchError, BenchResult};
+use crate::stats::{self, StatResult};
+use crate::system::{
+    BenchParams, BgWorkload, NumaPlacement, TimerSource, WakeupMechanism, WarmupStrategy,
+};
+use crate::timer::Timer;
 
 const PROBE_MIN_SECS: f64 = 1.0;
 const PROBE_START_N: usize = 50;
@@ -8,17 +18,87 @@ const MIN_N: usize = 500;
 const MAX_N: usize = 500_000;
 const TARGET_PHASE_SECS: f64 = 5.0;
 const WARMUP_RATIO: f64 = 0.2; // 1/5 of main phase
+const OVERHEAD_READS: usize = 10_000;
+/// A final probe with stddev/mean above this ran into a load spike; it is
+/// repeated, up to `MAX_PROBE_ATTEMPTS` in total, keeping the steadiest.
+pub const MAX_PROBE_CV: f64 = 0.5;
+const MAX_PROBE_ATTEMPTS: usize = 3;
+/// Cached calibrations older than this are measured again.
+const CACHE_MAX_AGE_SECS: u64 = 7 * 24 * 3600;
+/// Iterations of each `--warmup-discard-verify` probe, plus a fifth as
+/// warmup.
+const QUIET_PROBE_N: usize = 2_000;
+/// `--warmup-discard-verify` probes before measuring anyway.
+pub const MAX_QUIET_PROBES: usize = 10;
+/// Iterations of the `--settle-ns auto` probe, plus a fifth as warmup.
+const SETTLE_PROBE_N: usize = 5_000;
+/// `--settle-ns auto` pauses this many shadow-ack p99s after each batch,
+/// within `SETTLE_RANGE_NS`.
+const SETTLE_FACTOR: f64 = 4.0;
+const SETTLE_RANGE_NS: (u64, u64) = (2_000, 100_000);
 
+/// Iteration counts sized so each measured phase takes about
+/// `TARGET_PHASE_SECS`, plus the probe's latency for reference.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CalibrationResult {
     pub iterations: usize,
     pub warmup: usize,
     pub probe_mean_us: f64,
     pub probe_stddev_us: f64,
+    /// Smallest delta between two back-to-back timer reads.
+    pub timer_overhead_ns: u64,
+    /// Final probes run before one was steady enough (1 = first try).
+    #[serde(default = "one")]
+    pub attempts: usize,
+    /// Wall-clock seconds per dispatched iteration in the final probe,
+    /// all overhead included; 0 in caches written before it existed.
+    #[serde(default)]
+    pub secs_per_iter: f64,
+}
+
+fn one() -> usize {
+    1
+}
+
+/// Cost of taking a timestamp with `source`: the minimum delta over
+/// back-to-back reads, i.e. what every latency sample carries on top of
+/// the wakeup itself.
+pub fn timer_overhead_ns(source: TimerSource) -> u64 {
+    let timer = Timer::new(source);
+    let mut best = u64::MAX;
+    let mut prev = timer.now_ns();
+    for _ in 0..OVERHEAD_READS {
+        let now = timer.now_ns();
+        best = best.min(now.wrapping_sub(prev));
+        prev = now;
+    }
+    best
+}
+
+/// Runs one calibration probe of `iterations` after `warmup`.
+pub type Probe<'a> = dyn FnMut(&BenchParams, usize, usize) -> BenchResult + 'a;
+
+/// Run probes of growing size to estimate the per-iteration cost and pick
+/// `iterations`/`warmup` for the measured phases.
+pub fn calibrate(params: &BenchParams) -> Result<CalibrationResult, BenchError> {
+    calibrate_with(params, &mut bench::bench_burst_sync)
 }
 
-pub fn calibrate(params: &BenchParams) -> CalibrationResult {
+/// [`calibrate`] with every probe run by `probe`, e.g. off-thread under a
+/// deadline. The first error it returns ends the calibration.
+pub fn calibrate_with(
+    params: &BenchParams,
+    probe: &mut Probe,
+) -> Result<CalibrationResult, BenchError> {
     // Exponentially scale up until a single probe takes >= 1 second.
     // This avoids hard-coded iteration counts that may overshoot on slow systems.
+    // Probes size the run from wall-clock time, so they need a predictable
+    // warmup regardless of the strategy used for the measured phases.
+    let params = &BenchParams {
+        warmup_strategy: WarmupStrategy::Fixed,
+        perf: false,
+        ..params.clone()
+    };
     let mut probe_n = PROBE_START_N;
     let mut elapsed_s;
     let mut samples;
@@ -26,7 +106,7 @@ pub fn calibrate(params: &BenchParams) -> CalibrationResult {
     loop {
         let warmup = (probe_n / 5).max(10);
         let t0 = std::time::Instant::now();
-        samples = bench::bench_burst_sync(params, probe_n, warmup);
+        samples = probe(params, probe_n, warmup)?.samples;
         elapsed_s = t0.elapsed().as_secs_f64();
 
         if elapsed_s >= PROBE_MIN_SECS || probe_n >= MAX_N {
@@ -37,7 +117,20 @@ pub fn calibrate(params: &BenchParams) -> CalibrationResult {
         probe_n = (probe_n as f64 * factor) as usize;
     }
 
-    let sr = StatResult::compute(&mut samples);
+    let mut sr = StatResult::compute(&mut samples, params.trim_frac);
+    let cv = |sr: &StatResult| sr.stddev / sr.trimmed_mean.max(1.0);
+    let mut attempts = 1;
+    while cv(&sr) > MAX_PROBE_CV && attempts < MAX_PROBE_ATTEMPTS {
+        attempts += 1;
+        let t0 = std::time::Instant::now();
+        let mut retry = probe(params, probe_n, (probe_n / 5).max(10))?.samples;
+        let retry_elapsed = t0.elapsed().as_secs_f64();
+        let retry_sr = StatResult::compute(&mut retry, params.trim_frac);
+        if cv(&retry_sr) < cv(&sr) {
+            sr = retry_sr;
+            elapsed_s = retry_elapsed;
+        }
+    }
     let mean = sr.trimmed_mean;
     let stddev = sr.stddev;
 
@@ -57,10 +150,230 @@ pub fn calibrate(params: &BenchParams) -> CalibrationResult {
 
     let warmup = ((n as f64 * WARMUP_RATIO) as usize).max(100);
 
-    CalibrationResult {
+    Ok(CalibrationResult {
         iterations: n,
         warmup,
         probe_mean_us: mean / 1000.0,
         probe_stddev_us: stddev / 1000.0,
+        timer_overhead_ns: timer_overhead_ns(params.timer),
+        attempts,
+        secs_per_iter: per_iter_s,
+    })
+}
+
+/// How `check_quiesced` ended.
+#[derive(Clone, Copy)]
+pub struct Quiesce {
+    /// Probes run, the two that agreed included.
+    pub probes: usize,
+    /// False when `MAX_QUIET_PROBES` ran out first.
+    pub settled: bool,
+}
+
+/// `--warmup-discard-verify`: run short probes until two in a row agree,
+/// the stddev/mean of their trimmed means at most `tolerance`, so nothing
+/// transient (a daemon waking up, a frequency ramp) lands in the measured
+/// rounds. Gives up after `MAX_QUIET_PROBES`.
+pub fn check_quiesced(
+    params: &BenchParams,
+    tolerance: f64,
+    probe: &mut Probe,
+) -> Result<Quiesce, BenchError> {
+    let params = &BenchParams {
+        warmup_strategy: WarmupStrategy::Fixed,
+        perf: false,
+        phase_duration: None,
+        ..params.clone()
+    };
+    let mut prev: Option<f64> = None;
+    for probes in 1..=MAX_QUIET_PROBES {
+        let mut samples = probe(params, QUIET_PROBE_N, QUIET_PROBE_N / 5)?.samples;
+        let mean = StatResult::compute(&mut samples, params.trim_frac).trimmed_mean;
+        if let Some(prev) = prev {
+            // Two values: stddev is |a - b| / sqrt(2).
+            let cov =
+                (mean - prev).abs() / std::f64::consts::SQRT_2 / ((mean + prev) / 2.0).max(1.0);
+            if cov <= tolerance {
+                return Ok(Quiesce {
+                    probes,
+                    settled: true,
+                });
+            }
+        }
+        prev = Some(mean);
+    }
+    Ok(Quiesce {
+        probes: MAX_QUIET_PROBES,
+        settled: false,
+    })
+}
+
+/// Settle delay for `--settle-ns auto`, sized from the shadow pin
+/// request-to-ack round trip of a short probe; `None` without shadows.
+///
+/// The pause after each batch trades sample rate for independence. Too
+/// short, and the next wakeup can reach a worker that has not blocked in
+/// read() yet, or a shadow still migrating, so it measures a handoff rather
+/// than a wakeup and the distribution grows a second mode. Too long, and
+/// idle time dominates the cadence: a phase collects fewer samples for its
+/// time. The ack round trip is the machine's cost of exactly that
+/// cross-CPU handoff, so a few of its p99s clear it on fast and slow
+/// machines alike.
+pub fn settle_ns(params: &BenchParams) -> Result<Option<u64>, BenchError> {
+    if params.shadows_per_worker == 0 {
+        return Ok(None);
+    }
+    let params = &BenchParams {
+        warmup_strategy: WarmupStrategy::Fixed,
+        perf: false,
+        measure_shadow: true,
+        settle_ns: bench::DEFAULT_SETTLE_NS,
+        ..params.clone()
+    };
+    let mut rtt =
+        bench::bench_burst_sync(params, SETTLE_PROBE_N, SETTLE_PROBE_N / 5)?.shadow_samples;
+    if rtt.is_empty() {
+        return Ok(None);
+    }
+    rtt.sort_unstable();
+    let p99 = stats::percentile(&rtt, 0.99);
+    let (lo, hi) = SETTLE_RANGE_NS;
+    Ok(Some(((p99 * SETTLE_FACTOR) as u64).clamp(lo, hi)))
+}
+
+// ---------------------------------------------------------------------------
+// Cache (`--cache-calibration`)
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    cpu_model: String,
+    /// Unix time the calibration was measured.
+    created: u64,
+    result: CalibrationResult,
+}
+
+/// `$XDG_CACHE_HOME/poc-bench/calibration.json`, falling back to
+/// `~/.cache`.
+fn cache_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))?;
+    Some(base.join("poc-bench").join("calibration.json"))
+}
+
+/// The parameters a cached calibration is keyed on: the ones the user sets,
+/// not values worked out on each run (a probed settle delay, the measured
+/// timer overhead, the CPU lists read from sysfs) that would miss the cache
+/// between otherwise identical runs.
+#[derive(Serialize)]
+struct CacheKeyParams<'a> {
+    n_workers: usize,
+    n_background: usize,
+    n_idle: usize,
+    shadows_per_worker: usize,
+    queue_depth: usize,
+    oversubscribe: f64,
+    trim_frac: f64,
+    worker_priorities: &'a [i32],
+    worker_nice: i32,
+    wakeup: WakeupMechanism,
+    bg_workload: BgWorkload,
+    timer: TimerSource,
+    subtract_overhead: bool,
+    work_ns: u64,
+    measure_shadow: bool,
+    /// `None` with `--settle-ns auto`.
+    settle_ns: Option<u64>,
+    cross_cpu: bool,
+    numa: NumaPlacement,
+    no_smt: bool,
+}
+
+impl<'a> CacheKeyParams<'a> {
+    fn new(p: &'a BenchParams) -> Self {
+        Self {
+            n_workers: p.n_workers,
+            n_background: p.n_background,
+            n_idle: p.n_idle,
+            shadows_per_worker: p.shadows_per_worker,
+            queue_depth: p.queue_depth,
+            oversubscribe: p.oversubscribe,
+            trim_frac: p.trim_frac,
+            worker_priorities: &p.worker_priorities,
+            worker_nice: p.worker_nice,
+            wakeup: p.wakeup,
+            bg_workload: p.bg_workload,
+            timer: p.timer,
+            subtract_overhead: p.subtract_overhead_ns > 0,
+            work_ns: p.work_ns,
+            measure_shadow: p.measure_shadow,
+            settle_ns: (!p.settle_auto).then_some(p.settle_ns),
+            cross_cpu: p.cross_cpu,
+            numa: p.numa,
+            no_smt: !p.smt_cpus.is_empty(),
+        }
+    }
+}
+
+/// FNV-1a over the CPU model, the user-set run parameters and the phase
+/// target, so any change to what the calibration depends on misses the
+/// cache.
+pub fn cache_key(cpu_model: &str, params: &BenchParams) -> String {
+    let params = serde_json::to_string(&CacheKeyParams::new(params)).unwrap_or_default();
+    let input = format!("{cpu_model}\0{params}\0{TARGET_PHASE_SECS}");
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in input.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn read_cache() -> BTreeMap<String, CacheEntry> {
+    cache_path()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// A cached calibration for this CPU and these parameters, if one is
+/// younger than `CACHE_MAX_AGE_SECS`.
+pub fn load_cached(cpu_model: &str, params: &BenchParams) -> Option<CalibrationResult> {
+    let entry = read_cache().remove(&cache_key(cpu_model, params))?;
+    let fresh = now_secs().saturating_sub(entry.created) < CACHE_MAX_AGE_SECS;
+    (fresh && entry.cpu_model == cpu_model).then_some(entry.result)
+}
+
+/// Store `result` in the cache. Entries for other CPU models (the machine
+/// changed, or the cache dir is shared) are dropped.
+pub fn store_cached(
+    cpu_model: &str,
+    params: &BenchParams,
+    result: &CalibrationResult,
+) -> Result<(), String> {
+    let path = cache_path().ok_or("no cache directory (XDG_CACHE_HOME and HOME unset)")?;
+    let mut cache = read_cache();
+    cache.retain(|_, e| e.cpu_model == cpu_model);
+    cache.insert(
+        cache_key(cpu_model, params),
+        CacheEntry {
+            cpu_model: cpu_model.to_string(),
+            created: now_secs(),
+            result: result.clone(),
+        },
+    );
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
     }
+    let json = serde_json::to_string_pretty(&cache).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("{}: {e}", path.display()))
 }
@@ -0,0 +1,20 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::stats;
+
+/// `--cdf`: the empirical CDF of each mode's measured samples, one
+/// `mode,percentile,latency_ns` row per point of
+/// [`stats::cdf_percentiles`]. `mode` is `on`/`off`, or the sysctl value
+/// with `--modes`.
+pub fn write(path: &Path, curves: &[(String, Vec<f64>)]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "mode,percentile,latency_ns")?;
+    for (mode, curve) in curves {
+        for (p, ns) in stats::cdf_percentiles().zip(curve) {
+            writeln!(out, "{},{},{:.1}", mode, p, ns)?;
+        }
+    }
+    out.flush()
+}
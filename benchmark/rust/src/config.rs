@@ -0,0 +1,39 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// Run settings loaded from `--config`. Every field is optional; anything
+/// given explicitly on the command line takes precedence over the file.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FileConfig {
+    pub iterations: Option<usize>,
+    pub threads: Option<usize>,
+    pub background: Option<usize>,
+    pub rounds: Option<usize>,
+    pub no_compare: Option<bool>,
+}
+
+impl FileConfig {
+    /// Load a config file, picking the deserializer from the extension:
+    /// `.toml` for TOML, `.yaml`/`.yml` for YAML.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("read({}): {e}", path.display()))?;
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("toml") => toml::from_str(&text).map_err(|e| format!("{}: {e}", path.display())),
+            Some("yaml") | Some("yml") => {
+                serde_yaml::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))
+            }
+            _ => Err(format!(
+                "{}: unknown config format (expected .toml, .yaml or .yml)",
+                path.display()
+            )),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// cpuidle residency counters from sysfs.
+//
+// Each `/sys/devices/system/cpu/cpuN/cpuidle/stateM/` has cumulative
+// `time` (μs spent in the state) and `usage` (entries). A snapshot sums
+// them per state name over all online CPUs; the difference of two
+// snapshots is the residency for the phase between them.
+
+use std::fs;
+use std::time::Instant;
+
+pub struct IdleSnapshot {
+    at: Instant,
+    states: Vec<IdleState>,
+}
+
+#[derive(Clone)]
+pub struct IdleState {
+    pub name: String,
+    pub time_us: u64,
+    pub entries: u64,
+}
+
+/// Idle residency accumulated over one or more phases.
+#[derive(Clone, Default)]
+pub struct IdleResidency {
+    pub states: Vec<IdleState>,
+    /// Wall time covered, summed over phases (μs).
+    pub wall_us: u64,
+}
+
+impl IdleSnapshot {
+    /// `None` when the kernel exposes no cpuidle states (no cpuidle driver,
+    /// some VMs and containers).
+    pub fn take(ncpus: usize) -> Option<Self> {
+        let at = Instant::now();
+        let mut states: Vec<IdleState> = Vec::new();
+        for cpu in 0..ncpus {
+            for idx in 0.. {
+                let dir = format!("/sys/devices/system/cpu/cpu{cpu}/cpuidle/state{idx}");
+                let Ok(name) = fs::read_to_string(format!("{dir}/name")) else {
+                    break;
+                };
+                let read = |f: &str| {
+                    fs::read_to_string(format!("{dir}/{f}"))
+                        .ok()
+                        .and_then(|s| s.trim().parse::<u64>().ok())
+                        .unwrap_or(0)
+                };
+                let (time_us, entries) = (read("time"), read("usage"));
+                let name = name.trim();
+                match states.iter_mut().find(|s| s.name == name) {
+                    Some(s) => {
+                        s.time_us += time_us;
+                        s.entries += entries;
+                    }
+                    None => states.push(IdleState {
+                        name: name.to_string(),
+                        time_us,
+                        entries,
+                    }),
+                }
+            }
+        }
+        if states.is_empty() {
+            None
+        } else {
+            Some(Self { at, states })
+        }
+    }
+
+    pub fn since(&self, earlier: &IdleSnapshot) -> IdleResidency {
+        let states = self
+            .states
+            .iter()
+            .map(|s| {
+                let prev = earlier.states.iter().find(|p| p.name == s.name);
+                IdleState {
+                    name: s.name.clone(),
+                    time_us: s.time_us.saturating_sub(prev.map_or(0, |p| p.time_us)),
+                    entries: s.entries.saturating_sub(prev.map_or(0, |p| p.entries)),
+                }
+            })
+            .collect();
+        IdleResidency {
+            states,
+            wall_us: self.at.duration_since(earlier.at).as_micros() as u64,
+        }
+    }
+}
+
+impl IdleResidency {
+    pub fn add(&mut self, other: &IdleResidency) {
+        for s in &other.states {
+            match self.states.iter_mut().find(|m| m.name == s.name) {
+                Some(m) => {
+                    m.time_us += s.time_us;
+                    m.entries += s.entries;
+                }
+                None => self.states.push(s.clone()),
+            }
+        }
+        self.wall_us += other.wall_us;
+    }
+}
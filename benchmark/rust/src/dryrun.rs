@@ -0,0 +1,261 @@
+// `--dry-run`: the plan main works out before a run, printed instead of
+// carried out.
+//
+// Nothing here writes the POC sysctl, locks memory, pins the governor or
+// holds /dev/cpu_dma_latency; the calibration probe is the only part that
+// starts benchmark threads.
+
+use std::time::Duration;
+
+use poc_bench::calibrate::{self, CalibrationResult};
+use poc_bench::system::{self, BenchParams, PocSupport, SystemInfo};
+
+use crate::ui::format_secs;
+
+/// How many phases the run would measure, and how long each is.
+pub struct Schedule {
+    pub compare: bool,
+    /// Phases per round: one per compared mode (ON and OFF by default).
+    pub modes_per_round: usize,
+    pub discard_rounds: usize,
+    /// Fewest and most measured rounds; equal unless adaptive.
+    pub rounds: (usize, usize),
+    pub depths: Vec<usize>,
+    /// 2 with `--cstate-compare`, else 1.
+    pub passes: usize,
+    /// `--iterations`, or 0 to take the calibrated count.
+    pub iterations: usize,
+    pub warmup: Option<usize>,
+    /// `--settle-ns auto`: the pause is only probed right before a run.
+    pub settle_auto: bool,
+    /// `--warmup-discard-verify` tolerance (%).
+    pub verify: Option<f64>,
+}
+
+impl Schedule {
+    fn measured_phases(&self, rounds: usize) -> usize {
+        let per_pass = if self.compare {
+            self.modes_per_round * rounds
+        } else {
+            1
+        };
+        self.passes * self.depths.len() * per_pass
+    }
+
+    fn discard_phases(&self) -> usize {
+        if self.compare {
+            self.passes * self.depths.len() * self.modes_per_round * self.discard_rounds
+        } else {
+            0
+        }
+    }
+
+    /// Iterations and warmup per measured phase, as the run would settle
+    /// them.
+    fn phase_size(&self, cal: Option<&CalibrationResult>) -> Option<(usize, usize)> {
+        if self.iterations > 0 {
+            let warmup = self.warmup.unwrap_or((self.iterations / 5).max(100));
+            return Some((self.iterations, warmup));
+        }
+        cal.map(|c| (c.iterations, self.warmup.unwrap_or(c.warmup)))
+    }
+
+    /// Wall time of the whole run for the fewest and most rounds, or `None`
+    /// without a duration or a probe to go by.
+    fn estimate(
+        &self,
+        params: &BenchParams,
+        cal: Option<&CalibrationResult>,
+    ) -> Option<(Duration, Duration)> {
+        let (phase, discard) = match params.phase_duration {
+            Some(d) => (d, d / 5),
+            None => {
+                let spi = cal.map(|c| c.secs_per_iter).filter(|&s| s > 0.0)?;
+                let (n, w) = self.phase_size(cal)?;
+                let discard = (n / 5).max(500) + (w / 5).max(100);
+                (
+                    Duration::from_secs_f64((n + w) as f64 * spi),
+                    Duration::from_secs_f64(discard as f64 * spi),
+                )
+            }
+        };
+        let total = |rounds| {
+            phase * self.measured_phases(rounds) as u32 + discard * self.discard_phases() as u32
+        };
+        Some((total(self.rounds.0), total(self.rounds.1)))
+    }
+}
+
+pub fn print_plan(
+    sysinfo: &SystemInfo,
+    support: &PocSupport,
+    params: &BenchParams,
+    schedule: &Schedule,
+    probe: Option<&Result<CalibrationResult, String>>,
+) {
+    println!("Dry run: no sysctl written, nothing measured.");
+    println!();
+    println!("System:");
+    println!("  CPU:       {}", sysinfo.cpu_model);
+    println!(
+        "  CPUs:      {} online, {} physical cores",
+        sysinfo.ncpus, sysinfo.physical_cores
+    );
+    println!("  SMT:       {}", sysinfo.smt_summary());
+    println!("  Kernel:    {}", sysinfo.kernel);
+    if !sysinfo.isolated.is_empty() {
+        println!(
+            "  Isolated:  {}",
+            system::format_cpu_list(&sysinfo.isolated)
+        );
+    }
+    if sysinfo.numa_nodes.len() > 1 {
+        println!("  NUMA:      {} nodes", sysinfo.numa_nodes.len());
+    }
+
+    println!();
+    println!("POC sysctl:");
+    let path = system::poc_sysctl_path().map_or("-".into(), |p| p.display().to_string());
+    match support {
+        PocSupport::Present => println!(
+            "  {} = {} (writable)",
+            path,
+            system::poc_sysctl_read().unwrap_or(-1)
+        ),
+        PocSupport::PresentReadOnly { reason } => println!(
+            "  {} = {} (read-only: {})",
+            path,
+            system::poc_sysctl_read().unwrap_or(-1),
+            reason
+        ),
+        PocSupport::AbsentNoSysctl => println!("  not found: no POC selector in this kernel"),
+    }
+
+    println!();
+    println!("Placement:");
+    print_placement(sysinfo.ncpus, params);
+
+    println!();
+    println!("Calibration probe:");
+    match probe {
+        None => println!("  skipped: --duration sets the phase length"),
+        Some(Ok(c)) => println!(
+            "  {} iterations/phase, {} warmup, probe mean {:.2} us (stddev {:.2}), {:.1} us/iteration",
+            c.iterations,
+            c.warmup,
+            c.probe_mean_us,
+            c.probe_stddev_us,
+            c.secs_per_iter * 1e6
+        ),
+        Some(Err(e)) => println!("  failed: {}", e),
+    }
+    let cal = probe.and_then(|p| p.as_ref().ok());
+
+    println!();
+    println!("Schedule:");
+    let rounds = match schedule.rounds {
+        (min, max) if min == max => format!("{}", max),
+        (min, max) => format!("{}-{} (adaptive)", min, max),
+    };
+    if schedule.compare {
+        println!(
+            "  comparison: {} modes x {} rounds, {} discard round(s)",
+            schedule.modes_per_round, rounds, schedule.discard_rounds
+        );
+    } else {
+        println!("  single run (no comparison)");
+    }
+    if schedule.depths.len() > 1 || schedule.depths[0] > 1 {
+        println!(
+            "  queue depths: {}",
+            schedule
+                .depths
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+    }
+    if schedule.passes > 1 {
+        println!("  C-states: pinned pass, then deep pass");
+    }
+    match (params.phase_duration, schedule.phase_size(cal)) {
+        (Some(d), _) => println!("  phase: {:.1}s each", d.as_secs_f64()),
+        (None, Some((n, w))) => println!("  phase: {} iterations + {} warmup", n, w),
+        (None, None) => println!("  phase: unknown (no calibration)"),
+    }
+    if schedule.settle_auto {
+        println!("  settle: auto, probed before the run");
+    } else {
+        println!("  settle: {} ns after each batch", params.settle_ns);
+    }
+    if let Some(pct) = schedule.verify {
+        println!(
+            "  quiet check: up to {} probes per measurement, {}% tolerance",
+            calibrate::MAX_QUIET_PROBES,
+            pct
+        );
+    }
+    println!(
+        "  phases: {} measured, {} discard",
+        schedule.measured_phases(schedule.rounds.1),
+        schedule.discard_phases()
+    );
+    match schedule.estimate(params, cal) {
+        Some((lo, hi)) if lo == hi => {
+            println!("  estimated runtime: {}", format_secs(hi.as_secs_f64()))
+        }
+        Some((lo, hi)) => println!(
+            "  estimated runtime: {} to {}",
+            format_secs(lo.as_secs_f64()),
+            format_secs(hi.as_secs_f64())
+        ),
+        None => println!("  estimated runtime: unknown"),
+    }
+
+    println!();
+    println!("Parameters:");
+    match serde_json::to_string_pretty(params) {
+        Ok(json) => {
+            for line in json.lines() {
+                println!("  {}", line);
+            }
+        }
+        Err(e) => println!("  (unavailable: {})", e),
+    }
+}
+
+/// Where a benchmark phase pins each thread (see `bench_burst_inner`).
+fn print_placement(ncpus: usize, params: &BenchParams) {
+    let hk = params.housekeeping_cpus(ncpus);
+    if params.cross_cpu {
+        println!("  dispatcher:   CPU {}, then off the workers' cores", hk[0]);
+    } else {
+        println!("  dispatcher:   CPU {}", hk[0]);
+    }
+    let n_background = params.n_background.min(ncpus.saturating_sub(1));
+    if n_background > 0 {
+        let bg: Vec<usize> = (0..n_background).map(|i| hk[(i + 1) % hk.len()]).collect();
+        println!(
+            "  background:   {} x {} on CPU {}",
+            n_background,
+            params.bg_workload.label(),
+            bg.iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+    }
+    for w in 0..params.n_workers {
+        let cpus = params
+            .worker_cpus(w, hk[0])
+            .map_or("any".into(), |c| system::format_cpu_list(&c));
+        println!("  worker {:<5} CPUs {}", w, cpus);
+    }
+    if params.shadows_per_worker > 0 {
+        println!(
+            "  shadows:      {} per worker, on their worker's CPUs",
+            params.shadows_per_worker
+        );
+    }
+}
@@ -0,0 +1,136 @@
+// Restoration of everything the benchmark changes on the machine.
+//
+// Each guard undoes its change in `Drop`, so the terminal, the POC knob,
+// the C0 request and the cpufreq governors are put back whether `main`
+// returns normally or unwinds from a panic. Declared in `main` in the
+// order they are taken; drop order is the reverse.
+
+use std::io;
+
+use crossterm::cursor::Show;
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::ExecutableCommand;
+
+use poc_bench::system;
+
+/// Raw mode, the alternate screen and mouse capture for the TUI.
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn enter() -> Self {
+        enable_raw_mode().expect("failed to enable raw mode");
+        io::stdout()
+            .execute(EnterAlternateScreen)
+            .expect("failed to enter alternate screen");
+        io::stdout().execute(EnableMouseCapture).ok();
+        TerminalGuard
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        io::stdout().execute(DisableMouseCapture).ok();
+        disable_raw_mode().ok();
+        io::stdout().execute(LeaveAlternateScreen).ok();
+        io::stdout().execute(Show).ok();
+    }
+}
+
+/// Writes the POC knob's startup value back; a no-op when the knob was
+/// not writable (nothing was changed).
+pub struct SysctlGuard {
+    orig: Option<i32>,
+}
+
+impl SysctlGuard {
+    pub fn new(orig: Option<i32>) -> Self {
+        Self {
+            orig: orig.filter(|&v| v >= 0),
+        }
+    }
+}
+
+impl Drop for SysctlGuard {
+    fn drop(&mut self) {
+        if let Some(v) = self.orig {
+            system::poc_sysctl_write(v).ok();
+        }
+    }
+}
+
+/// The /dev/cpu_dma_latency request that keeps CPUs in C0 while held.
+pub struct DmaLatencyGuard {
+    fd: i32,
+}
+
+impl DmaLatencyGuard {
+    /// Take the request; not held if the device can't be opened.
+    pub fn open() -> Self {
+        Self {
+            fd: system::dma_latency_open(),
+        }
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.fd >= 0
+    }
+
+    /// Drop the request early so idle CPUs may enter deep C-states.
+    pub fn release(&mut self) {
+        system::dma_latency_close(self.fd);
+        self.fd = -1;
+    }
+}
+
+impl Drop for DmaLatencyGuard {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Governors replaced by `--pin-governor`, switched back on drop.
+pub struct GovernorGuard {
+    saved: Vec<(usize, String)>,
+}
+
+impl GovernorGuard {
+    pub fn new(saved: Vec<(usize, String)>) -> Self {
+        Self { saved }
+    }
+}
+
+impl Drop for GovernorGuard {
+    fn drop(&mut self) {
+        system::governor_restore(&self.saved);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::panic;
+
+    use super::*;
+
+    #[test]
+    fn sysctl_guard_restores_after_panic() {
+        let path = std::env::temp_dir().join(format!("poc-bench-sysctl-{}", std::process::id()));
+        fs::write(&path, "1\n").unwrap();
+        system::set_sysctl_path(path.clone());
+        assert_eq!(system::poc_sysctl_path(), Some(path.as_path()));
+
+        let result = panic::catch_unwind(|| {
+            let _guard = SysctlGuard::new(system::poc_sysctl_read());
+            system::poc_sysctl_write(0).unwrap();
+            assert_eq!(system::poc_sysctl_read(), Some(0));
+            panic!("benchmark failed mid-run");
+        });
+
+        assert!(result.is_err());
+        assert_eq!(system::poc_sysctl_read(), Some(1));
+        fs::remove_file(&path).ok();
+    }
+}
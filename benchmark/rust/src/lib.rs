@@ -0,0 +1,38 @@
+//! Wakeup-latency measurement core of `poc-bench`, usable without the TUI.
+//!
+//! A run pins a SCHED_FIFO dispatcher to CPU 0 that wakes worker threads
+//! through an eventfd, a futex or a pipe (`BenchParams::wakeup`) and
+//! records, per wakeup, the time from the post to the worker returning from
+//! its wait. [`run_benchmark`] is the simplest entry point;
+//! [`bench::bench_burst_async`] adds progress reporting.
+//!
+//! ```no_run
+//! use poc_bench::system::{BenchParams, SystemInfo};
+//! use poc_bench::stats::StatResult;
+//!
+//! let sys = SystemInfo::detect();
+//! let params = BenchParams::with_overrides(sys.ncpus, sys.physical_cores, None, None).unwrap();
+//! let mut samples = poc_bench::run_benchmark(&params, 10_000, 2_000).unwrap();
+//! let stats = StatResult::compute(&mut samples, params.trim_frac);
+//! assert!(stats.p99 < 100_000.0, "p99 above 100μs");
+//! ```
+
+pub mod bench;
+pub mod calibrate;
+pub mod cpuidle;
+pub mod perf;
+pub mod rng;
+pub mod schedstat;
+pub mod stats;
+pub mod system;
+pub mod timer;
+
+/// Measure `iterations` wakeups per worker after `warmup` discarded ones
+/// and return every latency in ns, worker by worker. Blocks until done.
+pub fn run_benchmark(
+    params: &system::BenchParams,
+    iterations: usize,
+    warmup: usize,
+) -> Result<Vec<u64>, bench::BenchError> {
+    bench::bench_burst_sync(params, iterations, warmup).map(|run| run.samples)
+}
@@ -1,35 +1,78 @@
-mod bench;
-mod calibrate;
-mod stats;
-mod system;
+mod baseline;
+mod cdf;
+mod config;
+mod dryrun;
+mod guard;
+mod progress;
+mod rawcsv;
+mod report;
+mod strict;
 mod ui;
+mod watchlog;
 
-use std::io;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use clap::Parser;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
-use crossterm::terminal::{
-    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind,
 };
-use crossterm::ExecutableCommand;
 use ratatui::backend::CrosstermBackend;
+use ratatui::layout::Rect;
 use ratatui::Terminal;
 
-use crate::stats::{Histogram, StatResult};
-use crate::system::{BenchParams, SystemInfo};
-use crate::ui::{App, Phase};
+use poc_bench::{bench, calibrate, cpuidle, perf, rng, schedstat, stats, system, timer};
+
+use crate::baseline::Baseline;
+use crate::bench::BenchRun;
+use crate::config::FileConfig;
+use crate::guard::{DmaLatencyGuard, GovernorGuard, SysctlGuard, TerminalGuard};
+use crate::progress::{ProgressFile, ProgressJson, ProgressLog};
+use crate::rawcsv::RawCsv;
+use crate::report::Metrics;
+use crate::stats::{Bucketing, Histogram, StatResult};
+use crate::system::{
+    BenchParams, BgWorkload, NumaPlacement, PocSupport, SystemInfo, ThreadClamp, TimerSource,
+    WakeupMechanism, WarmupStrategy,
+};
+use crate::ui::{
+    App, CStateCompare, DepthResult, ModeResult, Phase, PowerEstimate, PriorityClass, Theme,
+    WatchState,
+};
+use crate::watchlog::WatchLog;
 
 const DEFAULT_ROUNDS: usize = 4;
+/// Fewest measured iterations `--warmup` may leave per phase.
+const MIN_MEASURED_ITERATIONS: usize = 100;
+/// Warmup iterations per phase with `--duration` (no calibration to size it).
+const DURATION_WARMUP: usize = 1000;
+
+/// Exit statuses beyond 0 and 1, for scripts gating on a run.
+const EXIT_SINGLE_RUN: u8 = 2;
+const EXIT_ABORTED: u8 = 3;
+const EXIT_ERROR: u8 = 4;
+const EXIT_STATUS_HELP: &str = "\
+Exit status:
+  0  the ON/OFF (or --modes) comparison completed
+  1  invalid arguments, a failed --strict check or a --baseline regression
+  2  only a single run was measured (no writable POC sysctl, or --no-compare)
+  3  aborted with q or a signal before the end
+  4  the benchmark failed (too few CPUs, eventfd, calibration timeout, ...)";
 
 // ---------------------------------------------------------------------------
-// Global quit flag — set by SIGINT handler or key events
+// Global quit flag — set by the SIGINT/SIGTERM/SIGHUP handler or key events
 // ---------------------------------------------------------------------------
 
 static QUIT: AtomicBool = AtomicBool::new(false);
 
-extern "C" fn handle_sigint(_: libc::c_int) {
+// Only flags the quit; the normal exit path then restores the terminal and
+// the sysctl, neither of which is async-signal-safe to do here.
+extern "C" fn handle_quit_signal(_: libc::c_int) {
     QUIT.store(true, Ordering::Relaxed);
 }
 
@@ -37,6 +80,24 @@ fn quitting() -> bool {
     QUIT.load(Ordering::Relaxed)
 }
 
+/// Stop scheduling further phases: either the user quit or a phase failed.
+fn stopping(app: &App) -> bool {
+    quitting() || app.error.is_some()
+}
+
+fn fail(app: &mut App, msg: String) {
+    app.phase = Phase::Error(msg.clone());
+    app.error = Some(msg);
+}
+
+fn is_pause_event(ev: &Event) -> bool {
+    matches!(ev, Event::Key(key) if key.kind == KeyEventKind::Press && key.code == KeyCode::Char(' '))
+}
+
+fn is_restart_event(ev: &Event) -> bool {
+    matches!(ev, Event::Key(key) if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('r'))
+}
+
 fn is_quit_event(ev: &Event) -> bool {
     match ev {
         Event::Key(key) if key.kind == KeyEventKind::Press => {
@@ -47,6 +108,117 @@ fn is_quit_event(ev: &Event) -> bool {
     }
 }
 
+/// Everything that observes run state: the TUI or, when headless, the
+/// stderr progress log, plus the optional `--progress-file` and `--raw-csv`.
+struct Screen {
+    terminal: Option<Terminal<CrosstermBackend<io::Stdout>>>,
+    log: Option<ProgressLog>,
+    progress_file: Option<ProgressFile>,
+    progress_json: Option<ProgressJson>,
+    raw_csv: Option<RawCsv>,
+    watch_log: Option<WatchLog>,
+}
+
+impl Screen {
+    fn draw(&mut self, app: &App) {
+        if let Some(t) = self.terminal.as_mut() {
+            t.draw(|f| ui::draw(f, app)).ok();
+        }
+        if let Some(log) = self.log.as_mut() {
+            log.update(app);
+        }
+        if let Some(pf) = self.progress_file.as_mut() {
+            pf.update(app);
+        }
+        if let Some(pj) = self.progress_json.as_mut() {
+            pj.update(app);
+        }
+    }
+
+    /// Repaint from scratch after a resize: the previous frame's buffer no
+    /// longer matches the screen, so a diffing draw would leave debris.
+    fn redraw(&mut self, app: &App) {
+        if let Some(t) = self.terminal.as_mut() {
+            t.autoresize().ok();
+            t.clear().ok();
+        }
+        self.draw(app);
+    }
+
+    /// Append a measured phase to `--raw-csv`; a write error ends the run.
+    fn record_samples(&mut self, app: &mut App, round: usize, poc: i32, run: &BenchRun) {
+        if let Some(csv) = self.raw_csv.as_mut() {
+            if let Err(e) = csv.write_run(round, poc, run) {
+                fail(app, format!("raw csv: {}", e));
+            }
+        }
+    }
+}
+
+/// Clicking a histogram row selects that bucket for the footer status line;
+/// clicking anywhere else (or Esc) clears the selection. `s` toggles the
+/// sparkline in place of the histogram and `v` the per-round p99 view, `c`
+/// counts instead of percentages in its bars, `d` makes them cumulative,
+/// and `up`/`down` (`k`/`j`) scroll a clipped summary.
+fn handle_ui_event(screen: &Screen, app: &mut App, ev: &Event) {
+    match ev {
+        Event::Key(key)
+            if key.kind == KeyEventKind::Press
+                && matches!(
+                    key.code,
+                    KeyCode::Up | KeyCode::Down | KeyCode::Char('k') | KeyCode::Char('j')
+                ) =>
+        {
+            if let Some(Ok(size)) = screen.terminal.as_ref().map(|t| t.size()) {
+                let area = Rect::new(0, 0, size.width, size.height);
+                // Clamp first: a resize may have left the offset past the end.
+                let max = ui::summary_max_scroll(area, app);
+                let scroll = app.summary_scroll.min(max);
+                app.summary_scroll = if matches!(key.code, KeyCode::Up | KeyCode::Char('k')) {
+                    scroll.saturating_sub(1)
+                } else {
+                    (scroll + 1).min(max)
+                };
+            }
+        }
+        Event::Key(key) if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('s') => {
+            app.show_sparkline = !app.show_sparkline;
+            app.show_rounds = false;
+            app.selected_bucket = None;
+        }
+        Event::Key(key) if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('v') => {
+            app.show_rounds = !app.show_rounds;
+            app.show_sparkline = false;
+            app.selected_bucket = None;
+        }
+        Event::Key(key)
+            if key.kind == KeyEventKind::Press
+                && key.code == KeyCode::Char('c')
+                && !key.modifiers.contains(KeyModifiers::CONTROL) =>
+        {
+            app.hist_counts = !app.hist_counts;
+        }
+        Event::Key(key) if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('d') => {
+            app.hist_cdf = !app.hist_cdf;
+        }
+        Event::Mouse(m)
+            if m.kind == MouseEventKind::Down(MouseButton::Left)
+                && !app.show_sparkline
+                && !app.show_rounds =>
+        {
+            if let Some(Ok(size)) = screen.terminal.as_ref().map(|t| t.size()) {
+                let area = Rect::new(0, 0, size.width, size.height);
+                let n = app.bucketing.num_buckets();
+                app.selected_bucket = ui::bucket_at(area, m.column, m.row, n);
+            }
+        }
+        Event::Key(key) if key.kind == KeyEventKind::Press && key.code == KeyCode::Esc => {
+            app.selected_bucket = None;
+        }
+        _ => {}
+    }
+}
+
 // ---------------------------------------------------------------------------
 // CLI
 // ---------------------------------------------------------------------------
@@ -56,17 +228,152 @@ fn default_threads() -> usize {
 }
 
 fn default_background() -> usize {
-    let ncpus = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) as usize };
+    let ncpus = system::online_cpus();
     (ncpus as f64).log2().round() as usize
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum WarmupDiscard {
+    /// Drop a fixed number of warmup iterations
+    Fixed,
+    /// Drop iterations until the sliding-window p50 settles
+    Stable,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Wakeup {
+    /// eventfd write/read (EFD_SEMAPHORE)
+    Eventfd,
+    /// FUTEX_WAKE/FUTEX_WAIT on a per-worker counter; absolute numbers
+    /// differ from eventfd
+    Futex,
+    /// One-byte write/read on a pipe(2)
+    Pipe,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum BgLoad {
+    /// Tight spin loop: CPU contention only
+    Spin,
+    /// Stream through a 64 MiB buffer: cache and memory-bandwidth pressure
+    Memory,
+    /// Back-to-back getuid(): syscall-heavy neighbors
+    Syscall,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ThemeName {
+    Default,
+    Solarized,
+    /// Bright colors only, no dark gray
+    Highcontrast,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Timer {
+    /// clock_gettime(CLOCK_MONOTONIC)
+    Clock,
+    /// rdtsc, calibrated against CLOCK_MONOTONIC at startup; needs an
+    /// invariant TSC, otherwise falls back to clock
+    Tsc,
+}
+
+/// `--settle-ns`: a fixed pause, or one sized from a probe.
+#[derive(Clone, Copy, PartialEq)]
+enum Settle {
+    Ns(u64),
+    Auto,
+}
+
+impl Settle {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "auto" => Ok(Settle::Auto),
+            _ => s
+                .parse()
+                .map(Settle::Ns)
+                .map_err(|_| "expected a number of ns or auto".into()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum OutputFormat {
+    /// Interactive TUI, then a plain-text summary
+    Text,
+    /// No TUI; print the results as JSON on stdout
+    Json,
+    /// No TUI; print the results as a GitHub-flavored Markdown table
+    Markdown,
+    /// No TUI; print Prometheus gauges for the node_exporter textfile
+    /// collector
+    Prometheus,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum RegressionMetric {
+    Mean,
+    Trimmed,
+    P50,
+    P90,
+    P95,
+    P99,
+    P999,
+}
+
+impl RegressionMetric {
+    fn name(self) -> &'static str {
+        match self {
+            RegressionMetric::Mean => "mean",
+            RegressionMetric::Trimmed => "trimmed mean",
+            RegressionMetric::P50 => "p50",
+            RegressionMetric::P90 => "p90",
+            RegressionMetric::P95 => "p95",
+            RegressionMetric::P99 => "p99",
+            RegressionMetric::P999 => "p99.9",
+        }
+    }
+
+    fn value(self, m: &Baseline) -> f64 {
+        match self {
+            RegressionMetric::Mean => m.mean,
+            RegressionMetric::Trimmed => m.trimmed_mean,
+            RegressionMetric::P50 => m.p50,
+            RegressionMetric::P90 => m.p90,
+            RegressionMetric::P95 => m.p95,
+            RegressionMetric::P99 => m.p99,
+            RegressionMetric::P999 => m.p999,
+        }
+    }
+}
+
 #[derive(Parser)]
-#[command(name = "poc-bench", about = "POC Selector Benchmark with TUI")]
+#[command(
+    name = "poc-bench",
+    about = "POC Selector Benchmark with TUI",
+    after_help = EXIT_STATUS_HELP
+)]
 struct Cli {
     /// Override iteration count (0 = auto-calibrate)
     #[arg(short, long, default_value_t = 0)]
     iterations: usize,
 
+    /// Warmup iterations per phase, overriding iterations/5 (with
+    /// --iterations) or the calibrated count
+    #[arg(long, value_name = "N")]
+    warmup: Option<usize>,
+
+    /// Measured iterations per round instead of the calibrated count, for
+    /// more and shorter rounds with --rounds. Calibration still runs and
+    /// sizes the discard rounds; warmup defaults to N/5
+    #[arg(long, value_name = "N", conflicts_with_all = ["iterations", "duration"])]
+    iterations_per_round: Option<usize>,
+
+    /// Percent of samples dropped from each end for the trimmed mean
+    /// (0 = plain mean)
+    #[arg(long, value_name = "PERCENT", default_value_t = 1.0)]
+    trim: f64,
+
     /// Worker thread count
     #[arg(short = 't', long, default_value_t = default_threads())]
     threads: usize,
@@ -79,221 +386,1384 @@ struct Cli {
     #[arg(short, long, default_value_t = DEFAULT_ROUNDS)]
     rounds: usize,
 
+    /// Measure each phase for this many seconds instead of a fixed
+    /// iteration count (skips calibration)
+    #[arg(long, value_name = "SECONDS")]
+    duration: Option<f64>,
+
+    /// Keep running ON/OFF cycles until quit, one every SECONDS, showing
+    /// the last --watch-window cycles next to the all-time aggregate
+    #[arg(long, value_name = "SECONDS")]
+    watch: Option<u64>,
+
+    /// Cycles in the rolling window of --watch
+    #[arg(long, value_name = "K", default_value_t = 10)]
+    watch_window: usize,
+
+    /// Append each --watch cycle's results to this CSV
+    #[arg(long, value_name = "PATH")]
+    watch_log: Option<PathBuf>,
+
+    /// Adaptive rounds: instead of --rounds, keep running rounds until the
+    /// 95% CI of the per-round ON/OFF change is within ± PERCENT
+    #[arg(long, value_name = "PERCENT")]
+    rounds_precision: Option<f64>,
+
+    /// Fewest rounds an adaptive run does
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    rounds_min: usize,
+
+    /// Most rounds an adaptive run does, precise enough or not
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    rounds_max: usize,
+
+    /// Unmeasured ON/OFF cycles run before the measured rounds
+    #[arg(long, default_value_t = 1)]
+    discard_rounds: usize,
+
+    /// After the discard rounds, run short probes until two in a row agree
+    /// within --verify-tolerance, so the measured rounds start on a quiet
+    /// system
+    #[arg(long)]
+    warmup_discard_verify: bool,
+
+    /// Largest stddev/mean (%) of two probes' trimmed means that counts as
+    /// agreeing, for --warmup-discard-verify
+    #[arg(long, value_name = "PERCENT", default_value_t = 5.0)]
+    verify_tolerance: f64,
+
     /// Skip POC ON/OFF comparison
     #[arg(long)]
     no_compare: bool,
+
+    /// Run everything twice: with C-states pinned to C0, then with deep
+    /// idle states allowed, and report the wakeup penalty per mode
+    #[arg(long)]
+    cstate_compare: bool,
+
+    /// Wakeups queued per worker before waiting; a comma-separated list
+    /// (e.g. 1,2,4,8) sweeps the depths and reports latency per depth
+    #[arg(long, value_delimiter = ',', default_value = "1")]
+    queue_depth: Vec<usize>,
+
+    /// Compare these POC sysctl values (e.g. 0,1,2) instead of ON/OFF;
+    /// changes are reported against the first value
+    #[arg(long, value_delimiter = ',', value_name = "LIST")]
+    modes: Vec<i32>,
+
+    /// NUMA placement of the workers and their shadows: pack (the
+    /// dispatcher's node), spread (round-robin across nodes) or node=N
+    #[arg(long, value_parser = NumaPlacement::parse, default_value = "pack")]
+    numa: NumaPlacement,
+
+    /// Keep workers and their shadows on one hardware thread per physical
+    /// core, so no wakeup crosses SMT siblings
+    #[arg(long)]
+    no_smt: bool,
+
+    /// Busy-spin this many μs in each worker after every wakeup instead of
+    /// the brief fixed loop. Longer payloads keep workers runnable longer,
+    /// which feeds the scheduler's load tracking differently; the payload
+    /// runs after the wakeup timestamp and is never part of the latency
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    work_us: u64,
+
+    /// Also time each shadow's pin request until its ack and report the
+    /// round trips as a separate series (no effect with --no-shadows)
+    #[arg(long)]
+    measure_shadow: bool,
+
+    /// Measure cross-CPU wakeups only: before every batch the dispatcher
+    /// moves off the core (and if it can, the package) each worker last
+    /// ran on, so no wakeup is sent from the CPU it lands on
+    #[arg(long)]
+    cross_cpu: bool,
+
+    /// Dispatcher pause after each batch of wakeups, so workers are back
+    /// in read() and shadows settled before the next one. Shorter gives
+    /// more samples per second but risks timing a worker that has not
+    /// blocked yet; longer only wastes time. auto sizes it from the shadow
+    /// ack round trip of a short probe before the run
+    #[arg(long, value_parser = Settle::parse, value_name = "NS|auto", default_value = "10000")]
+    settle_ns: Settle,
+
+    /// Timestamp source for the wakeup latencies
+    #[arg(long, value_enum, default_value_t = Timer::Clock)]
+    timer: Timer,
+
+    /// Histogram buckets: log2, or linear:<width_us>:<count> for narrow
+    /// distributions (e.g. linear:1:16)
+    #[arg(long, value_parser = Bucketing::parse, default_value = "log2")]
+    hist: Bucketing,
+
+    /// Label histogram bars with sample counts instead of percentages
+    /// (toggle with `c` in the TUI)
+    #[arg(long)]
+    hist_counts: bool,
+
+    /// Add the geometric mean to the summary, a central value one slow
+    /// round can't dominate
+    #[arg(long)]
+    show_geomean: bool,
+
+    /// Kernel path used to wake the workers
+    #[arg(long, value_enum, default_value_t = Wakeup::Eventfd)]
+    wakeup: Wakeup,
+
+    /// What the background threads run
+    #[arg(long, value_enum, default_value_t = BgLoad::Spin)]
+    bg_workload: BgLoad,
+
+    /// How warmup iterations are discarded before recording
+    #[arg(long, value_enum, default_value_t = WarmupDiscard::Fixed)]
+    warmup_discard_strategy: WarmupDiscard,
+
+    /// Window p50 change (percent) below which `stable` warmup ends
+    #[arg(long, default_value_t = 5.0)]
+    stable_tolerance: f64,
+
+    /// Count context switches, migrations, page faults and cache/TLB
+    /// misses with perf_event_open for each measured phase
+    #[arg(long)]
+    perf: bool,
+
+    /// Keep "<overall fraction> <phase>" in this file, rewritten on each update
+    #[arg(long, value_name = "PATH")]
+    progress_file: Option<PathBuf>,
+
+    /// Stream newline-delimited JSON progress events to stderr (also with
+    /// the TUI), for external dashboards
+    #[arg(long)]
+    progress_json: bool,
+
+    /// Estimate the power to detect an ON/OFF change of this many percent
+    /// (from the calibration probe) and warn if the run is underpowered
+    #[arg(long, value_name = "PCT")]
+    detect_effect: Option<f64>,
+
+    /// Spawn FACTOR x online CPUs unpinned busy threads to oversubscribe
+    /// the machine while measuring
+    #[arg(long, value_name = "FACTOR", default_value_t = 0.0)]
+    oversubscribe: f64,
+
+    /// SCHED_FIFO priorities (1-99, 0 = normal) assigned to workers
+    /// round-robin; with more than one class, latency is reported per class
+    #[arg(long, value_delimiter = ',', value_name = "LIST")]
+    worker_priorities: Vec<i32>,
+
+    /// Run every worker SCHED_FIFO at priority 1, the dispatcher's own
+    /// (shorthand for --worker-priorities 1)
+    #[arg(long, conflicts_with = "worker_priorities")]
+    worker_fifo: bool,
+
+    /// Niceness (-20..19) of workers left at SCHED_OTHER; no effect on
+    /// SCHED_FIFO ones. Below 0 needs CAP_SYS_NICE, without which workers
+    /// keep the default and the summary says so
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        allow_negative_numbers = true
+    )]
+    worker_nice: i32,
+
+    /// Subtract the measured timer overhead (back-to-back timestamp cost)
+    /// from every latency sample
+    #[arg(long)]
+    subtract_overhead: bool,
+
+    /// Don't create shadow threads; workers block and wake on whatever CPU
+    /// the scheduler picks
+    #[arg(long)]
+    no_shadows: bool,
+
+    /// Cap the per-phase sample buffers at this many MiB, reducing the
+    /// iteration count if needed
+    #[arg(long, value_name = "MB")]
+    max_memory_mb: Option<usize>,
+
+    /// Refuse to run unless the environment passes every strict-mode check
+    /// (governor, RT throttling, tracer, vDSO clock, SCHED_FIFO, free CPUs)
+    #[arg(long)]
+    strict: bool,
+
+    /// Print the strict-mode checks and exit without running
+    #[arg(long)]
+    strict_report: bool,
+
+    /// Print the plan (system, POC sysctl, thread placement, calibration
+    /// probe, schedule and estimated runtime) and exit without touching
+    /// the sysctl or starting the TUI
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Print each online CPU's package, core, SMT siblings, NUMA node and
+    /// cpufreq governor, then exit
+    #[arg(long)]
+    list_cpus: bool,
+
+    /// Pick the ON/OFF order of each round at random instead of
+    /// alternating by round parity
+    #[arg(long)]
+    random_order: bool,
+
+    /// Confidence level of the bootstrap interval for the mean, strictly
+    /// between 0 and 1
+    #[arg(long, value_name = "LEVEL", default_value_t = 0.95)]
+    confidence: f64,
+
+    /// Seed for every randomized choice: round order with --random-order
+    /// and the bootstrap CI resampling. Default: from the clock; the seed
+    /// used is printed in the summary so a run can be replayed
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+
+    /// Measure the cost of a clock read at startup and warn if
+    /// clock_gettime is not vDSO-accelerated
+    #[arg(long)]
+    check_vdso: bool,
+
+    /// Switch all CPUs to the `performance` cpufreq governor for the run and
+    /// restore the previous governors on exit
+    #[arg(long)]
+    pin_governor: bool,
+
+    /// TUI color theme
+    #[arg(long, value_enum, default_value_t = ThemeName::Default)]
+    theme: ThemeName,
+
+    /// Draw the TUI without colors, using ASCII bars and arrows (overrides
+    /// --theme)
+    #[arg(long)]
+    no_color: bool,
+
+    /// Reuse a calibration cached for this CPU model and these parameters
+    /// (in $XDG_CACHE_HOME/poc-bench) and cache new ones
+    #[arg(long)]
+    cache_calibration: bool,
+
+    /// With --cache-calibration, calibrate anyway and refresh the cache
+    #[arg(long)]
+    recalibrate: bool,
+
+    /// Give up on calibration after this many seconds and report an error
+    /// instead of appearing to hang (a loaded machine, or a worker that is
+    /// never woken)
+    #[arg(long, value_name = "SECONDS")]
+    calib_timeout: Option<f64>,
+
+    /// Skip the TUI; print one progress line per phase to stderr
+    #[arg(long)]
+    no_tui: bool,
+
+    /// Result format; all but `text` skip the TUI so stdout stays clean
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Write the latency-vs-percentile curve of each mode to this CSV
+    /// (mode,percentile,latency_ns), 0.1 to 99.9 in steps of 0.1 and 99.99
+    #[arg(long, value_name = "PATH")]
+    cdf: Option<PathBuf>,
+
+    /// Write every measured sample to this CSV
+    /// (round,poc_on,worker,latency_ns)
+    #[arg(long, value_name = "PATH")]
+    raw_csv: Option<PathBuf>,
+
+    /// Write the results to this file instead of stdout (in `--format`)
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Path of the POC on/off knob, instead of
+    /// /proc/sys/kernel/sched_poc_selector and other procfs mounts
+    #[arg(long, value_name = "PATH")]
+    sysctl_path: Option<PathBuf>,
+
+    /// Compare POC ON against the results in this `--format json` file
+    /// and exit 1 if it regressed beyond --regression-threshold
+    #[arg(long, value_name = "PATH")]
+    baseline: Option<PathBuf>,
+
+    /// Allowed slowdown against --baseline, in percent
+    #[arg(long, value_name = "PERCENT", default_value_t = 5.0)]
+    regression_threshold: f64,
+
+    /// Metric compared against --baseline
+    #[arg(long, value_enum, default_value_t = RegressionMetric::P99)]
+    regression_metric: RegressionMetric,
+
+    /// Load settings from a TOML (.toml) or YAML (.yaml/.yml) file
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// `--threads`/`--background` were set on the command line or in the
+    /// config file rather than left at their defaults.
+    #[arg(skip)]
+    threads_given: bool,
+    #[arg(skip)]
+    background_given: bool,
+}
+
+impl Cli {
+    fn parse_with_config() -> Self {
+        // Argument errors exit 1 like every other invalid setting; clap's
+        // own 2 means a single run here.
+        let usage_exit = |e: clap::Error| -> ! {
+            e.print().ok();
+            std::process::exit(if e.use_stderr() { 1 } else { 0 });
+        };
+        let matches = Cli::command()
+            .try_get_matches()
+            .unwrap_or_else(|e| usage_exit(e));
+        let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| usage_exit(e));
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        cli.threads_given = from_cli("threads");
+        cli.background_given = from_cli("background");
+        if let Some(path) = cli.config.clone() {
+            match FileConfig::load(&path) {
+                Ok(cfg) => cli.apply_config(cfg, &matches),
+                Err(e) => {
+                    eprintln!("poc-bench: config: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        cli
+    }
+
+    /// Fill in settings from the config file unless they were given on the
+    /// command line.
+    fn apply_config(&mut self, cfg: FileConfig, matches: &ArgMatches) {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        if let (Some(v), false) = (cfg.iterations, from_cli("iterations")) {
+            self.iterations = v;
+        }
+        if let (Some(v), false) = (cfg.threads, from_cli("threads")) {
+            self.threads = v;
+            self.threads_given = true;
+        }
+        if let (Some(v), false) = (cfg.background, from_cli("background")) {
+            self.background = v;
+            self.background_given = true;
+        }
+        if let (Some(v), false) = (cfg.rounds, from_cli("rounds")) {
+            self.rounds = v;
+        }
+        if let (Some(v), false) = (cfg.no_compare, from_cli("no_compare")) {
+            self.no_compare = v;
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
 // Main
 // ---------------------------------------------------------------------------
 
-fn main() {
-    let cli = Cli::parse();
-    let sysinfo = SystemInfo::detect();
-    let params = BenchParams::with_overrides(
-        sysinfo.ncpus,
-        sysinfo.physical_cores,
-        Some(cli.threads),
-        Some(cli.background),
-    );
+fn main() -> ExitCode {
+    run()
+}
+
+/// All of `main`. Returning the status rather than calling
+/// `process::exit` lets the guards restore the machine first.
+fn run() -> ExitCode {
+    let cli = Cli::parse_with_config();
+    if cli.list_cpus {
+        print_cpu_list(&system::cpu_topology(system::online_cpus()));
+        return ExitCode::SUCCESS;
+    }
+    let mut sysinfo = SystemInfo::detect();
+    let mut params = BenchParams::with_overrides(
+        sysinfo.ncpus,
+        sysinfo.physical_cores,
+        Some(cli.threads),
+        Some(cli.background),
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("poc-bench: --background: {}", e);
+        std::process::exit(1);
+    });
+    // Only what was asked for: the defaults follow the CPU count already.
+    let given = |c: &&ThreadClamp| {
+        if c.what == "workers" {
+            cli.threads_given
+        } else {
+            cli.background_given
+        }
+    };
+    for c in params.clamped.iter().filter(given) {
+        eprintln!(
+            "poc-bench: {}: {} requested, {} fit on {} CPUs",
+            if c.what == "workers" {
+                "--threads"
+            } else {
+                "--background"
+            },
+            c.requested,
+            c.actual,
+            sysinfo.ncpus
+        );
+    }
+    if cli.no_shadows {
+        params.disable_shadows();
+    }
+    params.perf = cli.perf;
+    params.wakeup = match cli.wakeup {
+        Wakeup::Eventfd => WakeupMechanism::Eventfd,
+        Wakeup::Futex => WakeupMechanism::Futex,
+        Wakeup::Pipe => WakeupMechanism::Pipe,
+    };
+    params.bg_workload = match cli.bg_workload {
+        BgLoad::Spin => BgWorkload::Spin,
+        BgLoad::Memory => BgWorkload::Memory,
+        BgLoad::Syscall => BgWorkload::Syscall,
+    };
+    params.timer = match cli.timer {
+        Timer::Clock => TimerSource::Clock,
+        Timer::Tsc => TimerSource::Tsc,
+    };
+    params.phase_duration = cli
+        .duration
+        .filter(|&s| s > 0.0)
+        .map(Duration::from_secs_f64);
+    params.oversubscribe = cli.oversubscribe;
+    params.work_ns = cli.work_us * 1000;
+    params.measure_shadow = cli.measure_shadow;
+    params.cross_cpu = cli.cross_cpu;
+    if let Settle::Ns(ns) = cli.settle_ns {
+        params.settle_ns = ns;
+    }
+    if !(0.0..50.0).contains(&cli.trim) {
+        eprintln!("poc-bench: --trim: {} is outside 0..50 percent", cli.trim);
+        std::process::exit(1);
+    }
+    if !(cli.confidence > 0.0 && cli.confidence < 1.0) {
+        eprintln!(
+            "poc-bench: --confidence: {} is not strictly between 0 and 1",
+            cli.confidence
+        );
+        std::process::exit(1);
+    }
+    if let Some(secs) = cli.calib_timeout.filter(|&s| !(s > 0.0 && s.is_finite())) {
+        eprintln!(
+            "poc-bench: --calib-timeout: {} is not a positive number of seconds",
+            secs
+        );
+        std::process::exit(1);
+    }
+    if !(cli.verify_tolerance > 0.0 && cli.verify_tolerance.is_finite()) {
+        eprintln!(
+            "poc-bench: --verify-tolerance: {} is not a positive percentage",
+            cli.verify_tolerance
+        );
+        std::process::exit(1);
+    }
+    params.trim_frac = cli.trim / 100.0;
+    // Prefer isolated CPUs for the workers, as long as some housekeeping
+    // CPU is left for the dispatcher.
+    if sysinfo.isolated.len() < sysinfo.ncpus {
+        params.isolated_cpus = sysinfo.isolated.clone();
+    }
+    if let NumaPlacement::Node { node } = cli.numa {
+        if !sysinfo.numa_nodes.iter().any(|n| n.id == node) {
+            eprintln!("poc-bench: --numa: no node {} with online CPUs", node);
+            std::process::exit(1);
+        }
+    }
+    params.numa = cli.numa;
+    if sysinfo.numa_nodes.len() > 1 {
+        params.numa_nodes = sysinfo.numa_nodes.clone();
+    }
+    if cli.no_smt {
+        params.smt_cpus = sysinfo.smt_primary_cpus();
+    }
+    params.worker_priorities = if cli.worker_fifo {
+        vec![1]
+    } else {
+        cli.worker_priorities
+            .iter()
+            .map(|&p| p.clamp(0, 99))
+            .collect()
+    };
+    if !(-20..=19).contains(&cli.worker_nice) {
+        eprintln!(
+            "poc-bench: --worker-nice: {} is outside -20..19",
+            cli.worker_nice
+        );
+        std::process::exit(1);
+    }
+    params.worker_nice = cli.worker_nice;
+    params.warmup_strategy = match cli.warmup_discard_strategy {
+        WarmupDiscard::Fixed => WarmupStrategy::Fixed,
+        WarmupDiscard::Stable => WarmupStrategy::Stable {
+            tolerance: cli.stable_tolerance / 100.0,
+        },
+    };
+
+    if cli.strict || cli.strict_report {
+        let checks = strict::run_checks(&params);
+        let failed = checks.iter().filter(|c| !c.ok).count();
+        if cli.strict_report || failed > 0 {
+            strict::print_report(&checks);
+        }
+        if cli.strict_report {
+            return ExitCode::SUCCESS;
+        }
+        if failed > 0 {
+            eprintln!("poc-bench: --strict: {failed} check(s) failed, not running");
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(p) = cli.sysctl_path.clone() {
+        system::set_sysctl_path(p);
+    }
+
+    if cli.dry_run {
+        let support = system::peek_poc_support();
+        let schedule = dryrun::Schedule {
+            compare: !cli.no_compare && matches!(support, PocSupport::Present),
+            modes_per_round: cli.modes.len().max(2),
+            discard_rounds: cli.discard_rounds,
+            rounds: if cli.rounds_precision.is_some() {
+                (cli.rounds_min, cli.rounds_max)
+            } else {
+                (cli.rounds, cli.rounds)
+            },
+            depths: cli.queue_depth.clone(),
+            passes: if cli.cstate_compare { 2 } else { 1 },
+            iterations: cli.iterations_per_round.unwrap_or(cli.iterations),
+            warmup: cli.warmup,
+            settle_auto: cli.settle_ns == Settle::Auto,
+            verify: cli.warmup_discard_verify.then_some(cli.verify_tolerance),
+        };
+        let probe = params.phase_duration.is_none().then(|| {
+            (cli.cache_calibration && !cli.recalibrate)
+                .then(|| calibrate::load_cached(&sysinfo.cpu_model, &params))
+                .flatten()
+                .map_or_else(|| calibrate::calibrate(&params), Ok)
+                .map_err(|e| e.to_string())
+        });
+        dryrun::print_plan(&sysinfo, &support, &params, &schedule, probe.as_ref());
+        return ExitCode::SUCCESS;
+    }
+
+    if let (Some(w), true) = (cli.warmup, cli.iterations > 0) {
+        if let Err(e) = check_warmup(w, cli.iterations) {
+            eprintln!("poc-bench: --warmup: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(n) = cli.iterations_per_round {
+        let warmup = cli.warmup.unwrap_or((n / 5).max(100));
+        if let Err(e) = check_warmup(warmup, n) {
+            eprintln!("poc-bench: --iterations-per-round: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if cli.modes.len() == 1 {
+        eprintln!("poc-bench: --modes: need at least two values to compare");
+        std::process::exit(1);
+    }
+    if cli.watch.is_some()
+        && (!cli.modes.is_empty() || cli.cstate_compare || cli.queue_depth.len() > 1)
+    {
+        eprintln!(
+            "poc-bench: --watch: needs a plain ON/OFF comparison \
+             (no --modes, --cstate-compare or queue depth sweep)"
+        );
+        std::process::exit(1);
+    }
+    if cli.rounds_precision.is_some() {
+        if cli.rounds_min < 2 || cli.rounds_min > cli.rounds_max {
+            eprintln!("poc-bench: --rounds-min must be at least 2 and at most --rounds-max");
+            std::process::exit(1);
+        }
+        if cli.watch.is_some() || !cli.modes.is_empty() {
+            eprintln!("poc-bench: --rounds-precision: not with --watch or --modes");
+            std::process::exit(1);
+        }
+    }
+    let watch_log = match cli.watch_log.as_deref().map(WatchLog::open) {
+        Some(Ok(log)) => Some(log),
+        Some(Err(e)) => {
+            eprintln!("poc-bench: --watch-log: {}", e);
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
+    let baseline = match cli.baseline.as_deref().map(baseline::load) {
+        Some(Ok(base)) => {
+            let v = cli.regression_metric.value(&base);
+            if !(v > 0.0 && v.is_finite()) {
+                eprintln!(
+                    "poc-bench: --baseline: POC ON {} is {}, not a latency to compare against",
+                    cli.regression_metric.name(),
+                    v
+                );
+                std::process::exit(1);
+            }
+            Some(base)
+        }
+        Some(Err(e)) => {
+            eprintln!("poc-bench: --baseline: {}", e);
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
+    let raw_csv = match cli.raw_csv.as_deref().map(RawCsv::create) {
+        Some(Ok(csv)) => Some(csv),
+        Some(Err(e)) => {
+            eprintln!("poc-bench: --raw-csv: {}", e);
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
+    // Every sample is a pair of clock reads; a syscall fallback inflates all
+    // of them, so measure before anything else is running.
+    let clock_cost_ns = cli.check_vdso.then(bench::clock_read_cost_ns);
+    let tsc = (params.timer == TimerSource::Tsc)
+        .then(timer::calibrate_tsc)
+        .flatten();
+    if params.timer == TimerSource::Tsc && tsc.is_none() {
+        eprintln!("poc-bench: --timer tsc: no invariant TSC, using CLOCK_MONOTONIC");
+        params.timer = TimerSource::Clock;
+    }
+    let timer_overhead_ns = calibrate::timer_overhead_ns(params.timer);
+    if cli.subtract_overhead {
+        params.subtract_overhead_ns = timer_overhead_ns;
+    }
+    if cli.settle_ns == Settle::Auto {
+        params.settle_auto = true;
+        match calibrate::settle_ns(&params) {
+            Ok(Some(ns)) => params.settle_ns = ns,
+            Ok(None) => eprintln!(
+                "poc-bench: --settle-ns auto: no shadows to time, keeping {} ns",
+                params.settle_ns
+            ),
+            Err(e) => eprintln!(
+                "poc-bench: --settle-ns auto: {}, keeping {} ns",
+                e, params.settle_ns
+            ),
+        }
+    }
+
+    // Lock memory
+    unsafe {
+        libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE);
+    }
+
+    // Prevent deep C-states for accurate latency measurement.
+    // Writing 0 to /dev/cpu_dma_latency keeps all CPUs in C0 while the fd is open.
+    let mut dma_latency = DmaLatencyGuard::open();
+
+    // Install SIGINT handler (Ctrl+C before raw mode / during calibration)
+    // SIGTERM (kill, timeout, CI cancellation) and SIGHUP (closed terminal
+    // or ssh session) so all of them exit cleanly.
+    for sig in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+        unsafe {
+            libc::signal(sig, handle_quit_signal as *const () as libc::sighandler_t);
+        }
+    }
+
+    // Pre-check sysctl: readable AND writable?
+    let poc_support = system::detect_poc_support();
+    let sysctl_readable = !matches!(poc_support, PocSupport::AbsentNoSysctl);
+    let sysctl_writable = matches!(poc_support, PocSupport::Present);
+    let compare = !cli.no_compare && sysctl_writable;
+    let sysctl_path = system::poc_sysctl_path().map(|p| p.display().to_string());
+    let orig_poc = if sysctl_readable {
+        system::poc_sysctl_read().unwrap_or(1)
+    } else {
+        -1
+    };
+    // Puts the knob back however main exits from here on.
+    let sysctl_guard = SysctlGuard::new(sysctl_writable.then_some(orig_poc));
+    if cli.watch.is_some() && !compare {
+        eprintln!("poc-bench: --watch: needs a writable POC sysctl to flip between rounds");
+        return ExitCode::from(1);
+    }
+
+    // Saved like orig_poc and restored by the guard.
+    let governor_guard = GovernorGuard::new(if cli.pin_governor {
+        match system::governor_pin(&sysinfo.freq) {
+            Ok(saved) => {
+                sysinfo.freq = system::FreqScaling::detect(sysinfo.ncpus);
+                saved
+            }
+            Err(e) => {
+                eprintln!("poc-bench: --pin-governor: {}", e);
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    });
+
+    // Set up terminal
+    let tui = cli.format == OutputFormat::Text && !cli.no_tui;
+    let terminal_guard = tui.then(TerminalGuard::enter);
+    let terminal = tui.then(|| {
+        let backend = CrosstermBackend::new(io::stdout());
+        Terminal::new(backend).expect("failed to create terminal")
+    });
+    let mut screen = Screen {
+        log: terminal.is_none().then(ProgressLog::default),
+        terminal,
+        progress_file: cli.progress_file.clone().map(ProgressFile::new),
+        progress_json: cli.progress_json.then(ProgressJson::default),
+        raw_csv,
+        watch_log,
+    };
+
+    let mut app = App::new(sysinfo, params.clone());
+    app.sysctl_path = sysctl_path;
+    app.clock_cost_ns = clock_cost_ns;
+    app.tsc_hz = tsc.map(|c| c.freq_hz);
+    app.timer_overhead_ns = timer_overhead_ns;
+    app.theme = match cli.theme {
+        _ if cli.no_color => Theme::mono(),
+        ThemeName::Default => Theme::color(),
+        ThemeName::Solarized => Theme::solarized(),
+        ThemeName::Highcontrast => Theme::high_contrast(),
+    };
+    app.clock_res_ns = bench::clock_resolution_ns();
+    app.bucketing = cli.hist;
+    app.watch = cli
+        .watch
+        .map(|secs| WatchState::new(Duration::from_secs(secs), cli.watch_window));
+    app.hist_counts = cli.hist_counts;
+    app.rounds_precision = cli.rounds_precision;
+    app.show_geomean = cli.show_geomean;
+    app.seed = cli.seed.unwrap_or_else(rng::clock_seed);
+    app.random_order = cli.random_order;
+    app.iterations_per_round = cli.iterations_per_round;
+    app.confidence = cli.confidence;
+    if app.system.ncpus < params.min_cpus() {
+        let err = bench::BenchError::TooFewCpus {
+            online: app.system.ncpus,
+            needed: params.min_cpus(),
+        };
+        fail(&mut app, err.to_string());
+    }
+    let sysctl_warning = match &poc_support {
+        PocSupport::PresentReadOnly { reason } => Some(format!("sysctl: {}", reason)),
+        _ => None,
+    };
+    // Root is what's missing only when the knob exists.
+    app.needs_root = !system::is_root() && !sysctl_writable && sysctl_readable;
+    app.poc_support = poc_support;
+    let session = Session {
+        cli: &cli,
+        params: &params,
+        compare,
+        orig_poc,
+    };
+    run_session(
+        &mut screen,
+        &mut app,
+        &session,
+        &mut dma_latency,
+        sysctl_warning.as_deref(),
+    );
+
+    // --- Phase 3: Wait for quit (only if benchmark ran to completion) ---
+    // `r` starts the whole run over with the same parameters.
+    let mut show_summary;
+    loop {
+        // Quitting is the only way a `--watch` ends; it still gets a summary.
+        show_summary = !quitting() || app.watch.is_some();
+        app.cpus_at_end = system::online_cpus();
+        if quitting() {
+            break;
+        }
+        if app.error.is_none() {
+            app.phase = Phase::Done;
+        }
+        app.finished = true;
+        app.progress = 1.0;
+        screen.draw(&app);
+
+        let mut restart = false;
+        while tui && !quitting() {
+            if event::poll(Duration::from_millis(100)).unwrap_or(false) {
+                if let Ok(ev) = event::read() {
+                    if is_quit_event(&ev) {
+                        break;
+                    }
+                    if is_restart_event(&ev) {
+                        restart = true;
+                        break;
+                    }
+                    handle_ui_event(&screen, &mut app, &ev);
+                    if matches!(ev, Event::Resize(..)) {
+                        screen.redraw(&app);
+                    } else {
+                        screen.draw(&app);
+                    }
+                }
+            }
+        }
+        if !restart {
+            break;
+        }
+        // The C-state pass dropped the C0 request; take it again.
+        if cli.cstate_compare && !dma_latency.is_held() {
+            dma_latency = DmaLatencyGuard::open();
+        }
+        app.reset_results();
+        run_session(&mut screen, &mut app, &session, &mut dma_latency, None);
+    }
 
-    // Lock memory
-    unsafe {
-        libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE);
+    // --- Cleanup ---
+    // The guards also run on a panic; dropped here so the summary below
+    // lands on the normal screen with the machine already restored.
+    drop(dma_latency);
+    drop(governor_guard);
+    drop(sysctl_guard);
+    drop(screen.terminal.take());
+    drop(terminal_guard);
+    if let Some(csv) = screen.raw_csv.take() {
+        if let Err(e) = csv.finish() {
+            eprintln!("poc-bench: --raw-csv: {}", e);
+        }
+    }
+    if let Some(ref path) = cli.cdf {
+        if let Err(e) = cdf::write(path, &app.cdf) {
+            eprintln!("poc-bench: --cdf: {}", e);
+        }
+    }
+    // The output file is written even after `q` so an aborted run keeps
+    // whatever it collected; stdout only gets the summary of a finished run.
+    if let Some(ref path) = cli.output {
+        if let Err(e) = write_output(path, cli.format, &app) {
+            eprintln!("poc-bench: {}: {}", path.display(), e);
+        }
+    } else if show_summary {
+        write_results(&mut io::stdout().lock(), cli.format, &app).ok();
     }
 
-    // Prevent deep C-states for accurate latency measurement.
-    // Writing 0 to /dev/cpu_dma_latency keeps all CPUs in C0 while the fd is open.
-    let dma_latency_fd = unsafe {
-        let fd = libc::open(
-            b"/dev/cpu_dma_latency\0".as_ptr() as *const libc::c_char,
-            libc::O_WRONLY,
+    if let (Some(base), Some(current), true) = (baseline, app.final_on.as_ref(), show_summary) {
+        let metric = cli.regression_metric;
+        let r = baseline::Regression {
+            metric: metric.name(),
+            baseline_ns: metric.value(&base),
+            current_ns: metric.value(&Baseline::from(&Metrics::new(current))),
+        };
+        let delta = r.delta_pct();
+        let verdict = if delta > cli.regression_threshold {
+            "REGRESSION"
+        } else {
+            "ok"
+        };
+        eprintln!(
+            "baseline: POC ON {} {:.2} μs -> {:.2} μs ({:+.1}%, threshold {:.1}%): {}",
+            r.metric,
+            r.baseline_ns / 1000.0,
+            r.current_ns / 1000.0,
+            delta,
+            cli.regression_threshold,
+            verdict,
         );
-        if fd >= 0 {
-            let val: i32 = 0;
-            libc::write(fd, &val as *const i32 as *const libc::c_void, 4);
+        if delta > cli.regression_threshold {
+            return ExitCode::FAILURE;
         }
-        fd
-    };
+    }
+    exit_status(&app, compare, !show_summary)
+}
 
-    // Install SIGINT handler (Ctrl+C before raw mode / during calibration)
-    unsafe {
-        libc::signal(
-            libc::SIGINT,
-            handle_sigint as *const () as libc::sighandler_t,
-        );
+/// Status of a run that got as far as measuring; see `EXIT_STATUS_HELP`.
+fn exit_status(app: &App, compare: bool, aborted: bool) -> ExitCode {
+    if app.error.is_some() {
+        ExitCode::from(EXIT_ERROR)
+    } else if aborted {
+        ExitCode::from(EXIT_ABORTED)
+    } else if !compare {
+        ExitCode::from(EXIT_SINGLE_RUN)
+    } else {
+        ExitCode::SUCCESS
     }
+}
 
-    // Pre-check sysctl: readable AND writable?
-    let sysctl_readable = system::poc_sysctl_read().is_some();
-    let (sysctl_writable, sysctl_err) = if sysctl_readable {
-        let val = system::poc_sysctl_read().unwrap_or(1);
-        match system::poc_sysctl_write(val) {
-            Ok(()) => (true, None),
-            Err(e) => (false, Some(e)),
-        }
+fn write_results(out: &mut dyn Write, format: OutputFormat, app: &App) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => ui::write_summary(out, app),
+        OutputFormat::Json => ui::write_json(out, app),
+        OutputFormat::Markdown => ui::write_markdown(out, app),
+        OutputFormat::Prometheus => ui::write_prometheus(out, app),
+    }
+}
+
+/// `--output`: create missing parent directories, write to a temporary
+/// file next to `path` and rename it over, so readers such as the
+/// node_exporter textfile collector never see a half-written file.
+fn write_output(path: &Path, format: OutputFormat, app: &App) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut out = io::BufWriter::new(fs::File::create(&tmp)?);
+    write_results(&mut out, format, app)?;
+    out.flush()?;
+    fs::rename(&tmp, path)
+}
+
+/// Startup state a benchmark run needs; kept so `r` can run it again.
+struct Session<'a> {
+    cli: &'a Cli,
+    params: &'a BenchParams,
+    compare: bool,
+    /// POC knob value at startup, restored after every comparison and on
+    /// exit; never re-read on a restart.
+    orig_poc: i32,
+}
+
+/// Calibrate (unless the iteration count is fixed) and run every
+/// measurement pass. `sysctl_warning` is shown before the first pass.
+fn run_session(
+    screen: &mut Screen,
+    app: &mut App,
+    session: &Session,
+    dma_latency: &mut DmaLatencyGuard,
+    sysctl_warning: Option<&str>,
+) {
+    let Session {
+        cli,
+        params,
+        compare,
+        orig_poc,
+    } = *session;
+    app.run_start = Some(Instant::now());
+    let per_measurement = if compare {
+        cli.modes.len().max(2) * (cli.discard_rounds + max_rounds(cli))
     } else {
-        (false, None)
+        1
     };
-    let compare = !cli.no_compare && sysctl_writable;
-    let orig_poc = if sysctl_readable {
-        system::poc_sysctl_read().unwrap_or(1)
+    let passes = if cli.cstate_compare && dma_latency.is_held() {
+        2
     } else {
-        -1
+        1
     };
-
-    // Set up terminal
-    enable_raw_mode().expect("failed to enable raw mode");
-    io::stdout()
-        .execute(EnterAlternateScreen)
-        .expect("failed to enter alternate screen");
-    let backend = CrosstermBackend::new(io::stdout());
-    let mut terminal = Terminal::new(backend).expect("failed to create terminal");
-
-    let mut app = App::new(sysinfo, params.clone());
-    terminal.draw(|f| ui::draw(f, &app)).ok();
+    // `--watch` has no end, so no overall progress either.
+    app.steps_total = if cli.watch.is_some() {
+        0
+    } else {
+        usize::from(cli.iterations == 0 && params.phase_duration.is_none())
+            + passes * cli.queue_depth.len() * per_measurement
+    };
+    screen.draw(app);
 
     // --- Phase 1: Calibration ---
-    let (iterations, warmup) = if cli.iterations > 0 {
+    let (iterations, warmup) = if stopping(app) {
+        (0, 0)
+    } else if let Some(d) = params.phase_duration {
+        // Buffers sized for the deepest queue the sweep will use.
+        let deepest = BenchParams {
+            queue_depth: cli.queue_depth.iter().copied().max().unwrap_or(1),
+            ..params.clone()
+        };
+        (bench::duration_capacity(&deepest, d), DURATION_WARMUP)
+    } else if cli.iterations > 0 {
         app.calibration = None;
-        let warmup = (cli.iterations / 5).max(100);
+        let warmup = cli.warmup.unwrap_or((cli.iterations / 5).max(100));
         (cli.iterations, warmup)
     } else {
         app.phase = Phase::Calibrating;
+        app.steps_started += 1;
+        app.phase_start = Some(Instant::now());
         app.progress = 0.0;
-        terminal.draw(|f| ui::draw(f, &app)).ok();
+        screen.draw(app);
 
-        let cal = calibrate::calibrate(&params);
-        app.calibration = Some(cal.clone());
-        app.progress = 1.0;
-        terminal.draw(|f| ui::draw(f, &app)).ok();
+        let cached = (cli.cache_calibration && !cli.recalibrate)
+            .then(|| calibrate::load_cached(&app.system.cpu_model, params))
+            .flatten();
+        app.calibration_cached = cached.is_some();
+        let calibration = match cached {
+            Some(cal) => Ok(cal),
+            None => {
+                // Probes run off-thread so the screen keeps updating and a
+                // hung one can be abandoned at the deadline.
+                let deadline = cli
+                    .calib_timeout
+                    .map(|secs| (Instant::now() + Duration::from_secs_f64(secs), secs));
+                calibrate::calibrate_with(params, &mut |p, n, w| {
+                    let handle = bench::bench_burst_async(p, n, w);
+                    poll_run(screen, app, &handle, deadline, false)
+                        .unwrap_or(Err(bench::BenchError::Interrupted))
+                })
+            }
+        };
+        match calibration {
+            Ok(cal) => {
+                // The cache is an optimization; failing to write it is not
+                // worth interrupting the run for.
+                if cli.cache_calibration && !app.calibration_cached {
+                    calibrate::store_cached(&app.system.cpu_model, params, &cal).ok();
+                }
+                if let Some(pct) = cli.detect_effect {
+                    // The rounds the run plans for: with --rounds-precision
+                    // it only stops short of them once precise enough.
+                    let n = cli.iterations_per_round.unwrap_or(cal.iterations)
+                        * params.n_workers
+                        * max_rounds(cli);
+                    app.power = Some(PowerEstimate {
+                        effect_pct: pct,
+                        samples_per_mode: n,
+                        power: stats::power_two_sample(
+                            cal.probe_mean_us,
+                            cal.probe_stddev_us,
+                            n,
+                            pct / 100.0,
+                        ),
+                    });
+                }
+                app.calibration = Some(cal.clone());
+                app.progress = 1.0;
+                screen.draw(app);
+                match cli.warmup {
+                    Some(w) => {
+                        if let Err(e) = check_warmup(w, cal.iterations) {
+                            fail(app, format!("--warmup: {}", e));
+                        }
+                        (cal.iterations, w)
+                    }
+                    None => (cal.iterations, cal.warmup),
+                }
+            }
+            Err(_) if quitting() => (0, 0),
+            Err(e) => {
+                fail(app, e.to_string());
+                (0, 0)
+            }
+        }
+    };
 
-        (cal.iterations, cal.warmup)
+    // Discard rounds keep the calibrated size, so they warm up as long as
+    // usual however short `--iterations-per-round` makes the measured ones.
+    let discard = ((iterations / 5).max(500), (warmup / 5).max(100));
+    let (iterations, warmup) = match cli.iterations_per_round {
+        Some(n) if iterations > 0 => (n, cli.warmup.unwrap_or((n / 5).max(100))),
+        _ => (iterations, warmup),
     };
 
-    // --- Phase 2: Benchmark ---
-    if !quitting() {
-        if compare {
-            run_comparison(
-                &mut terminal,
-                &mut app,
-                &params,
-                iterations,
-                warmup,
-                orig_poc,
-                cli.rounds,
+    // Sample buffers grow with iterations x workers; shrink the run rather
+    // than get OOM-killed halfway through it.
+    let mut iterations = iterations;
+    if let Some(mb) = cli.max_memory_mb.filter(|_| !stopping(app)) {
+        let cap = bench::max_iterations_for(params, warmup, mb << 20);
+        if cap == 0 {
+            fail(
+                app,
+                format!(
+                    "--max-memory-mb {}: too small for the warmup buffers alone",
+                    mb
+                ),
             );
-        } else {
-            // Single run, no comparison
-            if !sysctl_writable && sysctl_readable {
-                let msg = match &sysctl_err {
-                    Some(e) => format!("sysctl: {}", e),
-                    None => "sysctl not writable (need root?)".into(),
-                };
-                app.phase = Phase::Error(msg);
-                terminal.draw(|f| ui::draw(f, &app)).ok();
-                std::thread::sleep(Duration::from_secs(3));
-            }
-            if !quitting() {
-                app.phase = Phase::Running {
-                    round: 1,
-                    total_rounds: 1,
-                    poc_on: sysctl_readable && orig_poc > 0,
-                };
-                let handle = bench::bench_burst_async(&params, iterations, warmup);
-                let samples = run_with_progress(&mut terminal, &mut app, &handle);
-
-                if !samples.is_empty() {
-                    let mut s = samples.clone();
-                    let sr = StatResult::compute(&mut s);
-                    app.hist_on = Some(Histogram::from_samples(&samples));
-                    app.final_on = Some(sr);
-                }
-            }
+        } else if iterations > cap {
+            app.iterations_capped = Some((iterations, cap));
+            iterations = cap;
         }
     }
 
-    // --- Phase 3: Wait for quit (only if benchmark ran to completion) ---
-    let show_summary = !quitting();
-    if !quitting() {
-        app.phase = Phase::Done;
-        app.finished = true;
-        app.progress = 1.0;
-        terminal.draw(|f| ui::draw(f, &app)).ok();
+    let plan = RunPlan {
+        iterations,
+        warmup,
+        discard,
+        rounds: max_rounds(cli),
+        discard_rounds: cli.discard_rounds,
+        compare,
+        orig_poc,
+        modes: cli.modes.clone(),
+        cdf: cli.cdf.is_some(),
+        rounds_min: cli.rounds_min,
+        verify: cli
+            .warmup_discard_verify
+            .then_some(cli.verify_tolerance / 100.0),
+    };
 
-        loop {
-            if quitting() {
-                break;
-            }
-            if event::poll(Duration::from_millis(100)).unwrap_or(false) {
-                if let Ok(ev) = event::read() {
-                    if is_quit_event(&ev) {
-                        break;
-                    }
-                }
+    // --- Phase 2: Benchmark ---
+    if !stopping(app) {
+        if let Some(msg) = sysctl_warning {
+            app.phase = Phase::Error(msg.to_string());
+            screen.draw(app);
+            std::thread::sleep(Duration::from_secs(3));
+        }
+        if cli.cstate_compare {
+            app.cstate = Some(CStateCompare::new(dma_latency.is_held()));
+        }
+        run_sweep(screen, app, params, &plan, &cli.queue_depth);
+
+        // Second pass with deep C-states allowed: closing the fd drops the
+        // PM QoS request, so idle CPUs may enter deep states between wakeups.
+        if cli.cstate_compare && dma_latency.is_held() && !stopping(app) {
+            dma_latency.release();
+            if let Some(cs) = app.cstate.as_mut() {
+                cs.pinned_on = app.final_on.take();
+                cs.pinned_off = app.final_off.take();
+                cs.deep_pass = true;
             }
+            app.hist_on = None;
+            app.hist_off = None;
+            run_sweep(screen, app, params, &plan, &cli.queue_depth);
         }
     }
+}
 
-    // --- Cleanup (always runs) ---
-    if dma_latency_fd >= 0 {
-        unsafe {
-            libc::close(dma_latency_fd);
-        }
+/// `--list-cpus` table; `-` for anything sysfs does not expose.
+fn print_cpu_list(cpus: &[system::CpuTopology]) {
+    let opt = |v: Option<String>| v.unwrap_or_else(|| "-".into());
+    println!(
+        "{:>4} {:>8} {:>5} {:<12} {:>5}  governor",
+        "cpu", "package", "core", "siblings", "node"
+    );
+    for c in cpus {
+        let siblings = if c.siblings.is_empty() {
+            "-".into()
+        } else {
+            system::format_cpu_list(&c.siblings)
+        };
+        println!(
+            "{:>4} {:>8} {:>5} {:<12} {:>5}  {}",
+            c.cpu,
+            opt(c.package.map(|p| p.to_string())),
+            opt(c.core.map(|p| p.to_string())),
+            siblings,
+            opt(c.node.map(|n| n.to_string())),
+            opt(c.governor.clone()),
+        );
     }
-    if sysctl_writable && orig_poc >= 0 {
-        system::poc_sysctl_write(orig_poc).ok();
+}
+
+/// Measured rounds a comparison runs at most: `--rounds-max` when
+/// adaptive, `--rounds` otherwise.
+fn max_rounds(cli: &Cli) -> usize {
+    if cli.rounds_precision.is_some() {
+        cli.rounds_max
+    } else {
+        cli.rounds
+    }
+}
+
+/// `--warmup` must stay shorter than the measured part of a phase, and
+/// leave enough of it for meaningful percentiles.
+fn check_warmup(warmup: usize, iterations: usize) -> Result<(), String> {
+    if iterations < MIN_MEASURED_ITERATIONS {
+        return Err(format!(
+            "{} measured iterations per phase, need at least {}",
+            iterations, MIN_MEASURED_ITERATIONS
+        ));
     }
-    disable_raw_mode().ok();
-    io::stdout().execute(LeaveAlternateScreen).ok();
-    terminal.show_cursor().ok();
-    if show_summary {
-        ui::print_summary(&app);
+    if warmup >= iterations {
+        return Err(format!(
+            "{} warmup iterations is not less than the {} measured",
+            warmup, iterations
+        ));
     }
+    Ok(())
 }
 
-fn run_comparison(
-    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    app: &mut App,
-    params: &BenchParams,
+/// Iteration counts and sysctl state shared by every measurement pass.
+struct RunPlan {
     iterations: usize,
     warmup: usize,
-    orig_poc: i32,
+    /// Iterations and warmup of each discard phase.
+    discard: (usize, usize),
     rounds: usize,
+    discard_rounds: usize,
+    compare: bool,
+    orig_poc: i32,
+    /// `--modes` values; empty for the ON/OFF comparison.
+    modes: Vec<i32>,
+    /// Keep every measured sample for the `--cdf` curves.
+    cdf: bool,
+    /// With `--rounds-precision`, the round count before which the
+    /// comparison may not stop early; `rounds` is then the maximum.
+    rounds_min: usize,
+    /// `--warmup-discard-verify` tolerance, as a fraction.
+    verify: Option<f64>,
+}
+
+/// `--warmup-discard-verify`: probe until the system is quiet, leaving
+/// the outcome in `app.quiesce`. The sysctl stays as the last discard
+/// phase left it.
+fn verify_quiesced(screen: &mut Screen, app: &mut App, params: &BenchParams, plan: &RunPlan) {
+    let Some(tolerance) = plan.verify else {
+        return;
+    };
+    let mut probes = 0;
+    let result = calibrate::check_quiesced(params, tolerance, &mut |p, n, w| {
+        probes += 1;
+        app.phase = Phase::Settling { probe: probes };
+        app.progress = 0.0;
+        screen.draw(app);
+        let handle = bench::bench_burst_async(p, n, w);
+        poll_run(screen, app, &handle, None, false).unwrap_or(Err(bench::BenchError::Interrupted))
+    });
+    match result {
+        Ok(q) => app.quiesce = Some(q),
+        Err(_) if quitting() => {}
+        Err(e) => fail(app, format!("quiet check failed: {}", e)),
+    }
+}
+
+/// Run one measurement per queue depth. With a single depth this is just
+/// `run_measurement`; with several, each depth's final results are recorded
+/// in `app.depth_sweep` and the last one stays on screen.
+fn run_sweep(
+    screen: &mut Screen,
+    app: &mut App,
+    params: &BenchParams,
+    plan: &RunPlan,
+    depths: &[usize],
 ) {
-    // --- Discard round ---
-    app.phase = Phase::Discard;
-    app.progress = 0.0;
-    terminal.draw(|f| ui::draw(f, app)).ok();
-
-    let discard_n = (iterations / 5).max(500);
-    let discard_w = (warmup / 5).max(100);
-
-    system::poc_sysctl_write(1).ok();
-    let h = bench::bench_burst_async(params, discard_n, discard_w);
-    let _ = run_with_progress(terminal, app, &h);
-    if quitting() {
+    let deep_cstates = app.cstate.as_ref().is_some_and(|cs| cs.deep_pass);
+    for (i, &depth) in depths.iter().enumerate() {
+        if stopping(app) {
+            break;
+        }
+        let mut params = params.clone();
+        params.queue_depth = depth.max(1);
+        app.params.queue_depth = params.queue_depth;
+        if i > 0 {
+            app.hist_on = None;
+            app.hist_off = None;
+            app.final_on = None;
+            app.final_off = None;
+        }
+        run_measurement(screen, app, &params, plan);
+        if depths.len() > 1 && !stopping(app) {
+            app.depth_sweep.push(DepthResult {
+                depth: params.queue_depth,
+                deep_cstates,
+                on: app.final_on.clone(),
+                off: app.final_off.clone(),
+            });
+        }
+    }
+}
+
+fn run_measurement(screen: &mut Screen, app: &mut App, params: &BenchParams, plan: &RunPlan) {
+    if plan.compare && !plan.modes.is_empty() {
+        run_modes(screen, app, params, plan);
         return;
     }
+    if plan.compare {
+        run_comparison(screen, app, params, plan);
+        return;
+    }
+
+    // Single run, no comparison
+    app.phase = Phase::Running {
+        round: 1,
+        total_rounds: 1,
+        poc_on: plan.orig_poc > 0,
+    };
+    let handle = bench::bench_burst_async(params, plan.iterations, plan.warmup);
+    let run = run_with_progress(screen, app, &handle);
+    let samples = &run.samples;
+
+    if !samples.is_empty() {
+        app.warmup_discarded.push(run.warmup_discarded);
+        if let Some(ref c) = run.perf {
+            app.perf_on.get_or_insert_with(Default::default).add(c);
+        }
+        if let Some(ref r) = run.idle {
+            app.idle_on.get_or_insert_with(Default::default).add(r);
+        }
+        if let Some(ref d) = run.sched {
+            app.sched_on.get_or_insert_with(Default::default).add(d);
+        }
+        record_priority_classes(app, params, &run, true);
+        record_shadow(app, &run, true);
+        screen.record_samples(app, 1, plan.orig_poc.max(0), &run);
+        let mut s = samples.clone();
+        let sr = StatResult::compute(&mut s, params.trim_frac);
+        app.hist_on = Some(Histogram::from_samples(samples, app.bucketing));
+        app.final_on = Some(sr);
+        if plan.cdf {
+            app.cdf = vec![("on".into(), stats::cdf(&mut s))];
+        }
+    }
+}
+
+fn run_comparison(screen: &mut Screen, app: &mut App, params: &BenchParams, plan: &RunPlan) {
+    let RunPlan {
+        iterations,
+        warmup,
+        rounds,
+        orig_poc,
+        ..
+    } = *plan;
 
-    system::poc_sysctl_write(0).ok();
-    app.progress = 0.5;
-    terminal.draw(|f| ui::draw(f, app)).ok();
-    let h = bench::bench_burst_async(params, discard_n, discard_w);
-    let _ = run_with_progress(terminal, app, &h);
-    if quitting() {
+    // --- Discard rounds ---
+    let (discard_n, discard_w) = plan.discard;
+    let discard_params = BenchParams {
+        phase_duration: params.phase_duration.map(|d| d / 5),
+        ..params.clone()
+    };
+
+    for round in 0..plan.discard_rounds {
+        app.phase = Phase::Discard {
+            round: round + 1,
+            total_rounds: plan.discard_rounds,
+        };
+        app.progress = 0.0;
+        screen.draw(app);
+
+        system::poc_sysctl_write(1).ok();
+        let h = bench::bench_burst_async(&discard_params, discard_n, discard_w);
+        let _ = run_with_progress(screen, app, &h);
+        if stopping(app) {
+            system::poc_sysctl_write(orig_poc).ok();
+            return;
+        }
+
+        system::poc_sysctl_write(0).ok();
+        app.progress = 0.5;
+        screen.draw(app);
+        let h = bench::bench_burst_async(&discard_params, discard_n, discard_w);
+        let _ = run_with_progress(screen, app, &h);
+        if stopping(app) {
+            system::poc_sysctl_write(orig_poc).ok();
+            return;
+        }
+    }
+    verify_quiesced(screen, app, params, plan);
+    if stopping(app) {
+        system::poc_sysctl_write(orig_poc).ok();
         return;
     }
 
     // --- Measured rounds ---
+    let mut order_rng = app.random_order.then(|| rng::Rng::new(app.seed));
+    app.round_order.clear();
+    app.rounds_on.clear();
+    app.rounds_off.clear();
     let mut results_on = Vec::new();
     let mut results_off = Vec::new();
+    let mut hist_on = Histogram::new(app.bucketing);
+    let mut hist_off = Histogram::new(app.bucketing);
     let mut all_on = Vec::new();
     let mut all_off = Vec::new();
 
-    'rounds: for round in 0..rounds {
-        let on_first = round % 2 == 0;
+    // `--watch`: unbounded, until quit; `total_rounds` 0 marks that.
+    let watching = app.watch.is_some();
+    let total_rounds = if watching { 0 } else { rounds };
+    'rounds: for round in 0.. {
+        if !watching && round >= rounds {
+            break;
+        }
+        let cycle_start = Instant::now();
+        let on_first = match order_rng.as_mut() {
+            Some(rng) => rng.next_bool(),
+            None => round % 2 == 0,
+        };
+        if !watching {
+            app.round_order.push(on_first);
+        }
         let order: [(bool, &str); 2] = if on_first {
             [(true, "POC ON"), (false, "CFS")]
         } else {
@@ -301,44 +1771,108 @@ fn run_comparison(
         };
 
         for &(poc_on, _label) in &order {
-            if quitting() {
+            if stopping(app) {
                 break 'rounds;
             }
 
             app.phase = Phase::Running {
                 round: round + 1,
-                total_rounds: rounds,
+                total_rounds,
                 poc_on,
             };
             app.progress = 0.0;
-            terminal.draw(|f| ui::draw(f, app)).ok();
+            screen.draw(app);
 
             system::poc_sysctl_write(if poc_on { 1 } else { 0 }).ok();
             let h = bench::bench_burst_async(params, iterations, warmup);
-            let samples = run_with_progress(terminal, app, &h);
+            let run = run_with_progress(screen, app, &h);
+            let samples = &run.samples;
 
-            if quitting() {
+            if stopping(app) {
                 break 'rounds;
             }
 
             if !samples.is_empty() {
+                app.warmup_discarded.push(run.warmup_discarded);
+                if let Some(ref c) = run.perf {
+                    let slot = if poc_on {
+                        &mut app.perf_on
+                    } else {
+                        &mut app.perf_off
+                    };
+                    slot.get_or_insert_with(Default::default).add(c);
+                }
+                if let Some(ref r) = run.idle {
+                    let slot = if poc_on {
+                        &mut app.idle_on
+                    } else {
+                        &mut app.idle_off
+                    };
+                    slot.get_or_insert_with(Default::default).add(r);
+                }
+                if let Some(ref d) = run.sched {
+                    let slot = if poc_on {
+                        &mut app.sched_on
+                    } else {
+                        &mut app.sched_off
+                    };
+                    slot.get_or_insert_with(Default::default).add(d);
+                }
+                record_priority_classes(app, params, &run, poc_on);
+                record_shadow(app, &run, poc_on);
+                screen.record_samples(app, round + 1, i32::from(poc_on), &run);
                 let mut s = samples.clone();
-                let sr = StatResult::compute(&mut s);
+                let sr = StatResult::compute(&mut s, params.trim_frac);
                 if poc_on {
-                    all_on.extend_from_slice(&samples);
+                    hist_on.add_samples(samples);
+                } else {
+                    hist_off.add_samples(samples);
+                }
+                // A watch never ends: keep only the recent window and a
+                // running merge, not every sample or round (no
+                // significance, CI or CDF then).
+                if let Some(w) = app.watch.as_mut() {
+                    w.push(poc_on, sr.clone());
+                    let slot = if poc_on {
+                        &mut app.final_on
+                    } else {
+                        &mut app.final_off
+                    };
+                    *slot = Some(match slot.take() {
+                        Some(prev) => StatResult::merge(&[prev, sr]),
+                        None => sr,
+                    });
+                } else if poc_on {
+                    all_on.extend_from_slice(samples);
+                    app.rounds_on.push(sr.clone());
                     results_on.push(sr);
                 } else {
-                    all_off.extend_from_slice(&samples);
+                    all_off.extend_from_slice(samples);
+                    app.rounds_off.push(sr.clone());
                     results_off.push(sr);
                 }
             }
 
             // Update histograms with cumulative data
-            if !all_on.is_empty() {
-                app.hist_on = Some(Histogram::from_samples(&all_on));
+            if hist_on.total > 0 {
+                app.hist_on = Some(hist_on.clone());
             }
-            if !all_off.is_empty() {
-                app.hist_off = Some(Histogram::from_samples(&all_off));
+            if hist_off.total > 0 {
+                app.hist_off = Some(hist_off.clone());
+            }
+            let (rounds_on, rounds_off): (&[StatResult], &[StatResult]) = match app.watch.as_mut() {
+                // Watching, the spread is over the recent window only.
+                Some(w) => (
+                    w.recent_on.make_contiguous(),
+                    w.recent_off.make_contiguous(),
+                ),
+                None => (&results_on, &results_off),
+            };
+            if rounds_on.len() > 1 {
+                app.between_on = Some(StatResult::between_round_stddev(rounds_on));
+            }
+            if rounds_off.len() > 1 {
+                app.between_off = Some(StatResult::between_round_stddev(rounds_off));
             }
             if !results_on.is_empty() {
                 app.final_on = Some(StatResult::merge(&results_on));
@@ -347,55 +1881,320 @@ fn run_comparison(
                 app.final_off = Some(StatResult::merge(&results_off));
             }
 
-            terminal.draw(|f| ui::draw(f, app)).ok();
+            screen.draw(app);
+        }
+
+        if let Some(w) = app.watch.as_mut() {
+            w.cycles += 1;
+            if let (Some(log), Some(on), Some(off)) = (
+                screen.watch_log.as_mut(),
+                w.recent_on.back(),
+                w.recent_off.back(),
+            ) {
+                if let Err(e) = log.append(w.cycles, on, off) {
+                    fail(app, format!("watch log: {}", e));
+                    break 'rounds;
+                }
+            }
+            let deadline = cycle_start + w.interval;
+            wait_until(screen, app, deadline);
+        }
+
+        if let Some(target) = app.rounds_precision {
+            app.round_delta_ci = stats::round_delta_ci(&results_on, &results_off);
+            let precise = app.round_delta_ci.is_some_and(|(_, half)| half <= target);
+            if precise && round + 1 >= plan.rounds_min {
+                break;
+            }
         }
     }
 
+    if !stopping(app) && !all_on.is_empty() && !all_off.is_empty() {
+        app.significance = Some(stats::mann_whitney_u(&all_on, &all_off));
+        let mut rng = rng::Rng::new(app.seed);
+        let mut ci = |samples: &[u64]| {
+            StatResult::bootstrap_ci(
+                samples,
+                app.confidence,
+                stats::BOOTSTRAP_RESAMPLES,
+                &mut rng,
+            )
+        };
+        app.ci_on = Some(ci(&all_on));
+        app.ci_off = Some(ci(&all_off));
+        screen.draw(app);
+    }
+    // After the bootstrap: sorting first would change its resamples.
+    if plan.cdf {
+        app.cdf = [("on", &mut all_on), ("off", &mut all_off)]
+            .into_iter()
+            .filter(|(_, all)| !all.is_empty())
+            .map(|(mode, all)| (mode.to_string(), stats::cdf(all)))
+            .collect();
+    }
+
     // Restore original POC setting
     system::poc_sysctl_write(orig_poc).ok();
 }
 
-fn run_with_progress(
-    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    app: &mut App,
-    handle: &bench::BenchHandle,
-) -> Vec<u64> {
-    loop {
-        if quitting() {
-            return Vec::new();
+/// `--modes`: `run_comparison` over any list of sysctl values. Each round
+/// starts one value later than the previous one (or at a random value with
+/// `--random-order`), so no value always runs first.
+fn run_modes(screen: &mut Screen, app: &mut App, params: &BenchParams, plan: &RunPlan) {
+    let RunPlan {
+        iterations,
+        warmup,
+        rounds,
+        orig_poc,
+        ..
+    } = *plan;
+    let n = plan.modes.len();
+    app.modes = plan
+        .modes
+        .iter()
+        .map(|&value| ModeResult {
+            value,
+            stats: None,
+            hist: None,
+        })
+        .collect();
+
+    // --- Discard rounds ---
+    let (discard_n, discard_w) = plan.discard;
+    let discard_params = BenchParams {
+        phase_duration: params.phase_duration.map(|d| d / 5),
+        ..params.clone()
+    };
+
+    for round in 0..plan.discard_rounds {
+        app.phase = Phase::Discard {
+            round: round + 1,
+            total_rounds: plan.discard_rounds,
+        };
+        for (i, &value) in plan.modes.iter().enumerate() {
+            app.progress = i as f64 / n as f64;
+            screen.draw(app);
+            system::poc_sysctl_write(value).ok();
+            let h = bench::bench_burst_async(&discard_params, discard_n, discard_w);
+            let _ = run_with_progress(screen, app, &h);
+            if stopping(app) {
+                system::poc_sysctl_write(orig_poc).ok();
+                return;
+            }
         }
+    }
+    verify_quiesced(screen, app, params, plan);
+    if stopping(app) {
+        system::poc_sysctl_write(orig_poc).ok();
+        return;
+    }
 
-        let p = handle.progress.load(Ordering::Relaxed);
-        app.progress = if handle.total > 0 {
-            p as f64 / handle.total as f64
-        } else {
-            0.0
+    // --- Measured rounds ---
+    let mut order_rng = app.random_order.then(|| rng::Rng::new(app.seed));
+    let mut results: Vec<Vec<StatResult>> = vec![Vec::new(); n];
+    let mut hists = vec![Histogram::new(app.bucketing); n];
+    let mut all: Vec<Vec<u64>> = vec![Vec::new(); n];
+
+    'rounds: for round in 0..rounds {
+        let first = match order_rng.as_mut() {
+            Some(rng) => (rng.next_u64() % n as u64) as usize,
+            None => round % n,
         };
-        terminal.draw(|f| ui::draw(f, app)).ok();
+        for k in 0..n {
+            let i = (first + k) % n;
+            let value = plan.modes[i];
+            if stopping(app) {
+                break 'rounds;
+            }
 
-        if let Some(result) = handle.try_recv() {
-            app.progress = 1.0;
-            return result;
+            app.phase = Phase::Mode {
+                round: round + 1,
+                total_rounds: rounds,
+                value,
+            };
+            app.progress = 0.0;
+            screen.draw(app);
+
+            system::poc_sysctl_write(value).ok();
+            let h = bench::bench_burst_async(params, iterations, warmup);
+            let run = run_with_progress(screen, app, &h);
+            if stopping(app) {
+                break 'rounds;
+            }
+
+            if !run.samples.is_empty() {
+                app.warmup_discarded.push(run.warmup_discarded);
+                screen.record_samples(app, round + 1, value, &run);
+                let mut s = run.samples.clone();
+                results[i].push(StatResult::compute(&mut s, params.trim_frac));
+                hists[i].add_samples(&run.samples);
+                if plan.cdf {
+                    all[i].extend_from_slice(&run.samples);
+                }
+                let mode = &mut app.modes[i];
+                mode.stats = Some(StatResult::merge(&results[i]));
+                mode.hist = Some(hists[i].clone());
+            }
+            screen.draw(app);
+        }
+    }
+    if plan.cdf {
+        app.cdf = plan
+            .modes
+            .iter()
+            .zip(&mut all)
+            .filter(|(_, all)| !all.is_empty())
+            .map(|(value, all)| (value.to_string(), stats::cdf(all)))
+            .collect();
+    }
+
+    // Restore original POC setting
+    system::poc_sysctl_write(orig_poc).ok();
+}
+
+/// Fold a phase's shadow ack round trips into the per-phase result.
+fn record_shadow(app: &mut App, run: &BenchRun, poc_on: bool) {
+    if run.shadow_samples.is_empty() {
+        return;
+    }
+    let mut s = run.shadow_samples.clone();
+    let sr = StatResult::compute(&mut s, app.params.trim_frac);
+    let slot = if poc_on {
+        &mut app.shadow_on
+    } else {
+        &mut app.shadow_off
+    };
+    *slot = Some(match slot.take() {
+        Some(prev) => StatResult::merge(&[prev, sr]),
+        None => sr,
+    });
+}
+
+/// Split a phase's samples by worker priority class and add them to the
+/// per-class results (only when `--worker-priorities` yields several classes).
+fn record_priority_classes(app: &mut App, params: &BenchParams, run: &BenchRun, poc_on: bool) {
+    for prio in params.priority_classes() {
+        let mut samples: Vec<u64> = (0..params.n_workers)
+            .filter(|&w| params.worker_priority(w) == prio)
+            .flat_map(|w| run.worker_samples(w).iter().copied())
+            .collect();
+        let sr = StatResult::compute(&mut samples, params.trim_frac);
+        let class = match app.priority_classes.iter_mut().find(|c| c.priority == prio) {
+            Some(c) => c,
+            None => {
+                app.priority_classes.push(PriorityClass {
+                    priority: prio,
+                    on: Vec::new(),
+                    off: Vec::new(),
+                });
+                app.priority_classes.last_mut().unwrap()
+            }
+        };
+        if poc_on {
+            class.on.push(sr);
+        } else {
+            class.off.push(sr);
         }
+    }
+}
 
-        if event::poll(Duration::from_millis(50)).unwrap_or(false) {
+/// `--watch`: sit out the rest of a cycle's interval, still redrawing and
+/// answering the keyboard.
+fn wait_until(screen: &mut Screen, app: &mut App, deadline: Instant) {
+    while !quitting() {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return;
+        }
+        screen.draw(app);
+        let tick = left.min(Duration::from_millis(50));
+        if screen.terminal.is_none() {
+            std::thread::sleep(tick);
+        } else if event::poll(tick).unwrap_or(false) {
             if let Ok(ev) = event::read() {
                 if is_quit_event(&ev) {
                     QUIT.store(true, Ordering::Relaxed);
-                    return Vec::new();
+                    return;
+                }
+                handle_ui_event(screen, app, &ev);
+                if matches!(ev, Event::Resize(..)) {
+                    screen.redraw(app);
                 }
             }
         }
     }
 }
 
-impl Clone for calibrate::CalibrationResult {
-    fn clone(&self) -> Self {
-        Self {
-            iterations: self.iterations,
-            warmup: self.warmup,
-            probe_mean_us: self.probe_mean_us,
-            probe_stddev_us: self.probe_stddev_us,
+fn run_with_progress(screen: &mut Screen, app: &mut App, handle: &bench::BenchHandle) -> BenchRun {
+    app.steps_started += 1;
+    app.phase_start = Some(Instant::now());
+    match poll_run(screen, app, handle, None, true) {
+        Some(Ok(result)) => {
+            app.worker_prio_denied |= result.worker_prio_denied;
+            app.dispatcher_moves += result.dispatcher_moves;
+            result
+        }
+        Some(Err(e)) => {
+            fail(app, e.to_string());
+            screen.draw(app);
+            BenchRun::default()
+        }
+        None => BenchRun::default(),
+    }
+}
+
+/// Keep the screen and input going until the run behind `handle` ends.
+/// `None` on a quit request; a `(deadline, seconds)` passed without a
+/// result abandons the run as a calibration timeout. Calibration probes
+/// time themselves, so they are not `pausable`.
+fn poll_run(
+    screen: &mut Screen,
+    app: &mut App,
+    handle: &bench::BenchHandle,
+    deadline: Option<(Instant, f64)>,
+    pausable: bool,
+) -> Option<bench::BenchResult> {
+    loop {
+        if quitting() {
+            handle.set_paused(false);
+            return None;
+        }
+
+        app.paused = handle.is_paused();
+        app.recent = handle.recent();
+        app.progress = handle.fraction();
+        screen.draw(app);
+
+        if let Some(result) = handle.try_recv() {
+            if result.is_ok() {
+                app.progress = 1.0;
+                app.paused = false;
+            }
+            return Some(result);
+        }
+        if let Some((at, secs)) = deadline {
+            if Instant::now() >= at {
+                return Some(Err(bench::BenchError::CalibrationTimeout { secs }));
+            }
+        }
+
+        if screen.terminal.is_none() {
+            std::thread::sleep(Duration::from_millis(50));
+        } else if event::poll(Duration::from_millis(50)).unwrap_or(false) {
+            if let Ok(ev) = event::read() {
+                if is_quit_event(&ev) {
+                    QUIT.store(true, Ordering::Relaxed);
+                    handle.set_paused(false);
+                    return None;
+                }
+                if pausable && is_pause_event(&ev) {
+                    handle.set_paused(!handle.is_paused());
+                }
+                handle_ui_event(screen, app, &ev);
+                if matches!(ev, Event::Resize(..)) {
+                    screen.redraw(app);
+                }
+            }
         }
     }
 }
@@ -0,0 +1,167 @@
+// perf_event_open(2) counters for the benchmark threads.
+//
+// Counters are opened on the dispatcher thread with `inherit` set before
+// any worker/shadow/background thread is spawned, so they also cover every
+// thread the dispatcher creates. Inherited counts are folded into the
+// parent when a child exits, which is why they are read after the joins.
+
+const PERF_TYPE_HARDWARE: u32 = 0;
+const PERF_TYPE_SOFTWARE: u32 = 1;
+const PERF_TYPE_HW_CACHE: u32 = 3;
+
+const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+const PERF_COUNT_SW_PAGE_FAULTS: u64 = 2;
+const PERF_COUNT_SW_CONTEXT_SWITCHES: u64 = 3;
+const PERF_COUNT_SW_CPU_MIGRATIONS: u64 = 4;
+// dTLB | (OP_READ << 8) | (RESULT_MISS << 16)
+const PERF_COUNT_HW_CACHE_DTLB_READ_MISS: u64 = 3 | (1 << 16);
+
+const ATTR_DISABLED: u64 = 1 << 0;
+const ATTR_INHERIT: u64 = 1 << 1;
+const ATTR_EXCLUDE_KERNEL: u64 = 1 << 5;
+const ATTR_EXCLUDE_HV: u64 = 1 << 6;
+
+const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+
+/// `struct perf_event_attr` up to PERF_ATTR_SIZE_VER0 (64 bytes), which
+/// every kernel with perf events accepts.
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+/// Counter totals for one phase; `None` where the counter could not be
+/// opened (no CAP_PERFMON, restrictive perf_event_paranoid, no PMU in a VM).
+#[derive(Clone, Default)]
+pub struct PerfCounts {
+    pub context_switches: Option<u64>,
+    pub cpu_migrations: Option<u64>,
+    pub page_faults: Option<u64>,
+    pub cache_misses: Option<u64>,
+    pub dtlb_misses: Option<u64>,
+}
+
+impl PerfCounts {
+    pub fn add(&mut self, other: &PerfCounts) {
+        fn acc(a: &mut Option<u64>, b: Option<u64>) {
+            if let Some(b) = b {
+                *a = Some(a.unwrap_or(0) + b);
+            }
+        }
+        acc(&mut self.context_switches, other.context_switches);
+        acc(&mut self.cpu_migrations, other.cpu_migrations);
+        acc(&mut self.page_faults, other.page_faults);
+        acc(&mut self.cache_misses, other.cache_misses);
+        acc(&mut self.dtlb_misses, other.dtlb_misses);
+    }
+
+    pub fn rows(&self) -> [(&'static str, Option<u64>); 5] {
+        [
+            ("ctx-switch", self.context_switches),
+            ("migrations", self.cpu_migrations),
+            ("page-faults", self.page_faults),
+            ("cache-miss", self.cache_misses),
+            ("dTLB-miss", self.dtlb_misses),
+        ]
+    }
+}
+
+pub struct PerfCounters {
+    fds: [i32; 5],
+}
+
+impl PerfCounters {
+    /// Open all counters disabled. Returns `None` if none could be opened.
+    pub fn open() -> Option<Self> {
+        let events = [
+            (PERF_TYPE_SOFTWARE, PERF_COUNT_SW_CONTEXT_SWITCHES),
+            (PERF_TYPE_SOFTWARE, PERF_COUNT_SW_CPU_MIGRATIONS),
+            (PERF_TYPE_SOFTWARE, PERF_COUNT_SW_PAGE_FAULTS),
+            (PERF_TYPE_HARDWARE, PERF_COUNT_HW_CACHE_MISSES),
+            (PERF_TYPE_HW_CACHE, PERF_COUNT_HW_CACHE_DTLB_READ_MISS),
+        ];
+        let fds = events.map(|(type_, config)| open_counter(type_, config));
+        if fds.iter().all(|&fd| fd < 0) {
+            None
+        } else {
+            Some(Self { fds })
+        }
+    }
+
+    pub fn enable(&self) {
+        for &fd in self.fds.iter().filter(|&&fd| fd >= 0) {
+            unsafe {
+                libc::ioctl(fd, PERF_EVENT_IOC_ENABLE, 0);
+            }
+        }
+    }
+
+    pub fn read(&self) -> PerfCounts {
+        let [cs, mig, pf, cm, tlb] = self.fds.map(read_counter);
+        PerfCounts {
+            context_switches: cs,
+            cpu_migrations: mig,
+            page_faults: pf,
+            cache_misses: cm,
+            dtlb_misses: tlb,
+        }
+    }
+}
+
+impl Drop for PerfCounters {
+    fn drop(&mut self) {
+        for &fd in self.fds.iter().filter(|&&fd| fd >= 0) {
+            unsafe {
+                libc::close(fd);
+            }
+        }
+    }
+}
+
+fn open_counter(type_: u32, config: u64) -> i32 {
+    // Unprivileged users under perf_event_paranoid=2 may only count user
+    // space, so retry without kernel events before giving up.
+    for extra in [0, ATTR_EXCLUDE_KERNEL] {
+        let attr = PerfEventAttr {
+            type_,
+            size: std::mem::size_of::<PerfEventAttr>() as u32,
+            config,
+            flags: ATTR_DISABLED | ATTR_INHERIT | ATTR_EXCLUDE_HV | extra,
+            ..Default::default()
+        };
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                &attr as *const PerfEventAttr,
+                0,  // this thread (and inherited children)
+                -1, // any CPU
+                -1, // no group
+                PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+        if fd >= 0 {
+            return fd as i32;
+        }
+    }
+    -1
+}
+
+fn read_counter(fd: i32) -> Option<u64> {
+    if fd < 0 {
+        return None;
+    }
+    let mut val: u64 = 0;
+    let n = unsafe { libc::read(fd, &mut val as *mut u64 as *mut libc::c_void, 8) };
+    (n == 8).then_some(val)
+}
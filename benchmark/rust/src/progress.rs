@@ -0,0 +1,158 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::stats::StatResult;
+use crate::ui::{self, App, Phase};
+
+/// Schema version of the `--progress-json` events, bumped on any change a
+/// consumer could trip over.
+const PROGRESS_JSON_VERSION: u32 = 1;
+
+/// Minimum time between two writes of the same phase.
+const WRITE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// `--progress-file`: a single line `<overall fraction> <phase label>`,
+/// rewritten in place so `cat` always shows the current state.
+pub struct ProgressFile {
+    path: PathBuf,
+    last_write: Option<Instant>,
+    last_label: String,
+}
+
+impl ProgressFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            last_write: None,
+            last_label: String::new(),
+        }
+    }
+
+    pub fn update(&mut self, app: &App) {
+        let label = ui::phase_label(app);
+        let due = self
+            .last_write
+            .is_none_or(|t| t.elapsed() >= WRITE_INTERVAL);
+        if !due && label == self.last_label {
+            return;
+        }
+        let line = format!("{:.4} {}\n", app.overall_progress(), label);
+        // fs::write truncates, so a reader never sees a stale tail.
+        fs::write(&self.path, line).ok();
+        self.last_write = Some(Instant::now());
+        self.last_label = label;
+    }
+}
+
+/// Headless progress: one stderr line per finished phase, e.g.
+/// `Round 2/4 [CFS]... done`.
+#[derive(Default)]
+pub struct ProgressLog {
+    last_label: String,
+    /// Phase in progress, reported once the next one starts.
+    running: Option<String>,
+}
+
+impl ProgressLog {
+    pub fn update(&mut self, app: &App) {
+        let label = ui::phase_label(app);
+        if label == self.last_label {
+            return;
+        }
+        self.last_label = label.clone();
+        if let Some(prev) = self.running.take() {
+            // `fail` sets app.error; an Error phase without it is a warning
+            // shown between phases.
+            let outcome = if app.error.is_some() {
+                "failed"
+            } else {
+                "done"
+            };
+            eprintln!("{}... {}", prev.trim_end_matches("..."), outcome);
+        }
+        match app.phase {
+            Phase::Done => {}
+            Phase::Error(_) => eprintln!("{}", label),
+            _ => self.running = Some(label),
+        }
+    }
+}
+
+/// `--progress-json`: newline-delimited JSON events on stderr, each with
+/// `"v"` and `"event"`: `calibration` once, `phase_start`/`phase_end` around
+/// every phase, and a `progress` snapshot at most every WRITE_INTERVAL.
+#[derive(Default)]
+pub struct ProgressJson {
+    last_label: String,
+    last_write: Option<Instant>,
+    calibration_sent: bool,
+}
+
+impl ProgressJson {
+    pub fn update(&mut self, app: &App) {
+        if let (false, Some(cal)) = (self.calibration_sent, app.calibration.as_ref()) {
+            self.calibration_sent = true;
+            emit(json!({
+                "event": "calibration",
+                "iterations": cal.iterations,
+                "warmup": cal.warmup,
+                "probe_mean_us": cal.probe_mean_us,
+                "probe_stddev_us": cal.probe_stddev_us,
+                "attempts": cal.attempts,
+                "cached": app.calibration_cached,
+            }));
+        }
+
+        let label = ui::phase_label(app);
+        if label != self.last_label {
+            if !self.last_label.is_empty() {
+                emit(json!({ "event": "phase_end", "phase": self.last_label }));
+            }
+            match &app.phase {
+                Phase::Done => emit(json!({ "event": "done" })),
+                Phase::Error(msg) => emit(json!({ "event": "error", "message": msg })),
+                _ => emit(json!({ "event": "phase_start", "phase": label })),
+            }
+            self.last_label = label.clone();
+        }
+
+        if self
+            .last_write
+            .is_some_and(|t| t.elapsed() < WRITE_INTERVAL)
+        {
+            return;
+        }
+        self.last_write = Some(Instant::now());
+        let current = current_stats(app);
+        emit(json!({
+            "event": "progress",
+            "phase": label,
+            "progress": app.progress,
+            "overall": app.overall_progress(),
+            "p50_ns": current.map(|sr| sr.p50),
+            "p99_ns": current.map(|sr| sr.p99),
+        }));
+    }
+}
+
+/// Cumulative results of the mode being measured, if any yet.
+fn current_stats(app: &App) -> Option<&StatResult> {
+    match app.phase {
+        Phase::Running { poc_on: true, .. } => app.final_on.as_ref(),
+        Phase::Running { poc_on: false, .. } => app.final_off.as_ref(),
+        Phase::Mode { value, .. } => app
+            .modes
+            .iter()
+            .find(|m| m.value == value)
+            .and_then(|m| m.stats.as_ref()),
+        _ => None,
+    }
+}
+
+fn emit(mut event: Value) {
+    event["v"] = json!(PROGRESS_JSON_VERSION);
+    eprintln!("{}", event);
+}
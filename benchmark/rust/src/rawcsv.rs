@@ -0,0 +1,34 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::bench::BenchRun;
+
+/// `--raw-csv`: every recorded sample of the measured phases, one per
+/// line, streamed as each phase finishes. `poc_on` is the sysctl value the
+/// phase ran with: 0 or 1, or any `--modes` value.
+pub struct RawCsv {
+    out: BufWriter<File>,
+}
+
+impl RawCsv {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "round,poc_on,worker,latency_ns")?;
+        Ok(Self { out })
+    }
+
+    pub fn write_run(&mut self, round: usize, poc: i32, run: &BenchRun) -> io::Result<()> {
+        let n_workers = run.samples.len().checked_div(run.per_worker).unwrap_or(0);
+        for w in 0..n_workers {
+            for ns in run.worker_samples(w) {
+                writeln!(self.out, "{},{},{},{}", round, poc, w, ns)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,498 @@
+// The versioned results document the JSON, Markdown and Prometheus
+// outputs are rendered from; the text ON/OFF summary (`write_summary`) still
+// reads `App` directly.
+//
+// `BenchReport` copies what those formats need out of `App` and the library
+// types into structs of its own, so `StatResult`, `BenchParams` and friends
+// can change shape without changing what `--format json` (or Markdown, or
+// Prometheus) emits. The field names below are the contract: adding a
+// field is compatible, renaming or removing one bumps `SCHEMA_VERSION`.
+
+use serde::Serialize;
+
+use crate::schedstat::SchedDelta;
+use crate::stats::{Histogram, StatResult};
+use crate::system::{
+    BenchParams, PocSupport, SystemInfo, TimerSource, WakeupMechanism, WarmupStrategy,
+};
+use crate::ui::App;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+pub struct BenchReport {
+    pub schema_version: u32,
+    pub system: SystemReport,
+    pub poc_support: SupportReport,
+    pub params: ParamsReport,
+    pub calibration: Option<CalibrationReport>,
+    /// `--iterations-per-round`, which overrides the calibrated count.
+    pub iterations_per_round: Option<usize>,
+    pub seed: u64,
+    pub poc_on: Option<ModeReport>,
+    pub poc_off: Option<ModeReport>,
+    /// `--modes` results, in the order given; the first is the baseline.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub modes: Vec<ModeValueReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow_on: Option<Metrics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow_off: Option<Metrics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sched_on: Option<SchedReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sched_off: Option<SchedReport>,
+    /// Conditions that make the numbers less trustworthy or incomplete.
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SystemReport {
+    pub cpu_model: String,
+    pub kernel: String,
+    pub ncpus: usize,
+    pub physical_cores: usize,
+    pub smt_threads: usize,
+    pub isolated: Vec<usize>,
+    pub numa_nodes: usize,
+    pub popcnt: &'static str,
+    pub ctz: &'static str,
+    pub ptselect: &'static str,
+    pub lzcnt: &'static str,
+    pub avx2: &'static str,
+    pub avx512f: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct SupportReport {
+    /// `present`, `present-read-only` or `absent-no-sysctl`.
+    pub state: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ParamsReport {
+    pub n_workers: usize,
+    pub n_background: usize,
+    pub n_idle: usize,
+    pub shadows_per_worker: usize,
+    pub queue_depth: usize,
+    /// `fixed` or `stable`.
+    pub warmup_strategy: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warmup_tolerance: Option<f64>,
+    pub trim_frac: f64,
+    pub wakeup: &'static str,
+    pub bg_workload: &'static str,
+    pub timer: &'static str,
+    pub worker_priorities: Vec<i32>,
+    pub worker_nice: i32,
+    pub oversubscribe: f64,
+    pub work_ns: u64,
+    pub subtract_overhead_ns: u64,
+    pub phase_duration_secs: Option<f64>,
+    pub isolated_cpus: Vec<usize>,
+    pub smt_cpus: Vec<usize>,
+    pub numa: String,
+    pub perf: bool,
+    pub measure_shadow: bool,
+    pub settle_ns: u64,
+    pub cross_cpu: bool,
+}
+
+#[derive(Serialize)]
+pub struct CalibrationReport {
+    pub iterations: usize,
+    pub warmup: usize,
+    pub probe_mean_us: f64,
+    pub probe_stddev_us: f64,
+    pub attempts: usize,
+    pub cached: bool,
+}
+
+/// Latency statistics of one mode, all in ns unless the name says
+/// otherwise.
+#[derive(Serialize)]
+pub struct Metrics {
+    pub mean: f64,
+    pub geomean: f64,
+    pub trimmed_mean: f64,
+    pub stddev: f64,
+    pub min: u64,
+    pub max: u64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub p999: f64,
+    /// p99 - p50.
+    pub jitter: f64,
+    /// stddev / mean, as a fraction.
+    pub cov: f64,
+    /// p99 / p50; 0 when p50 is 0.
+    pub tail_ratio: f64,
+    pub ops_per_sec: f64,
+    pub count: usize,
+    pub outlier_count: usize,
+    pub max_outlier_ns: u64,
+}
+
+#[derive(Serialize)]
+pub struct ModeReport {
+    #[serde(flatten)]
+    pub metrics: Metrics,
+    pub histogram: Option<Vec<BucketReport>>,
+}
+
+/// One histogram bucket, `[lo_us, hi_us)`; `hi_us` is null for the last.
+#[derive(Serialize)]
+pub struct BucketReport {
+    pub lo_us: u64,
+    pub hi_us: Option<u64>,
+    pub count: u32,
+}
+
+#[derive(Serialize)]
+pub struct ModeValueReport {
+    pub value: i32,
+    #[serde(flatten)]
+    pub result: Option<ModeReport>,
+}
+
+/// /proc/schedstat totals over the measured phases, all CPUs.
+#[derive(Serialize)]
+pub struct SchedReport {
+    pub switches: u64,
+    pub wakeups: u64,
+    pub migrations: u64,
+}
+
+impl BenchReport {
+    pub fn from_app(app: &App) -> Self {
+        let mut warnings = Vec::new();
+        if let Some(banner) = app.poc_support.banner(&app.system.kernel) {
+            warnings.push(banner);
+        }
+        if let Some(warn) = app.system.freq.warning() {
+            warnings.push(warn);
+        }
+        if app.cpus_at_end != app.system.ncpus {
+            warnings.push(format!(
+                "online CPU count changed during the run ({} -> {})",
+                app.system.ncpus, app.cpus_at_end
+            ));
+        }
+        Self {
+            schema_version: SCHEMA_VERSION,
+            system: SystemReport::new(&app.system),
+            poc_support: SupportReport::new(&app.poc_support),
+            params: ParamsReport::new(&app.params),
+            calibration: app.calibration.as_ref().map(|c| CalibrationReport {
+                iterations: c.iterations,
+                warmup: c.warmup,
+                probe_mean_us: c.probe_mean_us,
+                probe_stddev_us: c.probe_stddev_us,
+                attempts: c.attempts,
+                cached: app.calibration_cached,
+            }),
+            iterations_per_round: app.iterations_per_round,
+            seed: app.seed,
+            poc_on: ModeReport::new(&app.final_on, &app.hist_on),
+            poc_off: ModeReport::new(&app.final_off, &app.hist_off),
+            modes: app
+                .modes
+                .iter()
+                .map(|m| ModeValueReport {
+                    value: m.value,
+                    result: ModeReport::new(&m.stats, &m.hist),
+                })
+                .collect(),
+            shadow_on: app.shadow_on.as_ref().map(Metrics::new),
+            shadow_off: app.shadow_off.as_ref().map(Metrics::new),
+            sched_on: app.sched_on.as_ref().map(SchedReport::new),
+            sched_off: app.sched_off.as_ref().map(SchedReport::new),
+            warnings,
+            error: app.error.clone(),
+        }
+    }
+
+    /// Result columns with their titles and the index of the one changes
+    /// are measured against: `--modes` values, POC ON vs CFS, or the single
+    /// run.
+    pub fn columns(&self) -> (Vec<(String, Option<&Metrics>)>, usize) {
+        fn metrics(r: &Option<ModeReport>) -> Option<&Metrics> {
+            r.as_ref().map(|r| &r.metrics)
+        }
+        if !self.modes.is_empty() {
+            let cols = self
+                .modes
+                .iter()
+                .map(|m| (format!("POC={}", m.value), metrics(&m.result)))
+                .collect();
+            (cols, 0)
+        } else if self.poc_off.is_some() {
+            let cols = vec![
+                ("POC ON".to_string(), metrics(&self.poc_on)),
+                ("CFS".to_string(), metrics(&self.poc_off)),
+            ];
+            (cols, 1)
+        } else {
+            (vec![("Result".to_string(), metrics(&self.poc_on))], 0)
+        }
+    }
+}
+
+impl SystemReport {
+    fn new(sys: &SystemInfo) -> Self {
+        let hw = &sys.hw_features;
+        Self {
+            cpu_model: sys.cpu_model.clone(),
+            kernel: sys.kernel.clone(),
+            ncpus: sys.ncpus,
+            physical_cores: sys.physical_cores,
+            smt_threads: sys.smt_threads(),
+            isolated: sys.isolated.clone(),
+            numa_nodes: sys.numa_nodes.len().max(1),
+            popcnt: hw.popcnt,
+            ctz: hw.ctz,
+            ptselect: hw.ptselect,
+            lzcnt: hw.lzcnt,
+            avx2: hw.avx2,
+            avx512f: hw.avx512f,
+        }
+    }
+}
+
+impl SupportReport {
+    fn new(support: &PocSupport) -> Self {
+        match support {
+            PocSupport::Present => Self {
+                state: "present",
+                reason: None,
+            },
+            PocSupport::PresentReadOnly { reason } => Self {
+                state: "present-read-only",
+                reason: Some(reason.clone()),
+            },
+            PocSupport::AbsentNoSysctl => Self {
+                state: "absent-no-sysctl",
+                reason: None,
+            },
+        }
+    }
+}
+
+impl ParamsReport {
+    fn new(p: &BenchParams) -> Self {
+        let (warmup_strategy, warmup_tolerance) = match p.warmup_strategy {
+            WarmupStrategy::Fixed => ("fixed", None),
+            WarmupStrategy::Stable { tolerance } => ("stable", Some(tolerance)),
+        };
+        Self {
+            n_workers: p.n_workers,
+            n_background: p.n_background,
+            n_idle: p.n_idle,
+            shadows_per_worker: p.shadows_per_worker,
+            queue_depth: p.queue_depth,
+            warmup_strategy,
+            warmup_tolerance,
+            trim_frac: p.trim_frac,
+            wakeup: match p.wakeup {
+                WakeupMechanism::Eventfd => "eventfd",
+                WakeupMechanism::Futex => "futex",
+                WakeupMechanism::Pipe => "pipe",
+            },
+            bg_workload: p.bg_workload.label(),
+            timer: match p.timer {
+                TimerSource::Clock => "clock",
+                TimerSource::Tsc => "tsc",
+            },
+            worker_priorities: p.worker_priorities.clone(),
+            worker_nice: p.worker_nice,
+            oversubscribe: p.oversubscribe,
+            work_ns: p.work_ns,
+            subtract_overhead_ns: p.subtract_overhead_ns,
+            phase_duration_secs: p.phase_duration.map(|d| d.as_secs_f64()),
+            isolated_cpus: p.isolated_cpus.clone(),
+            smt_cpus: p.smt_cpus.clone(),
+            numa: p.numa.label(),
+            perf: p.perf,
+            measure_shadow: p.measure_shadow,
+            settle_ns: p.settle_ns,
+            cross_cpu: p.cross_cpu,
+        }
+    }
+}
+
+impl Metrics {
+    pub fn new(sr: &StatResult) -> Self {
+        Self {
+            mean: sr.mean,
+            geomean: sr.geomean,
+            trimmed_mean: sr.trimmed_mean,
+            stddev: sr.stddev,
+            min: sr.min,
+            max: sr.max,
+            p50: sr.p50,
+            p90: sr.p90,
+            p95: sr.p95,
+            p99: sr.p99,
+            p999: sr.p999,
+            jitter: sr.jitter(),
+            cov: sr.cov(),
+            tail_ratio: sr.tail_ratio(),
+            ops_per_sec: sr.ops_per_sec(),
+            count: sr.count,
+            outlier_count: sr.outlier_count,
+            max_outlier_ns: sr.max_outlier_ns,
+        }
+    }
+
+    /// Value of a summary row, in μs (cov in percent, p99/p50 and ops/sec
+    /// as is).
+    pub fn row(&self, row: &str) -> f64 {
+        match row {
+            "mean" => self.mean / 1000.0,
+            "geomean" => self.geomean / 1000.0,
+            "trimmed" => self.trimmed_mean / 1000.0,
+            "p50" => self.p50 / 1000.0,
+            "p90" => self.p90 / 1000.0,
+            "p95" => self.p95 / 1000.0,
+            "p99" => self.p99 / 1000.0,
+            "p99.9" => self.p999 / 1000.0,
+            "min" => self.min as f64 / 1000.0,
+            "max" => self.max as f64 / 1000.0,
+            "stddev" => self.stddev / 1000.0,
+            "cov" => self.cov * 100.0,
+            "jitter" => self.jitter / 1000.0,
+            "p99/p50" => self.tail_ratio,
+            _ => self.ops_per_sec,
+        }
+    }
+}
+
+impl ModeReport {
+    fn new(sr: &Option<StatResult>, hist: &Option<Histogram>) -> Option<Self> {
+        sr.as_ref().map(|sr| Self {
+            metrics: Metrics::new(sr),
+            histogram: hist.as_ref().map(|h| {
+                h.buckets
+                    .iter()
+                    .enumerate()
+                    .map(|(b, &count)| {
+                        let (lo_us, hi_us) = h.bucketing.range_us(b);
+                        BucketReport {
+                            lo_us,
+                            hi_us,
+                            count,
+                        }
+                    })
+                    .collect()
+            }),
+        })
+    }
+}
+
+impl SchedReport {
+    fn new(d: &SchedDelta) -> Self {
+        Self {
+            switches: d.switches,
+            wakeups: d.wakeups,
+            migrations: d.remote_wakeups,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::calibrate::CalibrationResult;
+    use crate::ui::ModeResult;
+
+    /// Every field a report can carry, so all of them serialize.
+    fn fixture() -> BenchReport {
+        let mut app = App::new(SystemInfo::detect(), BenchParams::calculate(8, 4));
+        let mut samples: Vec<u64> = (0..1_000).map(|i| 2_000 + i * 13 % 9_000).collect();
+        let hist = Histogram::from_samples(&samples, app.bucketing);
+        let result = StatResult::compute(&mut samples, crate::stats::DEFAULT_TRIM_FRAC);
+        app.calibration = Some(CalibrationResult {
+            iterations: 10_000,
+            warmup: 2_000,
+            probe_mean_us: 5.0,
+            probe_stddev_us: 1.0,
+            timer_overhead_ns: 20,
+            attempts: 1,
+            secs_per_iter: 1e-5,
+        });
+        app.iterations_per_round = Some(10_000);
+        app.final_on = Some(result.clone());
+        app.final_off = Some(result.clone());
+        app.hist_on = Some(hist.clone());
+        app.hist_off = Some(hist.clone());
+        app.modes = vec![ModeResult {
+            value: 1,
+            stats: Some(result.clone()),
+            hist: Some(hist),
+        }];
+        app.shadow_on = Some(result.clone());
+        app.shadow_off = Some(result);
+        app.sched_on = Some(SchedDelta::default());
+        app.sched_off = Some(SchedDelta::default());
+        BenchReport::from_app(&app)
+    }
+
+    /// One `path: key key ...` line per object in `v`, keys sorted; array
+    /// elements appear as `path[]`, taken from the first one.
+    fn key_lines(v: &Value, path: &str, out: &mut Vec<String>) {
+        match v {
+            Value::Object(map) => {
+                let mut keys: Vec<&str> = map.keys().map(|k| k.as_str()).collect();
+                keys.sort_unstable();
+                out.push(format!("{path}: {}", keys.join(" ")));
+                for (k, v) in map {
+                    key_lines(v, &format!("{path}.{k}"), out);
+                }
+            }
+            Value::Array(items) => {
+                if let Some(first) = items.first() {
+                    key_lines(first, &format!("{path}[]"), out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Every key of a full report, one line per object. Renaming or
+    /// removing any of them breaks readers: bump `SCHEMA_VERSION` and
+    /// update this together.
+    const GOLDEN_KEYS: &str = "\
+: calibration error iterations_per_round modes params poc_off poc_on poc_support sched_off sched_on schema_version seed shadow_off shadow_on system warnings
+.calibration: attempts cached iterations probe_mean_us probe_stddev_us warmup
+.modes[]: count cov geomean histogram jitter max max_outlier_ns mean min ops_per_sec outlier_count p50 p90 p95 p99 p999 stddev tail_ratio trimmed_mean value
+.modes[].histogram[]: count hi_us lo_us
+.params: bg_workload cross_cpu isolated_cpus measure_shadow n_background n_idle n_workers numa oversubscribe perf phase_duration_secs queue_depth settle_ns shadows_per_worker smt_cpus subtract_overhead_ns timer trim_frac wakeup warmup_strategy work_ns worker_nice worker_priorities
+.poc_off: count cov geomean histogram jitter max max_outlier_ns mean min ops_per_sec outlier_count p50 p90 p95 p99 p999 stddev tail_ratio trimmed_mean
+.poc_off.histogram[]: count hi_us lo_us
+.poc_on: count cov geomean histogram jitter max max_outlier_ns mean min ops_per_sec outlier_count p50 p90 p95 p99 p999 stddev tail_ratio trimmed_mean
+.poc_on.histogram[]: count hi_us lo_us
+.poc_support: state
+.sched_off: migrations switches wakeups
+.sched_on: migrations switches wakeups
+.shadow_off: count cov geomean jitter max max_outlier_ns mean min ops_per_sec outlier_count p50 p90 p95 p99 p999 stddev tail_ratio trimmed_mean
+.shadow_on: count cov geomean jitter max max_outlier_ns mean min ops_per_sec outlier_count p50 p90 p95 p99 p999 stddev tail_ratio trimmed_mean
+.system: avx2 avx512f cpu_model ctz isolated kernel lzcnt ncpus numa_nodes physical_cores popcnt ptselect smt_threads";
+
+    #[test]
+    fn json_keys_match_golden() {
+        let json = serde_json::to_value(fixture()).unwrap();
+        assert_eq!(json["schema_version"], 1);
+
+        let mut lines = Vec::new();
+        key_lines(&json, "", &mut lines);
+        assert_eq!(lines.join("\n"), GOLDEN_KEYS);
+    }
+}
@@ -0,0 +1,83 @@
+// Scheduler counters from /proc/schedstat.
+//
+// Every `cpuN` line carries cumulative per-runqueue counts: field 3 is
+// schedule() calls, 5 is try_to_wake_up() calls made on the CPU and 6 the
+// subset that woke the task onto that same CPU. A snapshot sums them over
+// all CPUs; the difference of two snapshots covers the phase between
+// them. The kernel only updates them while `kernel.sched_schedstats` is 1.
+
+use std::fs;
+
+use serde::Serialize;
+
+#[derive(Clone, Copy, Default)]
+pub struct SchedSnapshot {
+    switches: u64,
+    wakeups: u64,
+    local_wakeups: u64,
+}
+
+/// Scheduler activity over one or more phases, all CPUs together.
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct SchedDelta {
+    /// schedule() calls: context switches plus a few no-op reschedules.
+    pub switches: u64,
+    pub wakeups: u64,
+    /// Wakeups that put the task on a CPU other than the waker's: the
+    /// placement the selector decides, and where its migrations show up.
+    pub remote_wakeups: u64,
+}
+
+impl SchedSnapshot {
+    /// `None` without /proc/schedstat (kernels built without
+    /// CONFIG_SCHEDSTATS) or when it has no parseable `cpuN` line.
+    pub fn take() -> Option<Self> {
+        let text = fs::read_to_string("/proc/schedstat").ok()?;
+        let mut snap = SchedSnapshot::default();
+        let mut any = false;
+        for line in text.lines().filter(|l| l.starts_with("cpu")) {
+            let fields: Vec<u64> = line
+                .split_whitespace()
+                .skip(1)
+                .map_while(|f| f.parse().ok())
+                .collect();
+            if fields.len() < 6 {
+                continue;
+            }
+            snap.switches += fields[2];
+            snap.wakeups += fields[4];
+            snap.local_wakeups += fields[5];
+            any = true;
+        }
+        any.then_some(snap)
+    }
+
+    /// `None` when nothing counted in between: schedstats are compiled in
+    /// but switched off.
+    pub fn since(&self, earlier: &SchedSnapshot) -> Option<SchedDelta> {
+        let wakeups = self.wakeups.saturating_sub(earlier.wakeups);
+        let local = self.local_wakeups.saturating_sub(earlier.local_wakeups);
+        let delta = SchedDelta {
+            switches: self.switches.saturating_sub(earlier.switches),
+            wakeups,
+            remote_wakeups: wakeups.saturating_sub(local),
+        };
+        (delta.switches > 0 || delta.wakeups > 0).then_some(delta)
+    }
+}
+
+impl SchedDelta {
+    pub fn rows(&self) -> [(&'static str, u64); 3] {
+        [
+            ("switches", self.switches),
+            ("wakeups", self.wakeups),
+            ("migrations", self.remote_wakeups),
+        ]
+    }
+
+    pub fn add(&mut self, other: &SchedDelta) {
+        self.switches += other.switches;
+        self.wakeups += other.wakeups;
+        self.remote_wakeups += other.remote_wakeups;
+    }
+}
@@ -1,30 +1,174 @@
-/// Log2-scaled histogram buckets in microseconds.
-/// Buckets: [0,1), [1,2), [2,4), [4,8), [8,16), [16,32), [32,64), [64,128), [128+)
-pub const NUM_BUCKETS: usize = 9;
-pub const BUCKET_LABELS: [&str; NUM_BUCKETS] = [
-    " <1 ", "  1 ", "  2 ", "  4 ", "  8 ", " 16 ", " 32 ", " 64 ", "128+",
-];
+use serde::Serialize;
+
+use crate::rng::Rng;
+
+/// Resamples drawn for each bootstrap confidence interval.
+pub const BOOTSTRAP_RESAMPLES: usize = 1000;
+
+/// Most draws per bootstrap resample; larger sample sets are resampled
+/// m-out-of-n and the spread scaled back to the full size.
+pub const BOOTSTRAP_MAX_DRAWS: usize = 10_000;
+
+/// Default `trim_frac` for [`StatResult::compute`] (`--trim 1`).
+pub const DEFAULT_TRIM_FRAC: f64 = 0.01;
+
+/// Log2 buckets: [0,1), [1,2), [2,4), [4,8), [8,16), [16,32), [32,64),
+/// [64,128), [128+) microseconds.
+const LOG2_BUCKETS: usize = 9;
+
+/// How samples are split into histogram buckets. The last bucket is always
+/// open-ended.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Bucketing {
+    #[default]
+    Log2,
+    /// `count` buckets of `width_us` each, starting at 0.
+    Linear { width_us: u64, count: usize },
+}
+
+impl Bucketing {
+    /// Parse `log2` or `linear:<width_us>:<count>`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = s.split(':');
+        match parts.next() {
+            Some("log2") if parts.next().is_none() => Ok(Bucketing::Log2),
+            Some("linear") => {
+                let mut num = |what: &str| {
+                    parts
+                        .next()
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .filter(|&v| v > 0)
+                        .ok_or_else(|| format!("linear: missing or invalid {what}"))
+                };
+                let width_us = num("width")?;
+                let count = num("count")?;
+                if count < 2 {
+                    return Err("linear: need at least 2 buckets".into());
+                }
+                if parts.next().is_some() {
+                    return Err("expected linear:<width_us>:<count>".into());
+                }
+                Ok(Bucketing::Linear {
+                    width_us,
+                    count: count as usize,
+                })
+            }
+            _ => Err("expected log2 or linear:<width_us>:<count>".into()),
+        }
+    }
+
+    pub fn num_buckets(&self) -> usize {
+        match *self {
+            Bucketing::Log2 => LOG2_BUCKETS,
+            Bucketing::Linear { count, .. } => count,
+        }
+    }
+
+    fn bucket_of(&self, ns: u64) -> usize {
+        let us = ns / 1000; // ns → μs
+        let last = self.num_buckets() - 1;
+        match *self {
+            Bucketing::Log2 => ((u64::BITS - us.leading_zeros()) as usize).min(last),
+            Bucketing::Linear { width_us, .. } => ((us / width_us) as usize).min(last),
+        }
+    }
 
-#[derive(Clone, Default)]
+    /// Bucket bounds in microseconds as `[lo, hi)`; `hi` is `None` for the
+    /// open-ended last bucket.
+    pub fn range_us(&self, bucket: usize) -> (u64, Option<u64>) {
+        let open = bucket + 1 >= self.num_buckets();
+        let (lo, hi) = match *self {
+            Bucketing::Log2 if bucket == 0 => (0, 1),
+            Bucketing::Log2 => (1 << (bucket - 1), 1 << bucket),
+            Bucketing::Linear { width_us, .. } => {
+                (bucket as u64 * width_us, (bucket as u64 + 1) * width_us)
+            }
+        };
+        (lo, (!open).then_some(hi))
+    }
+
+    /// Row labels for the histogram, four columns wide where they fit.
+    pub fn labels(&self) -> Vec<String> {
+        (0..self.num_buckets())
+            .map(|b| match self.range_us(b) {
+                (0, Some(1)) => " <1 ".to_string(),
+                (lo, Some(_)) => format!("{lo:>3} "),
+                (lo, None) => format!("{:>4}", format!("{lo}+")),
+            })
+            .collect()
+    }
+}
+
+/// Summary statistics of a set of latency samples; all values in ns.
+#[derive(Clone, Default, Serialize)]
 pub struct StatResult {
     pub mean: f64,
+    /// Geometric mean, of `sample + 1` ns so zero samples don't collapse
+    /// it; steadier than `mean` across rounds with different scales.
+    pub geomean: f64,
+    /// Mean with the lowest and highest `trim_frac` of samples dropped.
     pub trimmed_mean: f64,
     pub stddev: f64,
     pub min: u64,
     pub max: u64,
-    pub p50: u64,
-    pub p99: u64,
+    /// Percentiles interpolated between samples, see [`percentile`].
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub p999: f64,
     pub count: usize,
+    /// Samples outside the Q1 - 3*IQR .. Q3 + 3*IQR fences.
+    pub outlier_count: usize,
+    /// Largest such sample (0 when there are none).
+    pub max_outlier_ns: u64,
 }
 
-#[derive(Clone, Default)]
+/// The `q` quantile (0..=1) of ascending `sorted`, interpolated linearly
+/// between the two nearest order statistics (Hyndman-Fan type 7, the
+/// default of R and NumPy). 0 for no samples.
+pub fn percentile(sorted: &[u64], q: f64) -> f64 {
+    let Some(&last) = sorted.last() else {
+        return 0.0;
+    };
+    let pos = (sorted.len() - 1) as f64 * q.clamp(0.0, 1.0);
+    let lo = pos.floor() as usize;
+    match sorted.get(lo + 1) {
+        Some(&hi) => {
+            let lo_v = sorted[lo] as f64;
+            lo_v + (hi as f64 - lo_v) * (pos - lo as f64)
+        }
+        None => last as f64,
+    }
+}
+
+/// Percentiles (0..100) of a `--cdf` curve: every 0.1 up to 99.9, then
+/// 99.99 for the far tail.
+pub fn cdf_percentiles() -> impl Iterator<Item = f64> {
+    (1..=999).map(|i| i as f64 / 10.0).chain([99.99])
+}
+
+/// Latency (ns) at each of [`cdf_percentiles`]; sorts `samples` in place.
+pub fn cdf(samples: &mut [u64]) -> Vec<f64> {
+    samples.sort_unstable();
+    cdf_percentiles()
+        .map(|p| percentile(samples, p / 100.0))
+        .collect()
+}
+
+/// Sample counts per bucket of `bucketing`.
+#[derive(Clone, Serialize)]
 pub struct Histogram {
-    pub buckets: [u32; NUM_BUCKETS],
+    pub bucketing: Bucketing,
+    pub buckets: Vec<u32>,
     pub total: u32,
 }
 
 impl StatResult {
-    pub fn compute(samples: &mut [u64]) -> Self {
+    /// Statistics of `samples` (ns); sorts the slice in place. `trim_frac`
+    /// (0..0.5) of the samples is dropped from each end for `trimmed_mean`.
+    pub fn compute(samples: &mut [u64], trim_frac: f64) -> Self {
         if samples.is_empty() {
             return Self::default();
         }
@@ -32,11 +176,16 @@ impl StatResult {
         let n = samples.len();
         let min = samples[0];
         let max = samples[n - 1];
-        let p50 = samples[n / 2];
-        let p99 = samples[((n - 1) as f64 * 0.99) as usize];
+        let p50 = percentile(samples, 0.50);
+        let p90 = percentile(samples, 0.90);
+        let p95 = percentile(samples, 0.95);
+        let p99 = percentile(samples, 0.99);
+        let p999 = percentile(samples, 0.999);
 
         let sum: f64 = samples.iter().map(|&v| v as f64).sum();
         let mean = sum / n as f64;
+        let ln_sum: f64 = samples.iter().map(|&v| (v as f64 + 1.0).ln()).sum();
+        let geomean = (ln_sum / n as f64).exp();
 
         let var: f64 = if n > 1 {
             samples
@@ -51,59 +200,194 @@ impl StatResult {
             0.0
         };
 
-        // IQR-based outlier removal: filter values outside Q1 - 3*IQR to Q3 + 3*IQR
-        let q1_idx = n / 4;
-        let q3_idx = 3 * n / 4;
-        let q1 = samples[q1_idx] as f64;
-        let q3 = samples[q3_idx] as f64;
+        // At least one sample always survives the trim, even for tiny n.
+        let trim = ((n as f64 * trim_frac.max(0.0)) as usize).min((n - 1) / 2);
+        let (lo, hi) = (trim, n - trim);
+        let trimmed_mean =
+            samples[lo..hi].iter().map(|&v| v as f64).sum::<f64>() / (hi - lo) as f64;
+
+        // IQR-based outlier count: values outside Q1 - 3*IQR to Q3 + 3*IQR,
+        // with the quartiles taken like every other percentile here. The
+        // fences are rounded inward to whole ns: samples are integers.
+        let q1 = percentile(samples, 0.25);
+        let q3 = percentile(samples, 0.75);
         let iqr = q3 - q1;
-        let lower = (q1 - 3.0 * iqr).max(0.0) as u64;
+        let lower = (q1 - 3.0 * iqr).max(0.0).ceil() as u64;
         let upper = (q3 + 3.0 * iqr) as u64;
-        let filtered: Vec<u64> = samples
-            .iter()
-            .filter(|&&v| v >= lower && v <= upper)
-            .copied()
-            .collect();
-        let trimmed_mean = if !filtered.is_empty() {
-            filtered.iter().map(|&v| v as f64).sum::<f64>() / filtered.len() as f64
+        let outlier_count = samples.iter().filter(|&&v| v < lower || v > upper).count();
+        // Sorted, so any outlier above the fence is the max; otherwise the
+        // largest one is the last below it.
+        let max_outlier_ns = if outlier_count == 0 {
+            0
+        } else if max > upper {
+            max
         } else {
-            mean
+            samples
+                .iter()
+                .rev()
+                .find(|&&v| v < lower)
+                .copied()
+                .unwrap_or(0)
         };
 
         Self {
             mean,
+            geomean,
             trimmed_mean,
             stddev: var.sqrt(),
             min,
             max,
             p50,
+            p90,
+            p95,
             p99,
+            p999,
             count: n,
+            outlier_count,
+            max_outlier_ns,
         }
     }
 
+    /// Combine per-round results, weighting each round by its sample
+    /// count (rounds differ in size with `--duration`). Mean and stddev are
+    /// exactly those of the concatenated samples: the pooled sum of squares
+    /// adds each round's spread around its own mean to the spread of the
+    /// round means around the overall mean. Trimmed mean and percentiles
+    /// are count-weighted averages (the samples themselves are gone);
+    /// min/max are taken over all rounds. Ratios such as `tail_ratio` follow
+    /// from the merged percentiles, not from averaging per-round ratios.
     pub fn merge(results: &[StatResult]) -> Self {
-        if results.is_empty() {
+        let count: usize = results.iter().map(|r| r.count).sum();
+        if count == 0 {
             return Self::default();
         }
-        let n = results.len() as f64;
-        let mean = results.iter().map(|r| r.mean).sum::<f64>() / n;
-        let trimmed_mean = results.iter().map(|r| r.trimmed_mean).sum::<f64>() / n;
-        let stddev = (results.iter().map(|r| r.stddev * r.stddev).sum::<f64>() / n).sqrt();
-        let min = results.iter().map(|r| r.min).min().unwrap_or(0);
-        let max = results.iter().map(|r| r.max).max().unwrap_or(0);
-        let p50 = (results.iter().map(|r| r.p50 as f64).sum::<f64>() / n) as u64;
-        let p99 = (results.iter().map(|r| r.p99 as f64).sum::<f64>() / n) as u64;
-        let count = results.iter().map(|r| r.count).sum();
+        let total = count as f64;
+        let weighted = |f: &dyn Fn(&StatResult) -> f64| {
+            results.iter().map(|r| f(r) * r.count as f64).sum::<f64>() / total
+        };
+        let mean = weighted(&|r| r.mean);
+        // Multiplicative: the count-weighted mean of the logs.
+        let geomean = weighted(&|r| r.geomean.max(1.0).ln()).exp();
+        let trimmed_mean = weighted(&|r| r.trimmed_mean);
+        let ss: f64 = results
+            .iter()
+            .map(|r| {
+                let n = r.count as f64;
+                let d = r.mean - mean;
+                (n - 1.0).max(0.0) * r.stddev * r.stddev + n * d * d
+            })
+            .sum();
+        let stddev = if count > 1 {
+            (ss / (total - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        let nonempty = || results.iter().filter(|r| r.count > 0);
+        let min = nonempty().map(|r| r.min).min().unwrap_or(0);
+        let max = nonempty().map(|r| r.max).max().unwrap_or(0);
+        let avg = |f: fn(&StatResult) -> f64| weighted(&f);
+        let p50 = avg(|r| r.p50);
+        let p90 = avg(|r| r.p90);
+        let p95 = avg(|r| r.p95);
+        let p99 = avg(|r| r.p99);
+        let p999 = avg(|r| r.p999);
+        let outlier_count = results.iter().map(|r| r.outlier_count).sum();
+        let max_outlier_ns = results.iter().map(|r| r.max_outlier_ns).max().unwrap_or(0);
         Self {
             mean,
+            geomean,
             trimmed_mean,
             stddev,
             min,
             max,
             p50,
+            p90,
+            p95,
             p99,
+            p999,
             count,
+            outlier_count,
+            max_outlier_ns,
+        }
+    }
+
+    /// Percentile-bootstrap confidence interval for the mean of `samples`:
+    /// `resamples` means of same-size draws with replacement, cut at the
+    /// `(1 - confidence) / 2` tails. Returns `(lower, upper)` in ns.
+    ///
+    /// Past `BOOTSTRAP_MAX_DRAWS` samples each resample draws only that
+    /// many, and its distance from the full mean is scaled by
+    /// sqrt(m / n): the standard error of a mean shrinks with the root of
+    /// the count. That keeps a long run's interval to a few tens of
+    /// milliseconds instead of seconds with the screen frozen.
+    pub fn bootstrap_ci(
+        samples: &[u64],
+        confidence: f64,
+        resamples: usize,
+        rng: &mut Rng,
+    ) -> (f64, f64) {
+        if samples.is_empty() || resamples == 0 {
+            return (0.0, 0.0);
+        }
+        let n = samples.len();
+        let m = n.min(BOOTSTRAP_MAX_DRAWS);
+        let mean = samples.iter().map(|&s| s as f64).sum::<f64>() / n as f64;
+        let scale = (m as f64 / n as f64).sqrt();
+        let mut means: Vec<f64> = (0..resamples)
+            .map(|_| {
+                let sum: u64 = (0..m)
+                    .map(|_| samples[(rng.next_u64() % n as u64) as usize])
+                    .sum();
+                mean + (sum as f64 / m as f64 - mean) * scale
+            })
+            .collect();
+        means.sort_unstable_by(f64::total_cmp);
+        let tail = (1.0 - confidence.clamp(0.0, 1.0)) / 2.0;
+        let lo = ((resamples - 1) as f64 * tail).round() as usize;
+        let hi = ((resamples - 1) as f64 * (1.0 - tail)).round() as usize;
+        (means[lo], means[hi])
+    }
+
+    /// Sample standard deviation of the per-round trimmed means: how much
+    /// the headline number drifts from round to round.
+    pub fn between_round_stddev(results: &[StatResult]) -> f64 {
+        if results.len() < 2 {
+            return 0.0;
+        }
+        let n = results.len() as f64;
+        let mean = results.iter().map(|r| r.trimmed_mean).sum::<f64>() / n;
+        let var = results
+            .iter()
+            .map(|r| {
+                let d = r.trimmed_mean - mean;
+                d * d
+            })
+            .sum::<f64>()
+            / (n - 1.0);
+        var.sqrt()
+    }
+
+    /// Coefficient of variation, stddev / mean; 0 without samples.
+    pub fn cov(&self) -> f64 {
+        if self.mean <= 0.0 {
+            0.0
+        } else {
+            self.stddev / self.mean
+        }
+    }
+
+    /// Tail jitter: how far p99 sits above the median (ns).
+    pub fn jitter(&self) -> f64 {
+        self.p99 - self.p50
+    }
+
+    /// Tail amplification, p99 / p50: how many times slower the tail is
+    /// than a typical wakeup. 0 when the median is 0.
+    pub fn tail_ratio(&self) -> f64 {
+        if self.p50 <= 0.0 {
+            0.0
+        } else {
+            self.p99 / self.p50
         }
     }
 
@@ -116,28 +400,37 @@ impl StatResult {
     }
 }
 
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new(Bucketing::default())
+    }
+}
+
 impl Histogram {
-    pub fn from_samples(samples: &[u64]) -> Self {
-        let mut h = Self::default();
-        for &ns in samples {
-            let us = ns / 1000; // ns → μs
-            let bucket = match us {
-                0 => 0,
-                1 => 1,
-                2..=3 => 2,
-                4..=7 => 3,
-                8..=15 => 4,
-                16..=31 => 5,
-                32..=63 => 6,
-                64..=127 => 7,
-                _ => 8,
-            };
-            h.buckets[bucket] += 1;
-            h.total += 1;
+    pub fn new(bucketing: Bucketing) -> Self {
+        Self {
+            bucketing,
+            buckets: vec![0; bucketing.num_buckets()],
+            total: 0,
         }
+    }
+
+    pub fn from_samples(samples: &[u64], bucketing: Bucketing) -> Self {
+        let mut h = Self::new(bucketing);
+        h.add_samples(samples);
         h
     }
 
+    /// Add more samples to the counts; equivalent to rebuilding from the
+    /// concatenation of everything added so far.
+    pub fn add_samples(&mut self, samples: &[u64]) {
+        for &ns in samples {
+            let bucket = self.bucketing.bucket_of(ns);
+            self.buckets[bucket] += 1;
+            self.total += 1;
+        }
+    }
+
     pub fn fraction(&self, bucket: usize) -> f64 {
         if self.total == 0 {
             0.0
@@ -145,4 +438,275 @@ impl Histogram {
             self.buckets[bucket] as f64 / self.total as f64
         }
     }
+
+    /// Fraction of samples in buckets `0..=bucket`.
+    pub fn cumulative_fraction(&self, bucket: usize) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.cumulative_count(bucket) as f64 / self.total as f64
+        }
+    }
+
+    pub fn cumulative_count(&self, bucket: usize) -> u32 {
+        self.buckets[..=bucket].iter().sum()
+    }
+}
+
+/// Power below which a planned run is reported as underpowered.
+pub const MIN_POWER: f64 = 0.8;
+
+/// Approximate power of a two-sided two-sample z-test at p < 0.05 to detect
+/// a relative change `effect` (0.02 = 2%) in the mean, given the per-sample
+/// `mean`/`stddev` and `n` samples per mode. Latency samples are skewed and
+/// not fully independent, so treat the result as optimistic.
+pub fn power_two_sample(mean: f64, stddev: f64, n: usize, effect: f64) -> f64 {
+    const Z_975: f64 = 1.959_964;
+    if n == 0 || stddev <= 0.0 {
+        return if effect != 0.0 { 1.0 } else { 0.0 };
+    }
+    let se = stddev * (2.0 / n as f64).sqrt();
+    let z = (effect * mean).abs() / se;
+    normal_cdf(z - Z_975) + normal_cdf(-z - Z_975)
+}
+
+/// Two-sided 95% Student t quantiles for 1..=30 degrees of freedom; the
+/// normal 1.96 beyond.
+const T_975: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// Mean and 95% t-interval half-width (both in percent) of the per-round
+/// ON vs OFF change of the trimmed mean, pairing `on[i]` with `off[i]`.
+/// `None` below two pairs.
+pub fn round_delta_ci(on: &[StatResult], off: &[StatResult]) -> Option<(f64, f64)> {
+    let deltas: Vec<f64> = on
+        .iter()
+        .zip(off)
+        .filter(|(_, off)| off.trimmed_mean > 0.0)
+        .map(|(on, off)| (on.trimmed_mean - off.trimmed_mean) / off.trimmed_mean * 100.0)
+        .collect();
+    let n = deltas.len();
+    if n < 2 {
+        return None;
+    }
+    let mean = deltas.iter().sum::<f64>() / n as f64;
+    let var = deltas.iter().map(|d| (d - mean) * (d - mean)).sum::<f64>() / (n - 1) as f64;
+    let t = T_975.get(n - 2).copied().unwrap_or(1.959_964);
+    Some((mean, t * (var / n as f64).sqrt()))
+}
+
+/// Per-round ON vs OFF change of p99 in percent, pairing `on[i]` with
+/// `off[i]`; rounds where OFF recorded nothing are skipped.
+pub fn round_p99_deltas(on: &[StatResult], off: &[StatResult]) -> Vec<f64> {
+    on.iter()
+        .zip(off)
+        .filter(|(_, off)| off.p99 > 0.0)
+        .map(|(on, off)| (on.p99 - off.p99) / off.p99 * 100.0)
+        .collect()
+}
+
+/// Sample standard deviation of `values`; 0 below two.
+pub fn sample_stddev(values: &[f64]) -> f64 {
+    let n = values.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let mean = values.iter().sum::<f64>() / n as f64;
+    let var = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / (n - 1) as f64;
+    var.sqrt()
+}
+
+/// p-value below which the ON/OFF difference is reported as significant.
+pub const SIGNIFICANCE_ALPHA: f64 = 0.05;
+
+/// Two-sided Mann-Whitney U test of `a` vs `b`, using the normal
+/// approximation with tie correction (the sample counts here are always
+/// large). Returns the p-value; 1.0 if either side is empty.
+pub fn mann_whitney_u(a: &[u64], b: &[u64]) -> f64 {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    if a.is_empty() || b.is_empty() {
+        return 1.0;
+    }
+    let mut all: Vec<(u64, bool)> = a
+        .iter()
+        .map(|&v| (v, true))
+        .chain(b.iter().map(|&v| (v, false)))
+        .collect();
+    all.sort_unstable_by_key(|&(v, _)| v);
+
+    // Rank sum of `a`, giving tied values their average rank.
+    let mut rank_sum_a = 0.0;
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < all.len() {
+        let mut j = i;
+        while j < all.len() && all[j].0 == all[i].0 {
+            j += 1;
+        }
+        let avg_rank = (i + j + 1) as f64 / 2.0;
+        let in_a = all[i..j].iter().filter(|&&(_, from_a)| from_a).count();
+        rank_sum_a += avg_rank * in_a as f64;
+        let t = (j - i) as f64;
+        tie_term += t * t * t - t;
+        i = j;
+    }
+
+    let n = n1 + n2;
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let mu = n1 * n2 / 2.0;
+    let var = n1 * n2 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+    if var <= 0.0 {
+        return 1.0;
+    }
+    // Continuity correction towards the mean.
+    let z = ((u - mu).abs() - 0.5).max(0.0) / var.sqrt();
+    (2.0 * (1.0 - normal_cdf(z))).min(1.0)
+}
+
+fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+// Abramowitz & Stegun 7.1.26, |error| < 1.5e-7.
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Latencies spread over every log2 bucket, the open-ended one included.
+    fn spread_samples(n: usize, seed: u64) -> Vec<u64> {
+        let mut rng = Rng::new(seed);
+        (0..n)
+            .map(|_| rng.next_u64() >> (rng.next_u64() % 64))
+            .collect()
+    }
+
+    #[test]
+    fn histogram_add_samples_matches_from_scratch() {
+        let samples = spread_samples(10_000, 1);
+        for bucketing in [
+            Bucketing::Log2,
+            Bucketing::Linear {
+                width_us: 5,
+                count: 20,
+            },
+        ] {
+            let whole = Histogram::from_samples(&samples, bucketing);
+            let mut incremental = Histogram::new(bucketing);
+            for chunk in samples.chunks(777) {
+                incremental.add_samples(chunk);
+            }
+            incremental.add_samples(&[]);
+            assert_eq!(incremental.buckets, whole.buckets);
+            assert_eq!(incremental.total, whole.total);
+            assert_eq!(whole.total as usize, samples.len());
+        }
+    }
+
+    #[test]
+    fn mann_whitney_u_known_values() {
+        // Fully separated groups of 10: U = 0, z = (50 - 0.5) / sqrt(175),
+        // p = 1.8267e-4 (SciPy's asymptotic test agrees).
+        let a: Vec<u64> = (1..=10).collect();
+        let b: Vec<u64> = (11..=20).collect();
+        assert!((mann_whitney_u(&a, &b) - 1.826_718e-4).abs() < 1e-6);
+        assert!((mann_whitney_u(&b, &a) - 1.826_718e-4).abs() < 1e-6);
+
+        // Ties across groups: U = 6 with average ranks, tie-corrected
+        // variance 21.806, p = 0.19883.
+        let p = mann_whitney_u(&[1, 2, 2, 3, 4], &[2, 3, 3, 5, 6]);
+        assert!((p - 0.198_829).abs() < 1e-5);
+    }
+
+    #[test]
+    fn mann_whitney_u_degenerate_inputs() {
+        // Every value tied: no variance, nothing to tell apart.
+        assert_eq!(mann_whitney_u(&[7; 20], &[7; 30]), 1.0);
+        assert_eq!(mann_whitney_u(&[], &[1, 2, 3]), 1.0);
+        assert_eq!(mann_whitney_u(&[1, 2, 3], &[]), 1.0);
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= 1e-9 * b.abs().max(1.0), "{a} != {b}");
+    }
+
+    #[test]
+    fn merge_matches_concatenated_samples() {
+        // Rounds of different sizes, one of a single sample and one empty.
+        let all = spread_samples(3_001, 2);
+        let rounds = [
+            &all[..1_000],
+            &all[1_000..1_001],
+            &all[1_001..1_001],
+            &all[1_001..],
+        ];
+        let per_round: Vec<StatResult> = rounds
+            .iter()
+            .map(|r| StatResult::compute(&mut r.to_vec(), DEFAULT_TRIM_FRAC))
+            .collect();
+        let merged = StatResult::merge(&per_round);
+        let whole = StatResult::compute(&mut all.clone(), DEFAULT_TRIM_FRAC);
+
+        assert_eq!(merged.count, whole.count);
+        assert_close(merged.mean, whole.mean);
+        assert_close(merged.stddev, whole.stddev);
+        assert_close(merged.geomean, whole.geomean);
+        assert_eq!(merged.min, whole.min);
+        assert_eq!(merged.max, whole.max);
+    }
+
+    #[test]
+    fn percentile_type7() {
+        assert_eq!(percentile(&[], 0.5), 0.0);
+        // n = 1: every quantile is the sample.
+        for q in [0.0, 0.5, 0.99, 1.0] {
+            assert_eq!(percentile(&[42], q), 42.0);
+        }
+        // n = 2: linear between the two.
+        assert_eq!(percentile(&[10, 20], 0.0), 10.0);
+        assert_eq!(percentile(&[10, 20], 0.25), 12.5);
+        assert_eq!(percentile(&[10, 20], 0.5), 15.0);
+        assert_eq!(percentile(&[10, 20], 0.99), 19.9);
+        assert_eq!(percentile(&[10, 20], 1.0), 20.0);
+        // R's quantile(1:5, type = 7) / NumPy's default.
+        let s = [1, 2, 3, 4, 5];
+        assert_eq!(percentile(&s, 0.25), 2.0);
+        assert_eq!(percentile(&s, 0.5), 3.0);
+        assert_close(percentile(&s, 0.9), 4.6);
+        // quantile(c(1, 3, 7, 15), c(.1, .5, .75)) = 1.6, 5, 9.
+        let s = [1, 3, 7, 15];
+        assert_close(percentile(&s, 0.1), 1.6);
+        assert_eq!(percentile(&s, 0.5), 5.0);
+        assert_eq!(percentile(&s, 0.75), 9.0);
+        // Out-of-range q is clamped.
+        assert_eq!(percentile(&s, -1.0), 1.0);
+        assert_eq!(percentile(&s, 2.0), 15.0);
+    }
+
+    #[test]
+    fn zero_trim_keeps_the_plain_mean() {
+        for n in [1, 2, 3, 100, 1_001] {
+            let mut samples: Vec<u64> = (0..n as u64).map(|i| i * i % 97).collect();
+            let r = StatResult::compute(&mut samples, 0.0);
+            assert_eq!(r.trimmed_mean, r.mean, "n = {n}");
+        }
+        // A trim still drops the extremes.
+        let mut samples = vec![1, 2, 3, 4, 1_000];
+        let r = StatResult::compute(&mut samples, 0.2);
+        assert_eq!(r.trimmed_mean, 3.0);
+        assert_eq!(r.mean, 202.0);
+    }
 }
@@ -0,0 +1,181 @@
+// Environment checks for `--strict` / `--strict-report`.
+//
+// Each check is independent and only observes the system (the FIFO probe
+// runs on a throwaway thread), so the report can be produced without
+// touching any state the benchmark later relies on.
+
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use crate::bench;
+use crate::system::{self, BenchParams};
+
+/// RT tasks may use this fraction of each period before throttling counts
+/// as "sufficient headroom" for the FIFO dispatcher.
+const MIN_RT_SHARE: f64 = 0.95;
+const RUNNABLE_SAMPLES: usize = 5;
+
+pub struct Check {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+pub fn run_checks(params: &BenchParams) -> Vec<Check> {
+    vec![
+        check_governor(),
+        check_rt_throttling(),
+        check_tracer(),
+        check_vdso(),
+        check_fifo(),
+        check_cpus(params),
+    ]
+}
+
+pub fn print_report(checks: &[Check]) {
+    println!("Strict-mode checks:");
+    for c in checks {
+        println!(
+            "  [{}] {:<14} {}",
+            if c.ok { " ok " } else { "FAIL" },
+            c.name,
+            c.detail
+        );
+    }
+}
+
+fn check_governor() -> Check {
+    let freq = system::FreqScaling::detect(system::online_cpus());
+    let others = freq.non_performance();
+    let detail = if freq.governors.is_empty() {
+        "no cpufreq (fixed frequency)".to_string()
+    } else if others.is_empty() {
+        "performance on all CPUs".to_string()
+    } else {
+        others.join(", ")
+    };
+    Check {
+        name: "governor",
+        ok: others.is_empty(),
+        detail,
+    }
+}
+
+fn check_rt_throttling() -> Check {
+    let read = |knob: &str| {
+        fs::read_to_string(format!("/proc/sys/kernel/{knob}"))
+            .ok()
+            .and_then(|s| s.trim().parse::<i64>().ok())
+    };
+    let (ok, detail) = match (read("sched_rt_runtime_us"), read("sched_rt_period_us")) {
+        (Some(-1), _) => (true, "disabled".to_string()),
+        (Some(rt), Some(period)) if period > 0 => {
+            let share = rt as f64 / period as f64;
+            (
+                share >= MIN_RT_SHARE,
+                format!("{rt}/{period}us ({:.0}% RT share)", share * 100.0),
+            )
+        }
+        _ => (false, "sched_rt_runtime_us unreadable".to_string()),
+    };
+    Check {
+        name: "rt-throttling",
+        ok,
+        detail,
+    }
+}
+
+fn check_tracer() -> Check {
+    let pid = fs::read_to_string("/proc/self/status").ok().and_then(|s| {
+        s.lines()
+            .find_map(|l| l.strip_prefix("TracerPid:"))
+            .and_then(|v| v.trim().parse::<i32>().ok())
+    });
+    let (ok, detail) = match pid {
+        Some(0) => (true, "none".to_string()),
+        Some(p) => (false, format!("traced by pid {p}")),
+        None => (false, "TracerPid unreadable".to_string()),
+    };
+    Check {
+        name: "tracer",
+        ok,
+        detail,
+    }
+}
+
+fn check_vdso() -> Check {
+    let ns = bench::clock_read_cost_ns();
+    Check {
+        name: "vdso-clock",
+        ok: ns <= bench::CLOCK_SYSCALL_THRESHOLD_NS,
+        detail: format!("{ns:.1}ns per clock read"),
+    }
+}
+
+fn check_fifo() -> Check {
+    // Probe on a throwaway thread so the main thread's policy is untouched.
+    let ok = thread::spawn(|| {
+        let param = libc::sched_param { sched_priority: 1 };
+        unsafe { libc::sched_setscheduler(0, libc::SCHED_FIFO, &param) == 0 }
+    })
+    .join()
+    .unwrap_or(false);
+    Check {
+        name: "sched-fifo",
+        ok,
+        detail: if ok {
+            "SCHED_FIFO available".to_string()
+        } else {
+            "sched_setscheduler(SCHED_FIFO) refused (need root or CAP_SYS_NICE)".to_string()
+        },
+    }
+}
+
+/// The run pins threads to specific CPUs, so they all have to be in our
+/// affinity mask, and nothing else should be runnable on them.
+fn check_cpus(params: &BenchParams) -> Check {
+    let ncpus = system::online_cpus();
+    let needed = 1 + params.n_background + params.n_workers * (1 + params.shadows_per_worker);
+    let allowed = bench::CpuSet::current().map_or(0, |s| s.count());
+    // procs_running counts this process too; take the minimum over a few
+    // samples so a momentary blip doesn't fail the check.
+    let others = (0..RUNNABLE_SAMPLES)
+        .filter_map(|i| {
+            if i > 0 {
+                thread::sleep(Duration::from_millis(20));
+            }
+            procs_running()
+        })
+        .min()
+        .map(|n| n.saturating_sub(1));
+
+    let mut problems = Vec::new();
+    if allowed < ncpus {
+        problems.push(format!("affinity allows {allowed} of {ncpus} CPUs"));
+    }
+    if needed > ncpus {
+        problems.push(format!("run needs {needed} CPUs, {ncpus} online"));
+    }
+    match others {
+        Some(0) => {}
+        Some(n) => problems.push(format!("{n} other runnable task(s)")),
+        None => problems.push("/proc/stat unreadable".to_string()),
+    }
+    Check {
+        name: "cpus",
+        ok: problems.is_empty(),
+        detail: if problems.is_empty() {
+            format!("{needed} of {ncpus} CPUs needed, all free")
+        } else {
+            problems.join(", ")
+        },
+    }
+}
+
+fn procs_running() -> Option<usize> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    stat.lines()
+        .find_map(|l| l.strip_prefix("procs_running"))
+        .and_then(|v| v.trim().parse().ok())
+}
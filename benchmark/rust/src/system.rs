@@ -1,47 +1,416 @@
 use std::collections::HashSet;
+use std::fmt;
 use std::fs;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde::Serialize;
 
 const SYSCTL_PATH: &str = "/proc/sys/kernel/sched_poc_selector";
+const SYSCTL_REL: &str = "sys/kernel/sched_poc_selector";
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct SystemInfo {
     pub ncpus: usize,
     pub physical_cores: usize,
     pub cpu_model: String,
+    #[serde(flatten)]
     pub hw_features: HwFeatures,
+    pub freq: FreqScaling,
+    /// CPUs isolated from the scheduler (`isolcpus=` / `nohz_full=`).
+    pub isolated: Vec<usize>,
+    /// NUMA nodes with online CPUs; empty without the node sysfs.
+    pub numa_nodes: Vec<NumaNode>,
+    /// Online hardware threads of each physical core, ascending and
+    /// ordered by first CPU; empty without the topology sysfs.
+    pub smt_siblings: Vec<Vec<usize>>,
+    /// Kernel release (`uname -r`).
+    pub kernel: String,
+}
+
+/// Whether the running kernel has the POC selector, judged by its sysctl.
+#[derive(Clone, Serialize)]
+#[serde(tag = "state", rename_all = "kebab-case")]
+pub enum PocSupport {
+    /// The knob exists and can be switched: ON/OFF comparison possible.
+    Present,
+    /// The knob exists but writing it back failed (usually not root).
+    PresentReadOnly { reason: String },
+    /// No knob anywhere: the kernel lacks the patch.
+    AbsentNoSysctl,
+}
+
+impl PocSupport {
+    /// Why a comparison is impossible, or `None` when it is possible.
+    pub fn banner(&self, kernel: &str) -> Option<String> {
+        match self {
+            PocSupport::Present => None,
+            PocSupport::PresentReadOnly { reason } => Some(format!(
+                "POC selector present in {kernel} but its sysctl is not writable ({reason}): single run only"
+            )),
+            PocSupport::AbsentNoSysctl => Some(format!(
+                "POC selector not found in kernel {kernel} (no sched_poc_selector sysctl): single run only"
+            )),
+        }
+    }
+}
+
+pub fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Probe the knob: read it, then write the same value back.
+pub fn detect_poc_support() -> PocSupport {
+    let Some(val) = poc_sysctl_read() else {
+        return PocSupport::AbsentNoSysctl;
+    };
+    match poc_sysctl_write(val) {
+        Ok(()) => PocSupport::Present,
+        Err(reason) => PocSupport::PresentReadOnly { reason },
+    }
 }
 
-#[derive(Clone)]
+/// [`detect_poc_support`] without the write-back, judging writability by
+/// permission alone: for `--dry-run`, which must leave the knob untouched.
+pub fn peek_poc_support() -> PocSupport {
+    let Some(path) = poc_sysctl_path() else {
+        return PocSupport::AbsentNoSysctl;
+    };
+    let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_encoded_bytes()) else {
+        return PocSupport::AbsentNoSysctl;
+    };
+    if unsafe { libc::access(c_path.as_ptr(), libc::W_OK) } == 0 {
+        PocSupport::Present
+    } else {
+        PocSupport::PresentReadOnly {
+            reason: format!("{}: {}", path.display(), std::io::Error::last_os_error()),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct NumaNode {
+    pub id: usize,
+    pub cpus: Vec<usize>,
+}
+
+/// Which NUMA node each worker (and so its shadows, which follow it) runs
+/// on (`--numa`). Only matters with more than one node.
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum NumaPlacement {
+    /// Every worker on the dispatcher's node: no cross-node wakeups.
+    Pack,
+    /// Workers round-robin over the nodes, starting at the one after the
+    /// dispatcher's, to measure cross-node wakeups on purpose.
+    Spread,
+    /// Every worker on this node.
+    Node { node: usize },
+}
+
+/// cpufreq state at startup. Frequency counts as pinned when every CPU
+/// with cpufreq runs the `performance` governor and boost is off.
+#[derive(Clone, Default, Serialize)]
+pub struct FreqScaling {
+    /// `(cpu, governor)` for every CPU exposing cpufreq.
+    pub governors: Vec<(usize, String)>,
+    /// `None` when neither the generic nor the intel_pstate knob exists.
+    pub boost: Option<bool>,
+}
+
+#[derive(Clone, Serialize)]
 pub struct HwFeatures {
     pub popcnt: &'static str,
     pub ctz: &'static str,
     pub ptselect: &'static str,
+    pub lzcnt: &'static str,
+    pub avx2: &'static str,
+    pub avx512f: &'static str,
+}
+
+/// How the warmup iterations at the start of each phase are discarded.
+#[derive(Clone, Copy, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum WarmupStrategy {
+    /// Drop a fixed number of iterations (from calibration or `--iterations`).
+    Fixed,
+    /// Drop iterations until the p50 of consecutive sliding windows differs
+    /// by at most `tolerance` (a fraction, e.g. 0.05).
+    Stable { tolerance: f64 },
+}
+
+/// Kernel path the dispatcher uses to wake workers. Futex wakeups skip the
+/// VFS layer that eventfd and pipe wakeups go through, so absolute
+/// latencies differ between mechanisms; compare POC ON/OFF within one.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WakeupMechanism {
+    Eventfd,
+    Futex,
+    Pipe,
+}
+
+/// What the background threads burn while the benchmark runs.
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BgWorkload {
+    /// Tight `spin_loop()`: pure CPU contention.
+    Spin,
+    /// Stream through a buffer larger than the caches: memory bandwidth
+    /// and cache thrashing.
+    Memory,
+    /// Back-to-back `getuid()`: constant kernel entries and exits.
+    Syscall,
+}
+
+impl BgWorkload {
+    pub fn label(self) -> &'static str {
+        match self {
+            BgWorkload::Spin => "spin",
+            BgWorkload::Memory => "memory",
+            BgWorkload::Syscall => "syscall",
+        }
+    }
 }
 
-#[derive(Clone)]
+/// Timestamp source for the wakeup latency itself (see `timer`).
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimerSource {
+    /// CLOCK_MONOTONIC via the vDSO.
+    Clock,
+    /// Calibrated invariant TSC (x86_64 only).
+    Tsc,
+}
+
+#[derive(Clone, Serialize)]
 pub struct BenchParams {
     pub n_workers: usize,
     pub n_background: usize,
     pub n_idle: usize,
     pub shadows_per_worker: usize,
+    /// Wakeups the dispatcher queues per worker before waiting for them.
+    pub queue_depth: usize,
+    pub warmup_strategy: WarmupStrategy,
+    /// Collect perf_event counters for each phase.
+    pub perf: bool,
+    /// Unpinned busy threads per online CPU competing with the workers
+    /// (0 = none).
+    pub oversubscribe: f64,
+    /// Fraction of samples dropped from each end for the trimmed mean.
+    pub trim_frac: f64,
+    /// SCHED_FIFO priorities assigned to workers round-robin (0 = normal).
+    pub worker_priorities: Vec<i32>,
+    /// Niceness of workers left at SCHED_OTHER (`--worker-nice`).
+    pub worker_nice: i32,
+    pub wakeup: WakeupMechanism,
+    pub bg_workload: BgWorkload,
+    pub timer: TimerSource,
+    /// Subtracted from every recorded latency, clamped at 0
+    /// (`--subtract-overhead`).
+    pub subtract_overhead_ns: u64,
+    /// Measure each phase for this long instead of a fixed iteration count
+    /// (the iteration count then only sizes the sample buffers).
+    pub phase_duration: Option<Duration>,
+    /// Workers and shadows run on these CPUs, the dispatcher and background
+    /// on the rest. Empty: sequential placement from CPU 0.
+    pub isolated_cpus: Vec<usize>,
+    /// Busy-spin each worker does after a wakeup (`--work-us`); 0 keeps
+    /// the brief fixed loop.
+    pub work_ns: u64,
+    /// Time each shadow request-to-ack round trip (`--measure-shadow`).
+    pub measure_shadow: bool,
+    /// Dispatcher pause after each batch of wakeups (`--settle-ns`).
+    pub settle_ns: u64,
+    /// `settle_ns` was probed (`--settle-ns auto`) rather than given.
+    #[serde(skip)]
+    pub settle_auto: bool,
+    /// Keep the dispatcher off the core every worker last ran on, so each
+    /// wakeup comes from another CPU (`--cross-cpu`).
+    pub cross_cpu: bool,
+    pub numa: NumaPlacement,
+    /// Node layout `numa` places workers on; empty (no NUMA placement)
+    /// on single-node machines.
+    #[serde(skip)]
+    pub numa_nodes: Vec<NumaNode>,
+    /// `--no-smt`: one CPU per physical core that workers (and so the
+    /// shadows that follow them) are confined to; empty = all threads.
+    pub smt_cpus: Vec<usize>,
+    /// Thread counts [`BenchParams::with_overrides`] had to change to fit
+    /// the online CPUs.
+    #[serde(skip)]
+    pub clamped: Vec<ThreadClamp>,
+}
+
+/// A requested thread count and the one the CPU budget allowed.
+#[derive(Clone, Debug)]
+pub struct ThreadClamp {
+    /// "workers" or "background".
+    pub what: &'static str,
+    pub requested: usize,
+    pub actual: usize,
+}
+
+impl fmt::Display for ThreadClamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} -> {}", self.what, self.requested, self.actual)
+    }
+}
+
+/// A thread request no clamping can make sense of.
+#[derive(Debug)]
+pub enum ParamsError {
+    /// Background threads alone would take every online CPU.
+    BackgroundExceedsCpus { requested: usize, ncpus: usize },
+}
+
+impl std::error::Error for ParamsError {}
+
+impl fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamsError::BackgroundExceedsCpus { requested, ncpus } => write!(
+                f,
+                "{requested} background thread(s) on {ncpus} online CPU(s) \
+                 leave none for the dispatcher and workers"
+            ),
+        }
+    }
+}
+
+impl NumaPlacement {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "pack" => Ok(NumaPlacement::Pack),
+            "spread" => Ok(NumaPlacement::Spread),
+            _ => s
+                .strip_prefix("node=")
+                .and_then(|n| n.parse().ok())
+                .map(|node| NumaPlacement::Node { node })
+                .ok_or_else(|| "expected pack, spread or node=<N>".into()),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            NumaPlacement::Pack => "pack".into(),
+            NumaPlacement::Spread => "spread".into(),
+            NumaPlacement::Node { node } => format!("node={node}"),
+        }
+    }
 }
 
 impl SystemInfo {
     pub fn detect() -> Self {
-        let ncpus = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) as usize };
+        let ncpus = online_cpus();
         let physical_cores = detect_physical_cores(ncpus);
         let cpu_model = read_cpu_model().unwrap_or_else(|| "Unknown".into());
         let hw_features = detect_hw_features();
+        let freq = FreqScaling::detect(ncpus);
+        let isolated = isolated_cpus(ncpus);
+        let numa_nodes = numa_nodes(ncpus);
+        let smt_siblings = smt_siblings(ncpus);
+        let kernel = fs::read_to_string("/proc/sys/kernel/osrelease")
+            .map(|s| s.trim().to_string())
+            .unwrap_or_else(|_| "unknown".into());
         Self {
             ncpus,
             physical_cores,
             cpu_model,
             hw_features,
+            freq,
+            isolated,
+            numa_nodes,
+            smt_siblings,
+            kernel,
+        }
+    }
+
+    /// Most hardware threads any core has; 1 without SMT (or topology).
+    pub fn smt_threads(&self) -> usize {
+        self.smt_siblings.iter().map(Vec::len).max().unwrap_or(1)
+    }
+
+    /// The first thread of every core, for `--no-smt`.
+    pub fn smt_primary_cpus(&self) -> Vec<usize> {
+        self.smt_siblings
+            .iter()
+            .filter_map(|s| s.first().copied())
+            .collect()
+    }
+
+    /// One-line SMT topology, e.g. `2 threads/core (8 cores, 16 CPUs)`.
+    pub fn smt_summary(&self) -> String {
+        match self.smt_threads() {
+            1 => "off (1 thread/core)".into(),
+            t => format!(
+                "{} threads/core ({} cores, {} CPUs)",
+                t,
+                self.smt_siblings.len(),
+                self.ncpus
+            ),
         }
     }
 }
 
+impl FreqScaling {
+    pub fn detect(ncpus: usize) -> Self {
+        let governors = (0..ncpus)
+            .filter_map(|cpu| {
+                fs::read_to_string(governor_path(cpu))
+                    .ok()
+                    .map(|g| (cpu, g.trim().to_string()))
+            })
+            .collect();
+        let read = |p: &str| fs::read_to_string(p).ok().map(|s| s.trim() == "1");
+        let boost = read("/sys/devices/system/cpu/cpufreq/boost")
+            .or_else(|| read("/sys/devices/system/cpu/intel_pstate/no_turbo").map(|nt| !nt));
+        Self { governors, boost }
+    }
+
+    /// CPUs not on the `performance` governor, as `cpuN=governor`.
+    pub fn non_performance(&self) -> Vec<String> {
+        self.governors
+            .iter()
+            .filter(|(_, g)| g != "performance")
+            .map(|(cpu, g)| format!("cpu{cpu}={g}"))
+            .collect()
+    }
+
+    pub fn pinned(&self) -> bool {
+        self.boost != Some(true) && self.governors.iter().all(|(_, g)| g == "performance")
+    }
+
+    /// One-line description of why frequency isn't pinned, if it isn't.
+    pub fn warning(&self) -> Option<String> {
+        if self.pinned() {
+            return None;
+        }
+        let mut others: Vec<&str> = self
+            .governors
+            .iter()
+            .map(|(_, g)| g.as_str())
+            .filter(|&g| g != "performance")
+            .collect();
+        let n = others.len();
+        others.sort_unstable();
+        others.dedup();
+        let mut parts = Vec::new();
+        if n > 0 {
+            parts.push(format!("governor {} on {} CPU(s)", others.join("/"), n));
+        }
+        if self.boost == Some(true) {
+            parts.push("boost enabled".to_string());
+        }
+        Some(format!(
+            "frequency scaling active ({}), expect noisier numbers",
+            parts.join(", ")
+        ))
+    }
+}
+
 impl BenchParams {
     #[allow(dead_code)]
     pub fn calculate(ncpus: usize, physical_cores: usize) -> Self {
@@ -49,14 +418,35 @@ impl BenchParams {
         Self::compute(ncpus, n_background, None)
     }
 
+    /// Explicit `workers`/`background` counts that do not fit are lowered
+    /// (workers raised to at least 1) and recorded in `clamped`; background
+    /// threads filling every CPU are an error.
     pub fn with_overrides(
         ncpus: usize,
         physical_cores: usize,
         workers: Option<usize>,
         background: Option<usize>,
-    ) -> Self {
+    ) -> Result<Self, ParamsError> {
+        if let Some(requested) = background.filter(|&b| b > 0 && b >= ncpus) {
+            return Err(ParamsError::BackgroundExceedsCpus { requested, ncpus });
+        }
         let n_background = background.unwrap_or(physical_cores * 3 / 4);
-        Self::compute(ncpus, n_background, workers)
+        let mut params = Self::compute(ncpus, n_background, workers);
+        if let Some(b) = background.filter(|&b| b != params.n_background) {
+            params.clamped.push(ThreadClamp {
+                what: "background",
+                requested: b,
+                actual: params.n_background,
+            });
+        }
+        if let Some(w) = workers.filter(|&w| w != params.n_workers) {
+            params.clamped.push(ThreadClamp {
+                what: "workers",
+                requested: w,
+                actual: params.n_workers,
+            });
+        }
+        Ok(params)
     }
 
     // ncpus = 1 (dispatcher) + bg + workers * (1 + shadows) + idle
@@ -75,44 +465,455 @@ impl BenchParams {
             n_background,
             n_idle,
             shadows_per_worker,
+            queue_depth: 1,
+            warmup_strategy: WarmupStrategy::Fixed,
+            perf: false,
+            oversubscribe: 0.0,
+            trim_frac: crate::stats::DEFAULT_TRIM_FRAC,
+            worker_priorities: Vec::new(),
+            worker_nice: 0,
+            phase_duration: None,
+            wakeup: WakeupMechanism::Eventfd,
+            bg_workload: BgWorkload::Spin,
+            timer: TimerSource::Clock,
+            subtract_overhead_ns: 0,
+            isolated_cpus: Vec::new(),
+            work_ns: 0,
+            measure_shadow: false,
+            settle_ns: crate::bench::DEFAULT_SETTLE_NS,
+            settle_auto: false,
+            cross_cpu: false,
+            numa: NumaPlacement::Pack,
+            numa_nodes: Vec::new(),
+            smt_cpus: Vec::new(),
+            clamped: Vec::new(),
+        }
+    }
+
+    /// Fewest online CPUs the run can be placed on: the dispatcher, one
+    /// worker and (unless disabled) its shadow each need their own.
+    pub fn min_cpus(&self) -> usize {
+        2 + self.shadows_per_worker.min(1)
+    }
+
+    /// CPUs for the dispatcher (first) and background threads: everything
+    /// not isolated, or all CPUs when there is no usable isolated set.
+    pub fn housekeeping_cpus(&self, ncpus: usize) -> Vec<usize> {
+        let hk: Vec<usize> = (0..ncpus)
+            .filter(|c| !self.isolated_cpus.contains(c))
+            .collect();
+        if hk.is_empty() {
+            (0..ncpus).collect()
+        } else {
+            hk
         }
     }
+
+    /// CPUs worker `w` may run on: its `numa` node, narrowed to the
+    /// isolated set when that leaves any, or just the isolated set without
+    /// NUMA placement; then narrowed to `smt_cpus` likewise. `None` leaves
+    /// the worker unrestricted.
+    pub fn worker_cpus(&self, w: usize, dispatcher_cpu: usize) -> Option<Vec<usize>> {
+        let cpus = self.node_cpus(w, dispatcher_cpu);
+        if self.smt_cpus.is_empty() {
+            return cpus;
+        }
+        let Some(cpus) = cpus else {
+            return Some(self.smt_cpus.clone());
+        };
+        let narrowed: Vec<usize> = cpus
+            .iter()
+            .copied()
+            .filter(|c| self.smt_cpus.contains(c))
+            .collect();
+        Some(if narrowed.is_empty() { cpus } else { narrowed })
+    }
+
+    fn node_cpus(&self, w: usize, dispatcher_cpu: usize) -> Option<Vec<usize>> {
+        let isolated = (!self.isolated_cpus.is_empty()).then(|| self.isolated_cpus.clone());
+        let nodes = &self.numa_nodes;
+        if nodes.len() < 2 {
+            return isolated;
+        }
+        let home = nodes
+            .iter()
+            .position(|n| n.cpus.contains(&dispatcher_cpu))
+            .unwrap_or(0);
+        let idx = match self.numa {
+            NumaPlacement::Pack => home,
+            NumaPlacement::Spread => (home + 1 + w) % nodes.len(),
+            NumaPlacement::Node { node } => nodes.iter().position(|n| n.id == node).unwrap_or(home),
+        };
+        let cpus = &nodes[idx].cpus;
+        let narrowed: Vec<usize> = cpus
+            .iter()
+            .copied()
+            .filter(|c| self.isolated_cpus.contains(c))
+            .collect();
+        Some(if narrowed.is_empty() {
+            cpus.clone()
+        } else {
+            narrowed
+        })
+    }
+
+    /// Run without shadow threads: workers wake wherever the scheduler puts
+    /// them. The shadows' CPUs go back to the idle pool.
+    pub fn disable_shadows(&mut self) {
+        self.n_idle += self.n_workers * self.shadows_per_worker;
+        self.shadows_per_worker = 0;
+    }
+
+    pub fn worker_priority(&self, worker: usize) -> i32 {
+        if self.worker_priorities.is_empty() {
+            0
+        } else {
+            self.worker_priorities[worker % self.worker_priorities.len()]
+        }
+    }
+
+    /// Distinct priority classes in first-seen order, when more than one.
+    pub fn priority_classes(&self) -> Vec<i32> {
+        let mut classes: Vec<i32> = Vec::new();
+        for w in 0..self.n_workers {
+            let p = self.worker_priority(w);
+            if !classes.contains(&p) {
+                classes.push(p);
+            }
+        }
+        if classes.len() > 1 {
+            classes
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Number of unpinned busy threads `oversubscribe` asks for.
+    pub fn oversubscribe_threads(&self, ncpus: usize) -> usize {
+        (self.oversubscribe.max(0.0) * ncpus as f64).round() as usize
+    }
+}
+
+pub fn online_cpus() -> usize {
+    unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) as usize }
+}
+
+/// Parse a kernel cpulist such as `2-5,8`. Returns `None` on anything else.
+pub fn parse_cpu_list(s: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for part in s.trim().split(',').filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((a, b)) => cpus.extend(a.parse::<usize>().ok()?..=b.parse::<usize>().ok()?),
+            None => cpus.push(part.parse().ok()?),
+        }
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    Some(cpus)
+}
+
+/// Inverse of [`parse_cpu_list`]; `cpus` must be sorted.
+pub fn format_cpu_list(cpus: &[usize]) -> String {
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < cpus.len() {
+        let mut j = i;
+        while j + 1 < cpus.len() && cpus[j + 1] == cpus[j] + 1 {
+            j += 1;
+        }
+        parts.push(if i == j {
+            cpus[i].to_string()
+        } else {
+            format!("{}-{}", cpus[i], cpus[j])
+        });
+        i = j + 1;
+    }
+    parts.join(",")
+}
+
+/// `(physical_package_id, core_id)` of `cpu`.
+pub(crate) fn package_core(cpu: usize) -> Option<(i32, i32)> {
+    let read = |f: &str| {
+        fs::read_to_string(format!("/sys/devices/system/cpu/cpu{cpu}/topology/{f}"))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+    Some((read("physical_package_id")?, read("core_id")?))
+}
+
+/// `cpu` and its hardware-thread siblings from
+/// `topology/thread_siblings_list`, keeping only online CPUs.
+fn thread_siblings(cpu: usize, ncpus: usize) -> Option<Vec<usize>> {
+    let list = fs::read_to_string(format!(
+        "/sys/devices/system/cpu/cpu{cpu}/topology/thread_siblings_list"
+    ))
+    .ok()?;
+    let cpus: Vec<usize> = parse_cpu_list(&list)?
+        .into_iter()
+        .filter(|&c| c < ncpus)
+        .collect();
+    (!cpus.is_empty()).then_some(cpus)
+}
+
+/// Hardware threads per physical core, from each CPU's siblings.
+fn smt_siblings(ncpus: usize) -> Vec<Vec<usize>> {
+    let mut cores: Vec<Vec<usize>> = (0..ncpus)
+        .filter_map(|cpu| thread_siblings(cpu, ncpus))
+        .collect();
+    cores.sort_unstable();
+    cores.dedup();
+    cores
+}
+
+/// Where one online CPU sits, as sysfs describes it (`--list-cpus`).
+/// Fields sysfs does not expose are `None`.
+pub struct CpuTopology {
+    pub cpu: usize,
+    pub package: Option<i32>,
+    pub core: Option<i32>,
+    pub siblings: Vec<usize>,
+    pub node: Option<usize>,
+    pub governor: Option<String>,
+}
+
+/// [`CpuTopology`] of every online CPU, in order.
+pub fn cpu_topology(ncpus: usize) -> Vec<CpuTopology> {
+    let nodes = numa_nodes(ncpus);
+    (0..ncpus)
+        .map(|cpu| {
+            let (package, core) = package_core(cpu).unzip();
+            CpuTopology {
+                cpu,
+                package,
+                core,
+                siblings: thread_siblings(cpu, ncpus).unwrap_or_default(),
+                node: nodes.iter().find(|n| n.cpus.contains(&cpu)).map(|n| n.id),
+                governor: fs::read_to_string(governor_path(cpu))
+                    .ok()
+                    .map(|g| g.trim().to_string()),
+            }
+        })
+        .collect()
+}
+
+/// NUMA nodes from /sys/devices/system/node, by id, keeping only online
+/// CPUs and nodes that have any (memory-only nodes have none).
+fn numa_nodes(ncpus: usize) -> Vec<NumaNode> {
+    let Ok(dir) = fs::read_dir("/sys/devices/system/node") else {
+        return Vec::new();
+    };
+    let mut nodes: Vec<NumaNode> = dir
+        .filter_map(|e| {
+            let e = e.ok()?;
+            let id = e.file_name().to_str()?.strip_prefix("node")?.parse().ok()?;
+            let list = fs::read_to_string(e.path().join("cpulist")).ok()?;
+            let cpus: Vec<usize> = parse_cpu_list(&list)?
+                .into_iter()
+                .filter(|&c| c < ncpus)
+                .collect();
+            (!cpus.is_empty()).then_some(NumaNode { id, cpus })
+        })
+        .collect();
+    nodes.sort_unstable_by_key(|n| n.id);
+    nodes
+}
+
+/// Online CPUs isolated with `isolcpus=` or `nohz_full=`. sysfs has both
+/// lists on any recent kernel; older ones only have the command line, where
+/// `isolcpus=` may carry flags (`domain,managed_irq,2-5`) ahead of the list.
+fn isolated_cpus(ncpus: usize) -> Vec<usize> {
+    let sysfs = ["isolated", "nohz_full"]
+        .iter()
+        .filter_map(|f| fs::read_to_string(format!("/sys/devices/system/cpu/{f}")).ok())
+        .collect::<Vec<_>>();
+    let lists: Vec<String> = if !sysfs.is_empty() {
+        sysfs
+    } else {
+        let cmdline = fs::read_to_string("/proc/cmdline").unwrap_or_default();
+        cmdline
+            .split_whitespace()
+            .filter_map(|arg| {
+                arg.strip_prefix("isolcpus=")
+                    .or_else(|| arg.strip_prefix("nohz_full="))
+            })
+            .map(|v| {
+                v.split(',')
+                    .filter(|p| p.starts_with(|c: char| c.is_ascii_digit()))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect()
+    };
+    let mut cpus: Vec<usize> = lists
+        .iter()
+        .filter_map(|l| parse_cpu_list(l))
+        .flatten()
+        .filter(|&c| c < ncpus)
+        .collect();
+    cpus.sort_unstable();
+    cpus.dedup();
+    cpus
+}
+
+/// Places the knob may be reached, in order of preference: the usual
+/// `/proc/sys` path, then the same file under any other procfs mount
+/// (containers and hardened setups sometimes mask or remount `/proc/sys`
+/// read-only while another proc instance stays usable).
+///
+/// There is no sysctl(2) fallback: the binary interface was removed in
+/// Linux 5.5 and never had a numeric id for this knob.
+///
+/// `--sysctl-path` replaces the whole list with the given file.
+fn sysctl_candidates() -> &'static [PathBuf] {
+    static CANDIDATES: OnceLock<Vec<PathBuf>> = OnceLock::new();
+    CANDIDATES.get_or_init(|| {
+        if let Some(p) = SYSCTL_OVERRIDE.get() {
+            return vec![p.clone()];
+        }
+        let mut paths = vec![PathBuf::from(SYSCTL_PATH)];
+        for mnt in procfs_mounts() {
+            let p = mnt.join(SYSCTL_REL);
+            if !paths.contains(&p) {
+                paths.push(p);
+            }
+        }
+        paths
+    })
+}
+
+static SYSCTL_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `path` as the only location of the knob (renamed knobs in kernel
+/// forks, procfs mounted elsewhere). Must be called before any other
+/// sysctl function.
+pub fn set_sysctl_path(path: PathBuf) {
+    SYSCTL_OVERRIDE.set(path).ok();
+}
+
+fn procfs_mounts() -> Vec<PathBuf> {
+    let Ok(info) = fs::read_to_string("/proc/self/mountinfo") else {
+        return Vec::new();
+    };
+    // mountinfo: "id parent major:minor root mountpoint opts... - fstype src opts"
+    info.lines()
+        .filter_map(|line| {
+            let (pre, post) = line.split_once(" - ")?;
+            if post.split_whitespace().next()? != "proc" {
+                return None;
+            }
+            pre.split_whitespace().nth(4).map(PathBuf::from)
+        })
+        .collect()
+}
+
+/// The path the knob is currently readable through, if any.
+pub fn poc_sysctl_path() -> Option<&'static Path> {
+    sysctl_candidates()
+        .iter()
+        .find(|p| read_knob(p).is_some())
+        .map(|p| p.as_path())
 }
 
 pub fn poc_sysctl_read() -> Option<i32> {
-    fs::read_to_string(SYSCTL_PATH)
+    sysctl_candidates().iter().find_map(|p| read_knob(p))
+}
+
+pub fn poc_sysctl_write(val: i32) -> Result<(), String> {
+    let mut errors = Vec::new();
+    for path in sysctl_candidates() {
+        match write_knob(path, val) {
+            Ok(()) => {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                return Ok(());
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    Err(errors.join("; "))
+}
+
+fn read_knob(path: &Path) -> Option<i32> {
+    fs::read_to_string(path)
         .ok()
         .and_then(|s| s.trim().parse().ok())
 }
 
-pub fn poc_sysctl_write(val: i32) -> Result<(), String> {
+fn write_knob(path: &Path, val: i32) -> Result<(), String> {
+    let p = path.display();
     let mut f = fs::OpenOptions::new()
         .write(true)
-        .open(SYSCTL_PATH)
-        .map_err(|e| format!("open({SYSCTL_PATH}): {e}"))?;
+        .open(path)
+        .map_err(|e| format!("open({p}): {e}"))?;
     // Single write_all call — writeln!/write! split output into multiple
     // write() syscalls, and procfs rejects the trailing "\n"-only write
     // with EINVAL. Formatting first ensures one atomic write(2).
     let buf = format!("{val}\n");
     f.write_all(buf.as_bytes())
-        .map_err(|e| format!("write({SYSCTL_PATH}): {e}"))?;
-    std::thread::sleep(std::time::Duration::from_millis(50));
-    Ok(())
+        .map_err(|e| format!("write({p}): {e}"))
 }
 
-fn detect_physical_cores(ncpus: usize) -> usize {
-    let mut cores = HashSet::new();
-    for cpu in 0..ncpus {
-        let pkg = fs::read_to_string(format!(
-            "/sys/devices/system/cpu/cpu{cpu}/topology/physical_package_id"
-        ));
-        let core = fs::read_to_string(format!("/sys/devices/system/cpu/cpu{cpu}/topology/core_id"));
-        if let (Ok(p), Ok(c)) = (pkg, core) {
-            if let (Ok(p), Ok(c)) = (p.trim().parse::<i32>(), c.trim().parse::<i32>()) {
-                cores.insert((p, c));
-            }
+fn governor_path(cpu: usize) -> PathBuf {
+    PathBuf::from(format!(
+        "/sys/devices/system/cpu/cpu{cpu}/cpufreq/scaling_governor"
+    ))
+}
+
+fn write_governor(cpu: usize, gov: &str) -> Result<(), String> {
+    let path = governor_path(cpu);
+    // One write(2), for the same reason as write_knob.
+    fs::write(&path, format!("{gov}\n")).map_err(|e| format!("write({}): {e}", path.display()))
+}
+
+/// Switch every CPU in `freq` to the `performance` governor and return the
+/// governors it replaced, for [`governor_restore`]. If a write fails the
+/// CPUs already switched are put back before the error is returned.
+pub fn governor_pin(freq: &FreqScaling) -> Result<Vec<(usize, String)>, String> {
+    let mut saved = Vec::new();
+    for (cpu, gov) in &freq.governors {
+        if gov == "performance" {
+            continue;
+        }
+        if let Err(e) = write_governor(*cpu, "performance") {
+            governor_restore(&saved);
+            return Err(e);
+        }
+        saved.push((*cpu, gov.clone()));
+    }
+    Ok(saved)
+}
+
+pub fn governor_restore(saved: &[(usize, String)]) {
+    for (cpu, gov) in saved {
+        write_governor(*cpu, gov).ok();
+    }
+}
+
+/// Keep all CPUs in C0 by writing 0 to `/dev/cpu_dma_latency`; the
+/// constraint holds for as long as the returned fd stays open. Returns -1
+/// when the device cannot be opened (usually: not root).
+pub fn dma_latency_open() -> i32 {
+    unsafe {
+        let fd = libc::open(c"/dev/cpu_dma_latency".as_ptr(), libc::O_WRONLY);
+        if fd >= 0 {
+            let val: i32 = 0;
+            libc::write(fd, &val as *const i32 as *const libc::c_void, 4);
         }
+        fd
     }
+}
+
+pub fn dma_latency_close(fd: i32) {
+    if fd >= 0 {
+        unsafe {
+            libc::close(fd);
+        }
+    }
+}
+
+fn detect_physical_cores(ncpus: usize) -> usize {
+    let cores: HashSet<(i32, i32)> = (0..ncpus).filter_map(package_core).collect();
     if cores.is_empty() {
         ncpus
     } else {
@@ -136,25 +937,32 @@ fn read_cpu_model() -> Option<String> {
 fn detect_hw_features() -> HwFeatures {
     use core::arch::x86_64::{__cpuid, __cpuid_count};
 
-    let popcnt;
-    let bmi1;
-    let bmi2;
+    // CPUID leaf 1: POPCNT (ECX bit 23)
+    let r1 = __cpuid(1);
+    let popcnt = (r1.ecx >> 23) & 1 == 1;
 
-    unsafe {
-        // CPUID leaf 1: POPCNT (ECX bit 23)
-        let r1 = __cpuid(1);
-        popcnt = (r1.ecx >> 23) & 1 == 1;
+    // CPUID leaf 7, subleaf 0: BMI1 (EBX bit 3), BMI2 (EBX bit 8)
+    let r7 = __cpuid_count(7, 0);
+    let bmi1 = (r7.ebx >> 3) & 1 == 1;
+    let bmi2 = (r7.ebx >> 8) & 1 == 1;
 
-        // CPUID leaf 7, subleaf 0: BMI1 (EBX bit 3), BMI2 (EBX bit 8)
-        let r7 = __cpuid_count(7, 0);
-        bmi1 = (r7.ebx >> 3) & 1 == 1;
-        bmi2 = (r7.ebx >> 8) & 1 == 1;
-    }
+    // CPUID leaf 0x80000001: LZCNT/ABM (ECX bit 5)
+    let max_ext = __cpuid(0x8000_0000).eax;
+    let lzcnt = max_ext >= 0x8000_0001 && (__cpuid(0x8000_0001).ecx >> 5) & 1 == 1;
+
+    // The AVX bits alone aren't enough: the OS must also save the wider
+    // registers (XCR0), which the std macro checks too.
+    let avx2 = std::arch::is_x86_feature_detected!("avx2");
+    let avx512f = std::arch::is_x86_feature_detected!("avx512f");
 
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
     HwFeatures {
-        popcnt: if popcnt { "yes" } else { "no" },
+        popcnt: yes_no(popcnt),
         ctz: if bmi1 { "TZCNT" } else { "BSF" },
         ptselect: if bmi2 { "PDEP" } else { "SW" },
+        lzcnt: yes_no(lzcnt),
+        avx2: yes_no(avx2),
+        avx512f: yes_no(avx512f),
     }
 }
 
@@ -164,6 +972,9 @@ fn detect_hw_features() -> HwFeatures {
         popcnt: "CNT",
         ctz: "RBIT+CLZ",
         ptselect: "SW",
+        lzcnt: "CLZ",
+        avx2: "n/a",
+        avx512f: "n/a",
     }
 }
 
@@ -173,5 +984,77 @@ fn detect_hw_features() -> HwFeatures {
         popcnt: "?",
         ctz: "?",
         ptselect: "?",
+        lzcnt: "?",
+        avx2: "?",
+        avx512f: "?",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_cpu_params_stay_in_range() {
+        let p = BenchParams::compute(1, 0, None);
+        assert_eq!(p.n_background, 0);
+        assert_eq!(p.n_workers, 1);
+        assert_eq!(p.n_idle, 0);
+        // The run refuses to start rather than pile everything on CPU 0.
+        assert!(p.min_cpus() > 1);
+
+        let mut p = BenchParams::with_overrides(1, 1, None, None).unwrap();
+        assert_eq!(p.n_background, 0);
+        assert!(p.clamped.is_empty());
+        p.disable_shadows();
+        assert_eq!(p.min_cpus(), 2);
+        assert_eq!(p.housekeeping_cpus(1), vec![0]);
+    }
+
+    #[test]
+    fn background_override_bounds() {
+        // One short of every CPU: accepted, but lowered to leave room for
+        // the dispatcher and a worker.
+        let p = BenchParams::with_overrides(8, 4, None, Some(7)).unwrap();
+        assert_eq!(p.n_background, 6);
+        assert_eq!(p.clamped.len(), 1);
+        let c = &p.clamped[0];
+        assert_eq!((c.what, c.requested, c.actual), ("background", 7, 6));
+
+        for bg in [8, 9, 100] {
+            assert!(matches!(
+                BenchParams::with_overrides(8, 4, None, Some(bg)),
+                Err(ParamsError::BackgroundExceedsCpus { requested, ncpus: 8 }) if requested == bg
+            ));
+        }
+
+        // Zero background always fits.
+        let p = BenchParams::with_overrides(8, 4, None, Some(0)).unwrap();
+        assert_eq!(p.n_background, 0);
+        assert!(p.clamped.is_empty());
+    }
+
+    #[test]
+    fn thread_override_clamping() {
+        // 8 CPUs, 2 background: 5 left, 2 shadows per worker, so 1 worker.
+        let fits = BenchParams::with_overrides(8, 4, Some(1), Some(2)).unwrap();
+        assert_eq!(fits.n_workers, 1);
+        assert!(fits.clamped.is_empty());
+
+        let p = BenchParams::with_overrides(8, 4, Some(4), Some(2)).unwrap();
+        assert_eq!(p.n_workers, 1);
+        let c = &p.clamped[0];
+        assert_eq!((c.what, c.requested, c.actual), ("workers", 4, 1));
+
+        // Zero workers is raised to one.
+        let p = BenchParams::with_overrides(8, 4, Some(0), Some(2)).unwrap();
+        assert_eq!(p.n_workers, 1);
+        assert_eq!(p.clamped[0].requested, 0);
+
+        // Every CPU is still accounted for after clamping.
+        let p = BenchParams::with_overrides(64, 32, Some(100), Some(10)).unwrap();
+        let used = 1 + p.n_background + p.n_workers * (1 + p.shadows_per_worker) + p.n_idle;
+        assert_eq!(used, 64);
+        assert_eq!(p.n_workers, 17);
     }
 }
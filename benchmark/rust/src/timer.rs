@@ -0,0 +1,132 @@
+// Timestamp source for the wakeup hot path (`--timer`).
+//
+// The default reads CLOCK_MONOTONIC through the vDSO. `--timer tsc` reads
+// the time-stamp counter directly, which skips the vDSO's clocksource
+// bookkeeping. TSC ticks are converted to ns with a ratio measured once
+// against CLOCK_MONOTONIC at startup: both clocks are sampled, the thread
+// busy-waits for CALIBRATION_NS, both are sampled again, and the tick delta
+// over the ns delta gives the frequency. Timestamps from different CPUs are
+// only comparable on an invariant TSC, so anything else falls back to the
+// clock.
+
+use std::sync::OnceLock;
+
+use crate::system::TimerSource;
+
+/// Length of the TSC-vs-CLOCK_MONOTONIC calibration window.
+const CALIBRATION_NS: u64 = 50_000_000;
+/// Fixed-point shift of the ticks-to-ns multiplier.
+const MULT_SHIFT: u32 = 32;
+
+static TSC: OnceLock<Option<TscCalibration>> = OnceLock::new();
+
+/// Result of the startup TSC calibration.
+#[derive(Clone, Copy, Debug)]
+pub struct TscCalibration {
+    /// Measured TSC frequency.
+    pub freq_hz: f64,
+    /// ns per tick, scaled by 2^MULT_SHIFT.
+    mult: u64,
+    /// Counter value at calibration; keeps the scaled product small.
+    base: u64,
+}
+
+/// A resolved timestamp source, cheap to copy into every thread.
+#[derive(Clone, Copy)]
+pub enum Timer {
+    Clock,
+    Tsc { base: u64, mult: u64 },
+}
+
+impl Timer {
+    /// Timer for `source`; TSC falls back to the clock when
+    /// [`calibrate_tsc`] fails (and calibrates on first use).
+    pub fn new(source: TimerSource) -> Self {
+        let tsc = match source {
+            TimerSource::Tsc => calibrate_tsc(),
+            TimerSource::Clock => None,
+        };
+        match tsc {
+            Some(cal) => Timer::Tsc {
+                base: cal.base,
+                mult: cal.mult,
+            },
+            None => Timer::Clock,
+        }
+    }
+
+    /// Current time in ns. Only differences between two reads of the same
+    /// timer are meaningful.
+    #[inline]
+    pub fn now_ns(&self) -> u64 {
+        match *self {
+            Timer::Clock => clock_ns(),
+            Timer::Tsc { base, mult } => {
+                let ticks = read_tsc().wrapping_sub(base);
+                ((ticks as u128 * mult as u128) >> MULT_SHIFT) as u64
+            }
+        }
+    }
+}
+
+pub fn clock_ns() -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+    }
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+/// Calibrate the TSC once; `None` when there is no invariant TSC.
+pub fn calibrate_tsc() -> Option<TscCalibration> {
+    *TSC.get_or_init(|| {
+        if !tsc_invariant() {
+            return None;
+        }
+        let (c0, t0) = (read_tsc(), clock_ns());
+        while clock_ns() - t0 < CALIBRATION_NS {
+            core::hint::spin_loop();
+        }
+        let (c1, t1) = (read_tsc(), clock_ns());
+        let ticks = c1.wrapping_sub(c0);
+        if ticks == 0 {
+            return None;
+        }
+        let ns = t1 - t0;
+        Some(TscCalibration {
+            freq_hz: ticks as f64 * 1e9 / ns as f64,
+            mult: (((ns as u128) << MULT_SHIFT) / ticks as u128) as u64,
+            base: c0,
+        })
+    })
+}
+
+/// CPUID 0x80000007 EDX bit 8: the TSC ticks at a constant rate in every
+/// P-, C- and T-state.
+#[cfg(target_arch = "x86_64")]
+fn tsc_invariant() -> bool {
+    use core::arch::x86_64::__cpuid;
+
+    let max_ext = __cpuid(0x8000_0000).eax;
+    max_ext >= 0x8000_0007 && (__cpuid(0x8000_0007).edx >> 8) & 1 == 1
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn tsc_invariant() -> bool {
+    false
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline]
+fn read_tsc() -> u64 {
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+#[inline]
+fn read_tsc() -> u64 {
+    0
+}
@@ -1,12 +1,21 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Gauge, Paragraph};
+use ratatui::widgets::{Block, Borders, Gauge, Paragraph, Sparkline};
 use ratatui::Frame;
 
-use crate::calibrate::CalibrationResult;
-use crate::stats::{Histogram, StatResult, BUCKET_LABELS, NUM_BUCKETS};
-use crate::system::{BenchParams, SystemInfo};
+use crate::bench;
+use crate::calibrate::{self, CalibrationResult, Quiesce};
+use crate::cpuidle::IdleResidency;
+use crate::perf::PerfCounts;
+use crate::report::{BenchReport, Metrics};
+use crate::schedstat::SchedDelta;
+use crate::stats::{self, Bucketing, Histogram, StatResult};
+use crate::system::{self, BenchParams, PocSupport, SystemInfo, WarmupStrategy};
 
 // ---------------------------------------------------------------------------
 // App state
@@ -15,178 +24,1040 @@ use crate::system::{BenchParams, SystemInfo};
 #[derive(Clone)]
 pub enum Phase {
     Calibrating,
-    Discard,
+    /// `--warmup-discard-verify` probe number `probe`.
+    Settling {
+        probe: usize,
+    },
+    Discard {
+        round: usize,
+        total_rounds: usize,
+    },
     Running {
         round: usize,
         total_rounds: usize,
         poc_on: bool,
     },
+    /// A measured phase of a `--modes` run at sysctl `value`.
+    Mode {
+        round: usize,
+        total_rounds: usize,
+        value: i32,
+    },
     Error(String),
     Done,
 }
 
+/// State of a `--cstate-compare` run. The first pass runs with CPUs held
+/// in C0; its results are parked here while the deep-idle pass refills
+/// `final_on`/`final_off`.
+pub struct CStateCompare {
+    pub available: bool,
+    pub deep_pass: bool,
+    pub pinned_on: Option<StatResult>,
+    pub pinned_off: Option<StatResult>,
+}
+
+impl CStateCompare {
+    pub fn new(available: bool) -> Self {
+        Self {
+            available,
+            deep_pass: false,
+            pinned_on: None,
+            pinned_off: None,
+        }
+    }
+
+    /// Trimmed-mean penalty (ns) of allowing deep C-states, per mode.
+    pub fn penalty(&self, app: &App) -> (Option<f64>, Option<f64>) {
+        if !self.deep_pass {
+            return (None, None);
+        }
+        let delta = |pinned: &Option<StatResult>, deep: &Option<StatResult>| match (pinned, deep) {
+            (Some(p), Some(d)) => Some(d.trimmed_mean - p.trimmed_mean),
+            _ => None,
+        };
+        (
+            delta(&self.pinned_on, &app.final_on),
+            delta(&self.pinned_off, &app.final_off),
+        )
+    }
+}
+
+/// Final results of one `--queue-depth` sweep step.
+pub struct DepthResult {
+    pub depth: usize,
+    pub deep_cstates: bool,
+    pub on: Option<StatResult>,
+    pub off: Option<StatResult>,
+}
+
+/// Cumulative results for one sysctl value of a `--modes` run.
+pub struct ModeResult {
+    pub value: i32,
+    pub stats: Option<StatResult>,
+    pub hist: Option<Histogram>,
+}
+
+/// `--watch`: each mode's results over the last `window` cycles. The
+/// all-time aggregate stays in `final_on`/`final_off`.
+pub struct WatchState {
+    pub interval: Duration,
+    pub window: usize,
+    /// ON/OFF cycles completed.
+    pub cycles: usize,
+    pub recent_on: VecDeque<StatResult>,
+    pub recent_off: VecDeque<StatResult>,
+}
+
+impl WatchState {
+    pub fn new(interval: Duration, window: usize) -> Self {
+        Self {
+            interval,
+            window: window.max(1),
+            cycles: 0,
+            recent_on: VecDeque::new(),
+            recent_off: VecDeque::new(),
+        }
+    }
+
+    /// Add a phase result, dropping the oldest one beyond the window.
+    pub fn push(&mut self, poc_on: bool, sr: StatResult) {
+        let q = if poc_on {
+            &mut self.recent_on
+        } else {
+            &mut self.recent_off
+        };
+        q.push_back(sr);
+        if q.len() > self.window {
+            q.pop_front();
+        }
+    }
+
+    /// The window's merged ON and OFF results, once both have any.
+    pub fn rolling(&self) -> Option<(StatResult, StatResult)> {
+        if self.recent_on.is_empty() || self.recent_off.is_empty() {
+            return None;
+        }
+        let merge =
+            |q: &VecDeque<StatResult>| StatResult::merge(&q.iter().cloned().collect::<Vec<_>>());
+        Some((merge(&self.recent_on), merge(&self.recent_off)))
+    }
+
+    fn clear(&mut self) {
+        self.cycles = 0;
+        self.recent_on.clear();
+        self.recent_off.clear();
+    }
+}
+
+/// `--detect-effect`: planned power to resolve a given ON/OFF change.
+pub struct PowerEstimate {
+    pub effect_pct: f64,
+    pub samples_per_mode: usize,
+    pub power: f64,
+}
+
+/// Per-round results for the workers of one `--worker-priorities` class.
+pub struct PriorityClass {
+    pub priority: i32,
+    pub on: Vec<StatResult>,
+    pub off: Vec<StatResult>,
+}
+
 pub struct App {
     pub system: SystemInfo,
     pub params: BenchParams,
     pub phase: Phase,
     pub progress: f64,
     pub calibration: Option<CalibrationResult>,
+    /// `calibration` came from the `--cache-calibration` cache.
+    pub calibration_cached: bool,
     pub hist_on: Option<Histogram>,
     pub hist_off: Option<Histogram>,
     pub final_on: Option<StatResult>,
     pub final_off: Option<StatResult>,
+    /// Shadow ack round trips per phase (`--measure-shadow`).
+    pub shadow_on: Option<StatResult>,
+    pub shadow_off: Option<StatResult>,
+    /// `--cdf` curves, one per mode label (see [`stats::cdf`]).
+    pub cdf: Vec<(String, Vec<f64>)>,
+    pub watch: Option<WatchState>,
+    pub cstate: Option<CStateCompare>,
+    pub error: Option<String>,
+    pub cpus_at_end: usize,
+    pub selected_bucket: Option<usize>,
+    pub depth_sweep: Vec<DepthResult>,
+    /// Warmup iterations dropped in each measured phase.
+    pub warmup_discarded: Vec<usize>,
+    pub perf_on: Option<PerfCounts>,
+    pub perf_off: Option<PerfCounts>,
+    /// Benchmark phases (calibration, discard and measured runs) started
+    /// and planned, for whole-run progress.
+    pub steps_started: usize,
+    pub steps_total: usize,
+    pub power: Option<PowerEstimate>,
+    /// Whether the kernel has a switchable POC knob.
+    pub poc_support: PocSupport,
+    /// Not root and the knob is read-only: the comparison needs root.
+    pub needs_root: bool,
+    /// Where the POC knob was found (it may be a fallback procfs mount).
+    pub sysctl_path: Option<String>,
+    /// (requested, allowed) iterations when `--max-memory-mb` cut the run.
+    pub iterations_capped: Option<(usize, usize)>,
+    /// Seed for every randomized choice (`--seed`, or from the clock).
+    pub seed: u64,
+    pub random_order: bool,
+    /// `--iterations-per-round`: measured rounds run this many instead of
+    /// the calibrated count.
+    pub iterations_per_round: Option<usize>,
+    /// Confidence level of the bootstrap intervals.
+    pub confidence: f64,
+    /// Bootstrap confidence interval of the mean, (lower, upper) in ns.
+    pub ci_on: Option<(f64, f64)>,
+    pub ci_off: Option<(f64, f64)>,
+    /// Whether POC ON ran first, per measured round.
+    pub round_order: Vec<bool>,
+    /// Per-round results of the ON/OFF comparison, before `merge` pools
+    /// them into `final_on`/`final_off` (not kept by `--watch`).
+    pub rounds_on: Vec<StatResult>,
+    pub rounds_off: Vec<StatResult>,
+    /// `--rounds-precision` target (percent): rounds run until the ON/OFF
+    /// delta's CI half-width drops below it.
+    pub rounds_precision: Option<f64>,
+    /// Per-round ON/OFF delta and its CI half-width, in percent.
+    pub round_delta_ci: Option<(f64, f64)>,
+    pub idle_on: Option<IdleResidency>,
+    pub idle_off: Option<IdleResidency>,
+    /// /proc/schedstat activity over the measured phases, when the kernel
+    /// counts it.
+    pub sched_on: Option<SchedDelta>,
+    pub sched_off: Option<SchedDelta>,
+    /// Some phase's workers ran without their `--worker-fifo`/
+    /// `--worker-priorities`/`--worker-nice` setting (no CAP_SYS_NICE).
+    pub worker_prio_denied: bool,
+    /// `--cross-cpu` moves of the dispatcher, over all phases.
+    pub dispatcher_moves: usize,
+    /// `--warmup-discard-verify` outcome of the last measurement.
+    pub quiesce: Option<Quiesce>,
+    /// CLOCK_MONOTONIC resolution (ns).
+    pub clock_res_ns: u64,
+    /// Histogram bucket layout (`--hist`).
+    pub bucketing: Bucketing,
+    /// Mann-Whitney U p-value of all ON vs all OFF samples.
+    pub significance: Option<f64>,
+    /// Cost of one clock read (`--check-vdso`).
+    pub clock_cost_ns: Option<f64>,
+    /// Calibrated TSC frequency when timing with `--timer tsc`.
+    pub tsc_hz: Option<f64>,
+    /// Back-to-back timestamp cost (ns).
+    pub timer_overhead_ns: u64,
+    pub theme: Theme,
+    /// Between-round stddev of the per-round trimmed means (ns).
+    pub between_on: Option<f64>,
+    pub between_off: Option<f64>,
+    pub priority_classes: Vec<PriorityClass>,
+    /// Per-value results of a `--modes` run, in the order given; deltas
+    /// are relative to the first.
+    pub modes: Vec<ModeResult>,
+    /// The running phase is held with `space`.
+    pub paused: bool,
+    /// Latest latencies of the running phase (ns), oldest first.
+    pub recent: Vec<u64>,
+    /// `s` swaps the histogram panel for a sparkline of `recent`.
+    pub show_sparkline: bool,
+    /// `v` swaps the histogram panel for the per-round p99 of ON and CFS.
+    pub show_rounds: bool,
+    /// Histogram bars show sample counts instead of percentages
+    /// (`--hist-counts`, toggled with `c`).
+    pub hist_counts: bool,
+    /// `d` turns the histogram bars cumulative: each is the fraction of
+    /// samples up to its bucket.
+    pub hist_cdf: bool,
+    /// Summary rows scrolled off the top when the panel is too short for
+    /// all of them (`up`/`down`, `k`/`j`).
+    pub summary_scroll: usize,
+    /// Add a geometric-mean row to the summaries (`--show-geomean`).
+    pub show_geomean: bool,
+    /// Start of the whole run and of the current phase, for the ETA.
+    pub run_start: Option<Instant>,
+    pub phase_start: Option<Instant>,
     pub finished: bool,
 }
 
 impl App {
     pub fn new(system: SystemInfo, params: BenchParams) -> Self {
+        let cpus_at_end = system.ncpus;
         Self {
             system,
             params,
             phase: Phase::Calibrating,
             progress: 0.0,
             calibration: None,
+            calibration_cached: false,
             hist_on: None,
             hist_off: None,
             final_on: None,
             final_off: None,
+            shadow_on: None,
+            shadow_off: None,
+            cdf: Vec::new(),
+            watch: None,
+            cstate: None,
+            error: None,
+            cpus_at_end,
+            selected_bucket: None,
+            depth_sweep: Vec::new(),
+            warmup_discarded: Vec::new(),
+            perf_on: None,
+            perf_off: None,
+            steps_started: 0,
+            steps_total: 0,
+            power: None,
+            poc_support: PocSupport::Present,
+            needs_root: false,
+            sysctl_path: None,
+            clock_cost_ns: None,
+            tsc_hz: None,
+            timer_overhead_ns: 0,
+            theme: Theme::default(),
+            significance: None,
+            clock_res_ns: 0,
+            bucketing: Bucketing::default(),
+            idle_on: None,
+            idle_off: None,
+            sched_on: None,
+            sched_off: None,
+            worker_prio_denied: false,
+            dispatcher_moves: 0,
+            quiesce: None,
+            seed: 0,
+            random_order: false,
+            iterations_per_round: None,
+            confidence: 0.95,
+            ci_on: None,
+            ci_off: None,
+            round_order: Vec::new(),
+            rounds_on: Vec::new(),
+            rounds_off: Vec::new(),
+            rounds_precision: None,
+            round_delta_ci: None,
+            iterations_capped: None,
+            between_on: None,
+            between_off: None,
+            priority_classes: Vec::new(),
+            modes: Vec::new(),
+            paused: false,
+            recent: Vec::new(),
+            show_sparkline: false,
+            show_rounds: false,
+            hist_counts: false,
+            hist_cdf: false,
+            summary_scroll: 0,
+            show_geomean: false,
+            run_start: None,
+            phase_start: None,
             finished: false,
         }
     }
 }
 
+impl App {
+    /// Drop everything a run produced, keeping system info, parameters and
+    /// display settings, so the run can start over.
+    pub fn reset_results(&mut self) {
+        self.phase = Phase::Calibrating;
+        self.progress = 0.0;
+        self.calibration = None;
+        self.calibration_cached = false;
+        self.hist_on = None;
+        self.hist_off = None;
+        self.final_on = None;
+        self.final_off = None;
+        self.shadow_on = None;
+        self.shadow_off = None;
+        self.cdf.clear();
+        if let Some(w) = self.watch.as_mut() {
+            w.clear();
+        }
+        self.cstate = None;
+        self.error = None;
+        self.selected_bucket = None;
+        self.depth_sweep.clear();
+        self.warmup_discarded.clear();
+        self.perf_on = None;
+        self.perf_off = None;
+        self.steps_started = 0;
+        self.power = None;
+        self.iterations_capped = None;
+        self.ci_on = None;
+        self.ci_off = None;
+        self.round_order.clear();
+        self.rounds_on.clear();
+        self.rounds_off.clear();
+        self.round_delta_ci = None;
+        self.idle_on = None;
+        self.idle_off = None;
+        self.sched_on = None;
+        self.sched_off = None;
+        self.worker_prio_denied = false;
+        self.dispatcher_moves = 0;
+        self.quiesce = None;
+        self.significance = None;
+        self.between_on = None;
+        self.between_off = None;
+        self.priority_classes.clear();
+        self.modes.clear();
+        self.recent.clear();
+        self.run_start = None;
+        self.phase_start = None;
+        self.finished = false;
+    }
+
+    /// Fraction of the whole run completed, counting the current phase.
+    pub fn overall_progress(&self) -> f64 {
+        if self.finished || self.steps_total == 0 {
+            return if self.finished { 1.0 } else { 0.0 };
+        }
+        // `progress` belongs to the most recently started step.
+        let done = self.steps_started.saturating_sub(1) as f64 + self.progress.clamp(0.0, 1.0);
+        (done / self.steps_total as f64).min(1.0)
+    }
+}
+
 // ---------------------------------------------------------------------------
-// Color constants
+// Theme
 // ---------------------------------------------------------------------------
 
-const COL_POC: Color = Color::Green;
-const COL_CFS: Color = Color::Yellow;
-const COL_BETTER: Color = Color::Green;
-const COL_WORSE: Color = Color::Red;
-const COL_DIM: Color = Color::DarkGray;
-const COL_LABEL: Color = Color::Cyan;
+/// Colors and glyphs for the TUI. The monochrome theme (`--no-color`)
+/// leaves every style at the terminal default and draws with ASCII, for
+/// captured output and terminals that hide dark gray.
+pub struct Theme {
+    /// `--theme` value, repeated in the summary.
+    pub name: &'static str,
+    pub color: bool,
+    pub poc: Color,
+    pub cfs: Color,
+    pub better: Color,
+    pub worse: Color,
+    pub dim: Color,
+    pub label: Color,
+    pub text: Color,
+    pub title: Color,
+    pub warn: Color,
+    pub error: Color,
+    pub done: Color,
+    pub busy: Color,
+    pub bar: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+impl Theme {
+    pub fn color() -> Self {
+        Self {
+            name: "default",
+            color: true,
+            poc: Color::Green,
+            cfs: Color::Yellow,
+            better: Color::Green,
+            worse: Color::Red,
+            dim: Color::DarkGray,
+            label: Color::Cyan,
+            text: Color::White,
+            title: Color::Cyan,
+            warn: Color::Yellow,
+            error: Color::Red,
+            done: Color::Green,
+            busy: Color::Blue,
+            bar: "\u{2588}",
+            up: "\u{25b2}",
+            down: "\u{25bc}",
+        }
+    }
+
+    /// Solarized accents; dim text uses base01 instead of DarkGray.
+    pub fn solarized() -> Self {
+        Self {
+            name: "solarized",
+            poc: Color::Rgb(0x85, 0x99, 0x00),
+            cfs: Color::Rgb(0xb5, 0x89, 0x00),
+            better: Color::Rgb(0x85, 0x99, 0x00),
+            worse: Color::Rgb(0xdc, 0x32, 0x2f),
+            dim: Color::Rgb(0x58, 0x6e, 0x75),
+            label: Color::Rgb(0x2a, 0xa1, 0x98),
+            text: Color::Rgb(0x93, 0xa1, 0xa1),
+            title: Color::Rgb(0x26, 0x8b, 0xd2),
+            warn: Color::Rgb(0xcb, 0x4b, 0x16),
+            error: Color::Rgb(0xdc, 0x32, 0x2f),
+            done: Color::Rgb(0x85, 0x99, 0x00),
+            busy: Color::Rgb(0x26, 0x8b, 0xd2),
+            ..Self::color()
+        }
+    }
+
+    /// Bright variants only; nothing drawn in DarkGray.
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "highcontrast",
+            poc: Color::LightGreen,
+            cfs: Color::LightYellow,
+            better: Color::LightGreen,
+            worse: Color::LightRed,
+            dim: Color::Gray,
+            label: Color::LightCyan,
+            text: Color::White,
+            title: Color::LightCyan,
+            warn: Color::LightYellow,
+            error: Color::LightRed,
+            done: Color::LightGreen,
+            busy: Color::LightBlue,
+            ..Self::color()
+        }
+    }
+
+    pub fn mono() -> Self {
+        Self {
+            name: "mono",
+            color: false,
+            bar: "#",
+            up: "^",
+            down: "v",
+            ..Self::color()
+        }
+    }
+
+    fn fg(&self, c: Color) -> Style {
+        if self.color {
+            Style::default().fg(c)
+        } else {
+            Style::default()
+        }
+    }
+
+    /// Column color of the `i`th `--modes` value; the first (the baseline)
+    /// gets the CFS color, the second the POC color.
+    fn mode(&self, i: usize) -> Color {
+        [self.cfs, self.poc, self.busy, self.label, self.warn][i % 5]
+    }
+
+    /// Text drawn on top of a bar of color `c`.
+    fn on(&self, c: Color) -> Style {
+        if self.color {
+            Style::default().fg(Color::Black).bg(c)
+        } else {
+            Style::default()
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::color()
+    }
+}
 
 // ---------------------------------------------------------------------------
 // Draw
 // ---------------------------------------------------------------------------
 
-pub fn draw(f: &mut Frame, app: &App) {
-    let chunks = Layout::default()
+fn layout(area: Rect) -> std::rc::Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(4), // header
-            Constraint::Length(3), // progress
-            Constraint::Min(12),   // histogram
-            Constraint::Length(8), // summary
-            Constraint::Length(1), // footer
+            Constraint::Length(6),  // header
+            Constraint::Length(3),  // progress
+            Constraint::Min(12),    // histogram
+            Constraint::Length(17), // summary
+            Constraint::Length(1),  // footer
         ])
-        .split(f.area());
+        .split(area)
+}
+
+/// Map a terminal cell to the histogram bucket row drawn there, if any.
+/// `area` is the full terminal area the frame was drawn into.
+pub fn bucket_at(area: Rect, column: u16, row: u16, num_buckets: usize) -> Option<usize> {
+    let hist = layout(area)[2];
+    let inner = Block::default().borders(Borders::ALL).inner(hist);
+    if inner.height < 3 || inner.width < 30 {
+        return None;
+    }
+    let inside_x = column >= inner.x && column < inner.x + inner.width;
+    // First inner row is the column header; buckets follow.
+    let first = inner.y + 1;
+    if !inside_x || row < first || row >= inner.y + inner.height {
+        return None;
+    }
+    let bucket = (row - first) as usize;
+    (bucket < num_buckets).then_some(bucket)
+}
+
+pub fn draw(f: &mut Frame, app: &App) {
+    let chunks = layout(f.area());
 
     draw_header(f, chunks[0], app);
     draw_progress(f, chunks[1], app);
-    draw_histogram(f, chunks[2], app);
-    draw_summary(f, chunks[3], app);
+    if app.show_rounds {
+        draw_rounds(f, chunks[2], app);
+    } else if app.show_sparkline {
+        draw_sparkline(f, chunks[2], app);
+    } else {
+        draw_histogram(f, chunks[2], app);
+    }
+    if app.modes.is_empty() {
+        draw_summary(f, chunks[3], app);
+    } else {
+        draw_mode_summary(f, chunks[3], app);
+    }
     draw_footer(f, chunks[4], app);
 }
 
+/// How workers are scheduled, when not the default.
+fn worker_suffix(priorities: &[i32], nice: i32) -> String {
+    let mut parts = Vec::new();
+    if priorities.iter().any(|&p| p > 0) {
+        parts.push("fifo".to_string());
+    }
+    if nice != 0 {
+        parts.push(format!("nice {}", nice));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", parts.join(", "))
+    }
+}
+
+/// ` (memory)` after the background thread count for a non-default
+/// `--bg-workload`.
+fn bg_suffix(workload: &str, n_background: usize) -> String {
+    if workload == "spin" || n_background == 0 {
+        String::new()
+    } else {
+        format!(" ({})", workload)
+    }
+}
+
+const ROOT_HINT: &str = "Run as root to compare POC ON/OFF";
+
 fn draw_header(f: &mut Frame, area: Rect, app: &App) {
+    let th = &app.theme;
     let hw = &app.system.hw_features;
-    let lines = vec![
+    let mut load = if app.params.oversubscribe > 0.0 {
+        format!(
+            " \u{00b7} oversub {:.1}x ({} hogs)",
+            app.params.oversubscribe,
+            app.params.oversubscribe_threads(app.system.ncpus)
+        )
+    } else {
+        String::new()
+    };
+    if app.params.work_ns > 0 {
+        load += &format!(" \u{00b7} work {}\u{03bc}s", app.params.work_ns / 1000);
+    }
+    if app.params.cross_cpu {
+        load += " \u{00b7} cross-CPU";
+    }
+    match app.quiesce {
+        Some(q) if q.settled => load += &format!(" \u{00b7} settled after {} probes", q.probes),
+        Some(q) => load += &format!(" \u{00b7} \u{26a0} not settled after {} probes", q.probes),
+        None => {}
+    }
+    let mut lines = vec![
         Line::from(vec![
             Span::styled(
                 &app.system.cpu_model,
-                Style::default()
-                    .fg(Color::White)
-                    .add_modifier(Modifier::BOLD),
+                th.fg(th.text).add_modifier(Modifier::BOLD),
             ),
             Span::styled(
                 format!(" \u{2502} {} CPUs", app.system.ncpus),
-                Style::default().fg(COL_DIM),
+                th.fg(th.dim),
+            ),
+            if app.system.isolated.is_empty() {
+                Span::raw("")
+            } else {
+                Span::styled(
+                    format!(
+                        " (isolated {})",
+                        system::format_cpu_list(&app.system.isolated)
+                    ),
+                    th.fg(th.dim),
+                )
+            },
+            if app.system.numa_nodes.len() > 1 {
+                Span::styled(
+                    format!(
+                        " \u{2502} {} nodes, {}",
+                        app.system.numa_nodes.len(),
+                        app.params.numa.label()
+                    ),
+                    th.fg(th.dim),
+                )
+            } else {
+                Span::raw("")
+            },
+            Span::styled(
+                format!(
+                    " \u{2502} SMT {}{}",
+                    app.system.smt_threads(),
+                    if app.params.smt_cpus.is_empty() {
+                        ""
+                    } else {
+                        ", no-smt"
+                    }
+                ),
+                th.fg(th.dim),
+            ),
+            Span::styled(
+                format!(
+                    " \u{2502} POPCNT={} CTZ={} PTSelect={} LZCNT={} AVX2={} AVX-512F={}",
+                    hw.popcnt, hw.ctz, hw.ptselect, hw.lzcnt, hw.avx2, hw.avx512f
+                ),
+                th.fg(th.dim),
             ),
+            match app.clock_cost_ns {
+                Some(ns) => Span::styled(
+                    format!(" \u{2502} clock {:.0}ns", ns),
+                    th.fg(if ns > bench::CLOCK_SYSCALL_THRESHOLD_NS {
+                        th.worse
+                    } else {
+                        th.dim
+                    }),
+                ),
+                None => Span::raw(""),
+            },
+            match app.tsc_hz {
+                Some(hz) => {
+                    Span::styled(format!(" \u{2502} TSC {:.2}GHz", hz / 1e9), th.fg(th.dim))
+                }
+                None => Span::raw(""),
+            },
             Span::styled(
                 format!(
-                    " \u{2502} POPCNT={} CTZ={} PTSelect={}",
-                    hw.popcnt, hw.ctz, hw.ptselect
+                    " \u{2502} timer {}ns{}",
+                    app.timer_overhead_ns,
+                    if app.params.subtract_overhead_ns > 0 {
+                        " (subtracted)"
+                    } else {
+                        ""
+                    }
                 ),
-                Style::default().fg(COL_DIM),
+                th.fg(th.dim),
             ),
         ]),
         Line::from(vec![
             Span::styled(
                 format!(
-                    "{} worker{} \u{00b7} {} bg \u{00b7} {} idle \u{00b7} {} shadow/w",
+                    "{} worker{}{} \u{00b7} {} bg{} \u{00b7} {} idle \u{00b7} {} shadow/w{}",
                     app.params.n_workers,
                     if app.params.n_workers > 1 { "s" } else { "" },
+                    worker_suffix(&app.params.worker_priorities, app.params.worker_nice),
                     app.params.n_background,
+                    bg_suffix(app.params.bg_workload.label(), app.params.n_background),
                     app.params.n_idle,
                     app.params.shadows_per_worker,
+                    load,
                 ),
-                Style::default().fg(COL_DIM),
+                th.fg(th.dim),
             ),
             if let Some(ref cal) = app.calibration {
                 Span::styled(
                     format!(
-                        " \u{00b7} {} iterations (auto: \u{03bc}={:.1}\u{03bc}s \u{03c3}={:.1}\u{03bc}s)",
-                        cal.iterations, cal.probe_mean_us, cal.probe_stddev_us,
+                        " \u{00b7} {} iterations ({}: \u{03bc}={:.1}\u{03bc}s \u{03c3}={:.1}\u{03bc}s{})",
+                        app.iterations_per_round.unwrap_or(cal.iterations),
+                        if app.calibration_cached { "cached" } else { "auto" },
+                        cal.probe_mean_us,
+                        cal.probe_stddev_us,
+                        if cal.attempts > 1 {
+                            format!(", {} attempts", cal.attempts)
+                        } else {
+                            String::new()
+                        },
                     ),
-                    Style::default().fg(COL_DIM),
+                    th.fg(th.dim),
                 )
             } else {
                 Span::raw("")
             },
+            match app.power {
+                Some(ref pw) => Span::styled(
+                    format!(" \u{00b7} power({:.1}%)={:.2}", pw.effect_pct, pw.power),
+                    th.fg(if pw.power < stats::MIN_POWER {
+                        th.worse
+                    } else {
+                        th.dim
+                    }),
+                ),
+                None => Span::raw(""),
+            },
         ]),
     ];
+    if app.needs_root {
+        lines.push(Line::from(Span::styled(
+            ROOT_HINT,
+            th.fg(th.error).add_modifier(Modifier::BOLD),
+        )));
+    }
+    if let Some(banner) = app.poc_support.banner(&app.system.kernel) {
+        lines.push(Line::from(Span::styled(
+            format!("\u{26a0} {}", banner),
+            th.fg(th.warn).add_modifier(Modifier::BOLD),
+        )));
+    }
+    if let Some(line) = cstate_penalty_line(app) {
+        lines.push(Line::from(Span::styled(line, th.fg(th.dim))));
+    }
+    if let Some(warn) = app.system.freq.warning() {
+        lines.push(Line::from(Span::styled(
+            format!("\u{26a0} {}", warn),
+            th.fg(th.warn),
+        )));
+    }
+    if !app.params.clamped.is_empty() {
+        let clamped: Vec<String> = app.params.clamped.iter().map(|c| c.to_string()).collect();
+        lines.push(Line::from(Span::styled(
+            format!(
+                "\u{26a0} clamped to {} CPUs: {}",
+                app.system.ncpus,
+                clamped.join(", ")
+            ),
+            th.fg(th.warn),
+        )));
+    }
 
     let block = Block::default()
         .title(" POC Selector Benchmark ")
-        .title_style(
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )
+        .title_style(th.fg(th.title).add_modifier(Modifier::BOLD))
         .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT);
     let paragraph = Paragraph::new(lines).block(block);
     f.render_widget(paragraph, area);
 }
 
-fn draw_progress(f: &mut Frame, area: Rect, app: &App) {
-    let label = match &app.phase {
+pub fn phase_label(app: &App) -> String {
+    match &app.phase {
         Phase::Calibrating => "Calibrating...".to_string(),
-        Phase::Discard => "Warmup (discard)...".to_string(),
+        Phase::Settling { probe } => format!("Checking the system is quiet (probe {})...", probe),
+        Phase::Discard {
+            round,
+            total_rounds,
+        } => {
+            if *total_rounds > 1 {
+                format!("Warmup (discard {}/{})...", round, total_rounds)
+            } else {
+                "Warmup (discard)...".to_string()
+            }
+        }
         Phase::Running {
             round,
             total_rounds,
             poc_on,
         } => {
             let mode = if *poc_on { "POC ON" } else { "CFS" };
-            format!("Round {}/{} [{}]", round, total_rounds, mode)
+            // `--watch` runs unbounded: no total to show.
+            let round = if *total_rounds == 0 {
+                format!("Cycle {}", round)
+            } else if app.rounds_precision.is_some() {
+                format!("Round {} (adaptive)", round)
+            } else {
+                format!("Round {}/{}", round, total_rounds)
+            };
+            format!("{} [{}]{}", round, mode, run_suffix(app))
         }
+        Phase::Mode {
+            round,
+            total_rounds,
+            value,
+        } => format!(
+            "Round {}/{} [POC={}]{}",
+            round,
+            total_rounds,
+            value,
+            run_suffix(app)
+        ),
         Phase::Error(msg) => format!("Error: {}", msg),
         Phase::Done => "Complete".to_string(),
+    }
+}
+
+/// Queue depth and C-state pass of a measured phase, for its label.
+fn run_suffix(app: &App) -> String {
+    let cstate = match &app.cstate {
+        Some(cs) if cs.deep_pass => " (deep C-states)",
+        Some(cs) if cs.available => " (C0 pinned)",
+        _ => "",
+    };
+    let depth = if app.params.queue_depth > 1 {
+        format!(" depth={}", app.params.queue_depth)
+    } else {
+        String::new()
     };
+    format!("{}{}", depth, cstate)
+}
+
+/// Color of the phase being measured.
+fn phase_color(app: &App) -> Option<Color> {
+    let th = &app.theme;
+    match app.phase {
+        Phase::Running { poc_on: true, .. } => Some(th.poc),
+        Phase::Running { poc_on: false, .. } => Some(th.cfs),
+        Phase::Mode { value, .. } => {
+            let i = app.modes.iter().position(|m| m.value == value).unwrap_or(0);
+            Some(th.mode(i))
+        }
+        _ => None,
+    }
+}
+
+fn draw_progress(f: &mut Frame, area: Rect, app: &App) {
+    let th = &app.theme;
+    let mut label = phase_label(app);
+    if app.paused {
+        label.push_str(" [PAUSED]");
+    }
+    label.push_str(&timing_label(app));
+    let block = Block::default().borders(Borders::LEFT | Borders::RIGHT);
+    let ratio = app.progress.clamp(0.0, 1.0);
+
+    // Gauge fills with block glyphs; draw an ASCII bar instead.
+    if !th.color {
+        let inner = block.inner(area);
+        let width = (inner.width as usize).saturating_sub(label.chars().count() + 3);
+        let filled = (ratio * width as f64).round() as usize;
+        let bar = format!(
+            "[{}{}] {}",
+            th.bar.repeat(filled),
+            ".".repeat(width - filled),
+            label
+        );
+        f.render_widget(Paragraph::new(bar).block(block), area);
+        return;
+    }
 
     let gauge = Gauge::default()
-        .block(Block::default().borders(Borders::LEFT | Borders::RIGHT))
+        .block(block)
         .gauge_style(
-            Style::default()
-                .fg(match &app.phase {
-                    Phase::Running { poc_on: true, .. } => COL_POC,
-                    Phase::Running { poc_on: false, .. } => COL_CFS,
-                    Phase::Error(_) => Color::Red,
-                    Phase::Done => Color::Green,
-                    _ => Color::Blue,
-                })
-                .add_modifier(Modifier::BOLD),
+            th.fg(match (&app.phase, phase_color(app)) {
+                (_, Some(c)) => c,
+                (Phase::Error(_), _) => th.error,
+                (Phase::Done, _) => th.done,
+                _ => th.busy,
+            })
+            .add_modifier(Modifier::BOLD),
         )
         .label(label)
-        .ratio(app.progress.clamp(0.0, 1.0));
+        .ratio(ratio);
     f.render_widget(gauge, area);
 }
 
+fn draw_sparkline(f: &mut Frame, area: Rect, app: &App) {
+    let th = &app.theme;
+    let max = app.recent.iter().copied().max().unwrap_or(0);
+    let block = Block::default()
+        .title(format!(
+            " Recent latency (last {}, max {:.1} \u{03bc}s) ",
+            app.recent.len(),
+            max as f64 / 1000.0
+        ))
+        .title_style(th.fg(th.label))
+        .borders(Borders::ALL);
+    let color = phase_color(app).unwrap_or(th.poc);
+    // Newest on the right edge when there are more samples than columns.
+    let width = block.inner(area).width as usize;
+    let data = &app.recent[app.recent.len().saturating_sub(width)..];
+    let spark = Sparkline::default()
+        .block(block)
+        .data(data)
+        .style(th.fg(color));
+    f.render_widget(spark, area);
+}
+
+/// Each round's p99 of ON and CFS as bars, newest last, with the spread of
+/// their change across rounds in the title: a lucky round stands out.
+fn draw_rounds(f: &mut Frame, area: Rect, app: &App) {
+    let th = &app.theme;
+    let deltas = stats::round_p99_deltas(&app.rounds_on, &app.rounds_off);
+    let title = if deltas.len() > 1 {
+        format!(
+            " Per-round p99 (\u{03bc}s) \u{00b7} \u{0394} stddev {:.1}% over {} rounds ",
+            stats::sample_stddev(&deltas),
+            deltas.len()
+        )
+    } else {
+        " Per-round p99 (\u{03bc}s) ".to_string()
+    };
+    let block = Block::default()
+        .title(title)
+        .title_style(th.fg(th.label))
+        .borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let rounds = app.rounds_on.len().max(app.rounds_off.len());
+    if rounds == 0 {
+        let text = Span::styled("No ON/OFF round finished yet", th.fg(th.dim));
+        f.render_widget(Paragraph::new(Line::from(text)), inner);
+        return;
+    }
+    let max = app
+        .rounds_on
+        .iter()
+        .chain(&app.rounds_off)
+        .map(|r| r.p99)
+        .fold(0.0, f64::max);
+    // "  R1 POC ON " before the bar, value and change after it.
+    let bar_w = (inner.width as usize).saturating_sub(12 + 10 + 9);
+    // Two rows per round; the newest rounds that fit.
+    let first = rounds.saturating_sub((inner.height as usize / 2).max(1));
+    let mut lines = Vec::new();
+    for r in first..rounds {
+        let (on, off) = (app.rounds_on.get(r), app.rounds_off.get(r));
+        for (name, result, color) in [("POC ON", on, th.poc), ("CFS", off, th.cfs)] {
+            let label = if name == "CFS" {
+                String::new()
+            } else {
+                format!("R{}", r + 1)
+            };
+            let p99 = result.map(|sr| sr.p99);
+            let filled = match p99 {
+                Some(v) if max > 0.0 => ((v / max * bar_w as f64).round() as usize).min(bar_w),
+                _ => 0,
+            };
+            let mut spans = vec![
+                Span::styled(format!("{:>4} {:<6} ", label, name), th.fg(th.dim)),
+                Span::styled(
+                    th.bar.repeat(filled) + &" ".repeat(bar_w - filled),
+                    th.fg(color),
+                ),
+                Span::styled(
+                    format!(
+                        " {:>9}",
+                        p99.map_or("-".into(), |v| format!("{:.2}", v / 1000.0))
+                    ),
+                    th.fg(th.text),
+                ),
+            ];
+            if name == "CFS" {
+                if let (Some(on), Some(off)) = (on, off.filter(|off| off.p99 > 0.0)) {
+                    let delta = (on.p99 - off.p99) / off.p99 * 100.0;
+                    let color = if delta < 0.0 { th.better } else { th.worse };
+                    spans.push(Span::styled(format!(" {:>+7.1}%", delta), th.fg(color)));
+                }
+            }
+            lines.push(Line::from(spans));
+        }
+    }
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
 fn draw_histogram(f: &mut Frame, area: Rect, app: &App) {
+    let th = &app.theme;
+    let title = if app.hist_cdf {
+        " Cumulative Latency Distribution (\u{03bc}s) "
+    } else {
+        " Latency Distribution (\u{03bc}s) "
+    };
     let block = Block::default()
-        .title(" Latency Distribution (\u{03bc}s) ")
-        .title_style(Style::default().fg(COL_LABEL))
+        .title(title)
+        .title_style(th.fg(th.label))
         .borders(Borders::ALL);
     let inner = block.inner(area);
     f.render_widget(block, area);
@@ -195,56 +1066,56 @@ fn draw_histogram(f: &mut Frame, area: Rect, app: &App) {
         return;
     }
 
+    let columns = histogram_columns(app);
+    let n = columns.len();
+
     // Header line
-    let half_w = (inner.width as usize - 8) / 2; // 8 for label + padding
-    let header = Line::from(vec![
-        Span::styled(format!("{:>6}", ""), Style::default()),
-        Span::raw(" "),
-        Span::styled(
-            center_pad("POC ON", half_w),
-            Style::default().fg(COL_POC).add_modifier(Modifier::BOLD),
-        ),
-        Span::raw(" "),
-        Span::styled(
-            center_pad("CFS (POC OFF)", half_w),
-            Style::default().fg(COL_CFS).add_modifier(Modifier::BOLD),
-        ),
-    ]);
+    // 8 for label + padding, 2 per extra column separator; saturating so
+    // no terminal width can wrap the arithmetic.
+    let col_w = (inner.width as usize)
+        .saturating_sub(8)
+        .saturating_sub(2 * n.saturating_sub(2))
+        / n.max(1);
+    let mut header = vec![Span::styled(format!("{:>6}", ""), Style::default())];
+    for (title, _, color) in &columns {
+        header.push(Span::raw(" "));
+        header.push(Span::styled(
+            center_pad(title, col_w),
+            th.fg(*color).add_modifier(Modifier::BOLD),
+        ));
+    }
 
-    let mut lines = vec![header];
+    let mut lines = vec![Line::from(header)];
 
-    // Find global max for scaling
-    let max_frac = max_histogram_frac(app.hist_on.as_ref(), app.hist_off.as_ref());
+    // Find global max for scaling; cumulative bars all end at 1.
+    let max_frac = if app.hist_cdf {
+        1.0
+    } else {
+        max_histogram_frac(columns.iter().filter_map(|c| c.1))
+    };
 
-    for bucket in 0..NUM_BUCKETS {
+    for (bucket, label) in app.bucketing.labels().iter().enumerate() {
         if lines.len() >= inner.height as usize {
             break;
         }
-        let bar_w = half_w.saturating_sub(1);
-        let on_frac = app
-            .hist_on
-            .as_ref()
-            .map(|h| h.fraction(bucket))
-            .unwrap_or(0.0);
-        let off_frac = app
-            .hist_off
-            .as_ref()
-            .map(|h| h.fraction(bucket))
-            .unwrap_or(0.0);
-
-        let on_bar = render_bar(on_frac, max_frac, bar_w, COL_POC);
-        let off_bar = render_bar(off_frac, max_frac, bar_w, COL_CFS);
-
-        let mut spans = vec![
-            Span::styled(
-                format!("{} ", BUCKET_LABELS[bucket]),
-                Style::default().fg(COL_DIM),
-            ),
-            Span::raw("\u{2502}"),
-        ];
-        spans.extend(on_bar);
-        spans.push(Span::raw("\u{2502} \u{2502}"));
-        spans.extend(off_bar);
+        let bar_w = col_w.saturating_sub(1);
+        let mut spans = vec![Span::styled(format!("{} ", label), th.fg(th.dim))];
+        for (i, (_, hist, color)) in columns.iter().enumerate() {
+            let (frac, count) = match hist {
+                Some(h) if app.hist_cdf => {
+                    (h.cumulative_fraction(bucket), h.cumulative_count(bucket))
+                }
+                Some(h) => (h.fraction(bucket), h.buckets[bucket]),
+                None => (0.0, 0),
+            };
+            let count = app.hist_counts.then_some(count);
+            spans.push(Span::raw(if i == 0 {
+                "\u{2502}"
+            } else {
+                "\u{2502} \u{2502}"
+            }));
+            spans.extend(render_bar(th, frac, max_frac, bar_w, *color, count));
+        }
         spans.push(Span::raw("\u{2502}"));
 
         lines.push(Line::from(spans));
@@ -254,23 +1125,72 @@ fn draw_histogram(f: &mut Frame, area: Rect, app: &App) {
     f.render_widget(paragraph, inner);
 }
 
+/// Most rows the summary panel in `area` (the full terminal) can scroll.
+pub fn summary_max_scroll(area: Rect, app: &App) -> usize {
+    max_scroll(layout(area)[3], summary_rows(app).len())
+}
+
+/// `rows` minus what fits in a summary panel of `area` under its header.
+fn max_scroll(area: Rect, rows: usize) -> usize {
+    rows.saturating_sub(area.height.saturating_sub(3) as usize)
+}
+
+/// Clamped scroll offset for a summary panel of `area`, and the bottom
+/// title marking which way rows are clipped (if any are).
+fn summary_scroll(app: &App, area: Rect, rows: usize) -> (usize, Option<Line<'static>>) {
+    let max = max_scroll(area, rows);
+    let skip = app.summary_scroll.min(max);
+    let hint = (max > 0).then(|| {
+        let up = if skip > 0 { "\u{2191}" } else { " " };
+        let down = if skip < max { "\u{2193}" } else { " " };
+        Line::from(format!(" {}{} j/k ", up, down)).right_aligned()
+    });
+    (skip, hint)
+}
+
 fn draw_summary(f: &mut Frame, area: Rect, app: &App) {
-    let block = Block::default()
-        .title(" Summary ")
-        .title_style(Style::default().fg(COL_LABEL))
+    let th = &app.theme;
+    let rows = summary_rows(app);
+    let (skip, hint) = summary_scroll(app, area, rows.len());
+    let rolling = app.watch.as_ref().and_then(WatchState::rolling);
+    let title = match (app.watch.as_ref(), app.significance) {
+        (Some(w), _) => format!(
+            " Summary \u{00b7} last {} of {} cycles{} ",
+            w.window.min(w.cycles.max(1)),
+            w.cycles,
+            watch_all_time(app)
+        ),
+        (None, Some(p)) => format!(
+            " Summary \u{00b7} p={:.3} ({}) ",
+            p,
+            significance_verdict(p)
+        ),
+        (None, None) => " Summary ".to_string(),
+    };
+    let mut block = Block::default()
+        .title(title)
+        .title_style(th.fg(th.label))
         .borders(Borders::ALL);
+    if let Some(hint) = hint {
+        block = block.title_bottom(hint.style(th.fg(th.dim)));
+    }
     let inner = block.inner(area);
     f.render_widget(block, area);
 
-    let (on, off) = match (app.final_on.as_ref(), app.final_off.as_ref()) {
-        (Some(on), Some(off)) => (on, off),
-        _ => {
+    // `--watch` shows the rolling window; the all-time deltas go in the title.
+    let pair = match rolling.as_ref() {
+        Some((on, off)) => Some((on, off)),
+        None => app.final_on.as_ref().zip(app.final_off.as_ref()),
+    };
+    let (on, off) = match pair {
+        Some(pair) => pair,
+        None => {
             let msg = if app.finished {
                 "No comparison data available"
             } else {
                 "Waiting for results..."
             };
-            let p = Paragraph::new(Line::from(Span::styled(msg, Style::default().fg(COL_DIM))));
+            let p = Paragraph::new(Line::from(Span::styled(msg, th.fg(th.dim))));
             f.render_widget(p, inner);
             return;
         }
@@ -280,34 +1200,22 @@ fn draw_summary(f: &mut Frame, area: Rect, app: &App) {
         Span::styled(format!("{:>12}", ""), Style::default()),
         Span::styled(
             format!("{:>14}", "POC ON"),
-            Style::default().fg(COL_POC).add_modifier(Modifier::BOLD),
+            th.fg(th.poc).add_modifier(Modifier::BOLD),
         ),
         Span::styled(
             format!("{:>14}", "CFS"),
-            Style::default().fg(COL_CFS).add_modifier(Modifier::BOLD),
+            th.fg(th.cfs).add_modifier(Modifier::BOLD),
         ),
         Span::styled(
             format!("{:>12}", "\u{0394}"),
-            Style::default()
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD),
+            th.fg(th.text).add_modifier(Modifier::BOLD),
         ),
     ])];
 
-    let rows: Vec<(&str, f64, f64, bool)> = vec![
-        ("mean", on.mean / 1000.0, off.mean / 1000.0, true),
-        (
-            "trimmed",
-            on.trimmed_mean / 1000.0,
-            off.trimmed_mean / 1000.0,
-            true,
-        ),
-        ("p50", on.p50 as f64 / 1000.0, off.p50 as f64 / 1000.0, true),
-        ("p99", on.p99 as f64 / 1000.0, off.p99 as f64 / 1000.0, true),
-        ("ops/sec", on.ops_per_sec(), off.ops_per_sec(), false),
-    ];
-
-    for (label, v_on, v_off, lower_is_better) in rows {
+    // Same rows and values as the text summary.
+    let (on, off) = (Metrics::new(on), Metrics::new(off));
+    for &(label, lower_is_better) in rows.iter().skip(skip) {
+        let (v_on, v_off) = (on.row(label), off.row(label));
         let delta = if v_off != 0.0 {
             (v_on - v_off) / v_off * 100.0
         } else {
@@ -319,27 +1227,18 @@ fn draw_summary(f: &mut Frame, area: Rect, app: &App) {
         } else {
             delta > 0.0
         };
-        let delta_color = if is_better { COL_BETTER } else { COL_WORSE };
-        let arrow = if delta < 0.0 { "\u{25bc}" } else { "\u{25b2}" };
+        let delta_color = if is_better { th.better } else { th.worse };
+        let arrow = if delta < 0.0 { th.down } else { th.up };
 
-        let (on_str, off_str) = if label == "ops/sec" {
-            (format_int(v_on), format_int(v_off))
-        } else {
-            (
-                format!("{:.2} \u{03bc}s", v_on),
-                format!("{:.2} \u{03bc}s", v_off),
-            )
-        };
+        let (on_str, off_str) = (format_row(label, v_on), format_row(label, v_off));
 
         lines.push(Line::from(vec![
-            Span::styled(format!("{:>12}", label), Style::default().fg(Color::White)),
-            Span::styled(format!("{:>14}", on_str), Style::default().fg(COL_POC)),
-            Span::styled(format!("{:>14}", off_str), Style::default().fg(COL_CFS)),
+            Span::styled(format!("{:>12}", label), th.fg(th.text)),
+            Span::styled(format!("{:>14}", on_str), th.fg(th.poc)),
+            Span::styled(format!("{:>14}", off_str), th.fg(th.cfs)),
             Span::styled(
                 format!("{:>+8.1}% {}", delta, arrow),
-                Style::default()
-                    .fg(delta_color)
-                    .add_modifier(Modifier::BOLD),
+                th.fg(delta_color).add_modifier(Modifier::BOLD),
             ),
         ]));
     }
@@ -348,22 +1247,196 @@ fn draw_summary(f: &mut Frame, area: Rect, app: &App) {
     f.render_widget(paragraph, inner);
 }
 
-fn draw_footer(f: &mut Frame, area: Rect, app: &App) {
-    let text = if app.finished {
-        "Press q to exit"
-    } else {
-        "Press q to abort"
+/// All-time mean and p99 change of a `--watch` run, for the summary title.
+fn watch_all_time(app: &App) -> String {
+    let (Some(on), Some(off)) = (app.final_on.as_ref(), app.final_off.as_ref()) else {
+        return String::new();
     };
-    let p = Paragraph::new(Line::from(Span::styled(text, Style::default().fg(COL_DIM))))
-        .alignment(ratatui::layout::Alignment::Center);
-    f.render_widget(p, area);
-}
+    let delta = |v_on: f64, v_off: f64| {
+        if v_off != 0.0 {
+            (v_on - v_off) / v_off * 100.0
+        } else {
+            0.0
+        }
+    };
+    format!(
+        " \u{00b7} all-time: mean {:+.1}%, p99 {:+.1}%",
+        delta(on.mean, off.mean),
+        delta(on.p99, off.p99)
+    )
+}
+
+/// `--modes` summary: one column per sysctl value, then the change of
+/// every later value against the first.
+fn draw_mode_summary(f: &mut Frame, area: Rect, app: &App) {
+    let th = &app.theme;
+    let rows = summary_rows(app);
+    let (skip, hint) = summary_scroll(app, area, rows.len());
+    let mut block = Block::default()
+        .title(" Summary ")
+        .title_style(th.fg(th.label))
+        .borders(Borders::ALL);
+    if let Some(hint) = hint {
+        block = block.title_bottom(hint.style(th.fg(th.dim)));
+    }
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let results: Option<Vec<&StatResult>> = app.modes.iter().map(|m| m.stats.as_ref()).collect();
+    let Some(results) = results else {
+        let msg = if app.finished {
+            "No comparison data available"
+        } else {
+            "Waiting for results..."
+        };
+        let p = Paragraph::new(Line::from(Span::styled(msg, th.fg(th.dim))));
+        f.render_widget(p, inner);
+        return;
+    };
+
+    let mut header = vec![Span::styled(format!("{:>10}", ""), Style::default())];
+    for (i, m) in app.modes.iter().enumerate() {
+        header.push(Span::styled(
+            format!("{:>12}", format!("POC={}", m.value)),
+            th.fg(th.mode(i)).add_modifier(Modifier::BOLD),
+        ));
+    }
+    for m in &app.modes[1..] {
+        header.push(Span::styled(
+            format!("{:>11}", format!("\u{0394}{}", m.value)),
+            th.fg(th.text).add_modifier(Modifier::BOLD),
+        ));
+    }
+    let mut lines = vec![Line::from(header)];
+
+    let results: Vec<Metrics> = results.into_iter().map(Metrics::new).collect();
+    for &(label, lower_is_better) in rows.iter().skip(skip) {
+        let values: Vec<f64> = results.iter().map(|m| m.row(label)).collect();
+        let mut spans = vec![Span::styled(format!("{:>10}", label), th.fg(th.text))];
+        for (i, &v) in values.iter().enumerate() {
+            let s = format_row(label, v);
+            spans.push(Span::styled(format!("{:>12}", s), th.fg(th.mode(i))));
+        }
+        for &v in &values[1..] {
+            let delta = if values[0] != 0.0 {
+                (v - values[0]) / values[0] * 100.0
+            } else {
+                0.0
+            };
+            let is_better = if lower_is_better {
+                delta < 0.0
+            } else {
+                delta > 0.0
+            };
+            let arrow = if delta < 0.0 { th.down } else { th.up };
+            spans.push(Span::styled(
+                format!("{:>+8.1}% {}", delta, arrow),
+                th.fg(if is_better { th.better } else { th.worse })
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    let paragraph = Paragraph::new(lines);
+    f.render_widget(paragraph, inner);
+}
+
+/// Rows of every summary, TUI panels included, and whether lower is better.
+const SUMMARY_ROWS: [(&str, bool); 14] = [
+    ("mean", true),
+    ("trimmed", true),
+    ("p50", true),
+    ("p90", true),
+    ("p95", true),
+    ("p99", true),
+    ("p99.9", true),
+    ("min", true),
+    ("max", true),
+    ("stddev", true),
+    ("cov", true),
+    ("jitter", true),
+    ("p99/p50", true),
+    ("ops/sec", false),
+];
+
+/// Rows of every summary, on screen and written out: `SUMMARY_ROWS`,
+/// plus the geometric mean after the mean with `--show-geomean`.
+fn summary_rows(app: &App) -> Vec<(&'static str, bool)> {
+    let mut rows = SUMMARY_ROWS.to_vec();
+    if app.show_geomean {
+        rows.insert(1, ("geomean", true));
+    }
+    rows
+}
+
+fn significance_verdict(p: f64) -> &'static str {
+    if p < stats::SIGNIFICANCE_ALPHA {
+        "significant"
+    } else {
+        "not significant"
+    }
+}
+
+fn draw_footer(f: &mut Frame, area: Rect, app: &App) {
+    let th = &app.theme;
+    let text = if app.finished {
+        "Press r to run again, q to exit"
+    } else {
+        "Press space to pause, q to abort"
+    };
+    let line = match app.selected_bucket {
+        Some(bucket) => Line::from(vec![
+            Span::styled(bucket_status(app, bucket), th.fg(th.label)),
+            Span::styled(format!(" \u{2502} {}", text), th.fg(th.dim)),
+        ]),
+        None => Line::from(Span::styled(text, th.fg(th.dim))),
+    };
+    let p = Paragraph::new(line).alignment(ratatui::layout::Alignment::Center);
+    f.render_widget(p, area);
+}
+
+fn bucket_status(app: &App, bucket: usize) -> String {
+    let range = match app.bucketing.range_us(bucket) {
+        (lo, Some(hi)) => format!("[{}, {}) \u{03bc}s", lo, hi),
+        (lo, None) => format!(">= {} \u{03bc}s", lo),
+    };
+    let count = |h: &Option<Histogram>| match h {
+        Some(h) if bucket < h.buckets.len() => format!(
+            "{} ({:.1}%)",
+            format_int(h.buckets[bucket] as f64),
+            h.fraction(bucket) * 100.0
+        ),
+        _ => "-".into(),
+    };
+    if !app.modes.is_empty() {
+        let cells: Vec<String> = app
+            .modes
+            .iter()
+            .map(|m| format!("POC={} {}", m.value, count(&m.hist)))
+            .collect();
+        return format!("{}: {}", range, cells.join(" \u{00b7} "));
+    }
+    format!(
+        "{}: POC ON {} \u{00b7} CFS {}",
+        range,
+        count(&app.hist_on),
+        count(&app.hist_off)
+    )
+}
 
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
 
-fn render_bar(frac: f64, max_frac: f64, width: usize, color: Color) -> Vec<Span<'static>> {
+fn render_bar(
+    th: &Theme,
+    frac: f64,
+    max_frac: f64,
+    width: usize,
+    color: Color,
+    count: Option<u32>,
+) -> Vec<Span<'static>> {
     if max_frac <= 0.0 || width == 0 {
         return vec![Span::raw(" ".repeat(width))];
     }
@@ -371,134 +1444,1166 @@ fn render_bar(frac: f64, max_frac: f64, width: usize, color: Color) -> Vec<Span<
     let filled = filled.min(width);
     let empty = width - filled;
 
-    let pct = if frac > 0.001 {
-        format!("{:>4.1}%", frac * 100.0)
-    } else {
-        "     ".to_string()
+    // The bar length stays a fraction either way, so columns with
+    // different totals remain comparable.
+    let pct = match count {
+        Some(c) if c > 0 => format!("{:>5}", format_int(c as f64)),
+        Some(_) => "     ".to_string(),
+        None if frac > 0.001 => format!("{:>4.1}%", frac * 100.0),
+        None => "     ".to_string(),
     };
 
-    // Overlay percentage on the bar
-    let bar_str = "\u{2588}".repeat(filled) + &" ".repeat(empty);
+    // Overlay percentage (or count) on the bar
+    let bar_str = th.bar.repeat(filled) + &" ".repeat(empty);
     let bar_chars: Vec<char> = bar_str.chars().collect();
 
-    if bar_chars.len() >= pct.len() + 1 && filled >= pct.len() + 1 {
+    if bar_chars.len() > pct.len() && filled > pct.len() {
         // Draw percentage inside the bar
         let before = filled - pct.len() - 1;
         let after = empty;
         vec![
-            Span::styled("\u{2588}".repeat(before + 1), Style::default().fg(color)),
-            Span::styled(pct, Style::default().fg(Color::Black).bg(color)),
-            Span::styled(" ".repeat(after), Style::default().fg(COL_DIM)),
+            Span::styled(th.bar.repeat(before + 1), th.fg(color)),
+            Span::styled(pct, th.on(color)),
+            Span::styled(" ".repeat(after), th.fg(th.dim)),
         ]
     } else {
         vec![
-            Span::styled("\u{2588}".repeat(filled), Style::default().fg(color)),
-            Span::styled(" ".repeat(empty), Style::default().fg(COL_DIM)),
+            Span::styled(th.bar.repeat(filled), th.fg(color)),
+            Span::styled(" ".repeat(empty), th.fg(th.dim)),
         ]
     }
 }
 
-fn max_histogram_frac(a: Option<&Histogram>, b: Option<&Histogram>) -> f64 {
+fn cstate_penalty_line(app: &App) -> Option<String> {
+    let cs = app.cstate.as_ref()?;
+    if !cs.available {
+        return Some("C-state compare: unavailable (cannot open /dev/cpu_dma_latency)".into());
+    }
+    let fmt = |v: Option<f64>| match v {
+        Some(ns) => format!("{:+.2} \u{03bc}s", ns / 1000.0),
+        None => "-".into(),
+    };
+    match cs.penalty(app) {
+        (None, None) => None,
+        (on, off) => Some(format!(
+            "Deep C-state penalty (trimmed): POC ON {} \u{00b7} CFS {}",
+            fmt(on),
+            fmt(off)
+        )),
+    }
+}
+
+/// Histogram panel columns: POC ON and CFS, or one per `--modes` value.
+fn histogram_columns(app: &App) -> Vec<(String, Option<&Histogram>, Color)> {
+    let th = &app.theme;
+    if app.modes.is_empty() {
+        return vec![
+            ("POC ON".to_string(), app.hist_on.as_ref(), th.poc),
+            ("CFS (POC OFF)".to_string(), app.hist_off.as_ref(), th.cfs),
+        ];
+    }
+    app.modes
+        .iter()
+        .enumerate()
+        .map(|(i, m)| (format!("POC={}", m.value), m.hist.as_ref(), th.mode(i)))
+        .collect()
+}
+
+fn max_histogram_frac<'a>(hists: impl IntoIterator<Item = &'a Histogram>) -> f64 {
     let mut max = 0.0_f64;
-    for i in 0..NUM_BUCKETS {
-        if let Some(h) = a {
-            max = max.max(h.fraction(i));
-        }
-        if let Some(h) = b {
+    for h in hists {
+        for i in 0..h.buckets.len() {
             max = max.max(h.fraction(i));
         }
     }
     max
 }
 
+/// `s` centered in `width` columns, or cut to them; counts chars, not
+/// bytes, so titles like "Δ" or "μs" never split mid-character.
 fn center_pad(s: &str, width: usize) -> String {
-    if s.len() >= width {
-        return s[..width].to_string();
+    let len = s.chars().count();
+    if len >= width {
+        return s.chars().take(width).collect();
     }
-    let pad = (width - s.len()) / 2;
-    format!(
-        "{}{}{}",
-        " ".repeat(pad),
-        s,
-        " ".repeat(width - pad - s.len())
-    )
+    let pad = (width - len) / 2;
+    format!("{}{}{}", " ".repeat(pad), s, " ".repeat(width - pad - len))
 }
 
-fn format_int(v: f64) -> String {
-    let v = v as u64;
-    if v >= 1_000_000 {
-        format!(
-            "{},{:03},{:03}",
-            v / 1_000_000,
-            (v / 1_000) % 1_000,
-            v % 1_000
-        )
-    } else if v >= 1_000 {
-        format!("{},{:03}", v / 1_000, v % 1_000)
+/// Elapsed and estimated time of the current phase and the time left for
+/// the whole run, e.g. ` 3.2s / ~12s · ~1m05s left`. Estimates are linear
+/// in `progress` and only shown once there is some to go on.
+fn timing_label(app: &App) -> String {
+    let Some(phase_start) = app.phase_start.filter(|_| !app.finished) else {
+        return String::new();
+    };
+    let elapsed = phase_start.elapsed().as_secs_f64();
+    let mut s = format!(" {:.1}s", elapsed);
+    if app.progress > 0.01 {
+        s += &format!(" / ~{:.0}s", elapsed / app.progress.min(1.0));
+    }
+    let overall = app.overall_progress();
+    if let Some(run_start) = app.run_start.filter(|_| overall > 0.01) {
+        let run = run_start.elapsed().as_secs_f64();
+        let left = run * (1.0 - overall) / overall;
+        s += &format!(" \u{00b7} ~{} left", format_secs(left));
+    }
+    s
+}
+
+pub fn format_secs(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
     } else {
-        format!("{}", v)
+        format!("{}s", secs)
+    }
+}
+
+/// A summary row value: ops/sec as an integer, cov in percent, min and
+/// max (whose range spans ns to ms) in the unit that fits, anything else
+/// in μs so the rows compare at a glance.
+fn format_row(label: &str, v: f64) -> String {
+    match label {
+        "ops/sec" => format_int(v),
+        "cov" => format!("{:.1} %", v),
+        "p99/p50" => format!("{:.2}x", v),
+        "min" | "max" => format_duration_ns(v * 1000.0),
+        _ => format!("{:.2} \u{03bc}s", v),
     }
 }
 
+/// `ns` in ns below 1 μs, μs below 1 ms and ms from there on, picked after
+/// rounding so 999.996 μs reads `1.00 ms` rather than `1000.00 μs`.
+fn format_duration_ns(ns: f64) -> String {
+    let abs = ns.abs();
+    if abs < 999.5 {
+        // + 0.0 turns a -0 from rounding into 0.
+        format!("{:.0} ns", ns.round() + 0.0)
+    } else if abs < 999_995.0 {
+        format!("{:.2} \u{03bc}s", ns / 1e3)
+    } else {
+        format!("{:.2} ms", ns / 1e6)
+    }
+}
+
+/// `v` rounded to an integer with comma thousands separators, any
+/// magnitude and sign (`-1,234,567`).
+fn format_int(v: f64) -> String {
+    let v = v.round();
+    let digits = format!("{:.0}", v.abs());
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if v < 0.0 {
+        out.push('-');
+    }
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
 // ---------------------------------------------------------------------------
 // Plain-text summary (printed after TUI exits)
 // ---------------------------------------------------------------------------
 
-pub fn print_summary(app: &App) {
-    println!();
-    println!("=== POC Selector Benchmark Results ===");
-    println!("CPU: {}", app.system.cpu_model);
+/// `--format json`: the [`BenchReport`] as one JSON object on stdout.
+pub fn write_json(out: &mut dyn Write, app: &App) -> io::Result<()> {
+    let report = BenchReport::from_app(app);
+    let s = serde_json::to_string_pretty(&report).map_err(io::Error::other)?;
+    writeln!(out, "{}", s)
+}
+
+/// `--format markdown`: system info as a bullet list, then the summary rows
+/// as a GitHub-flavored table, ready to paste into a PR or mail.
+pub fn write_markdown(out: &mut dyn Write, app: &App) -> io::Result<()> {
+    let r = BenchReport::from_app(app);
+    writeln!(out, "### POC Selector Benchmark Results")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "- **CPU:** {} ({} CPUs)",
+        r.system.cpu_model, r.system.ncpus
+    )?;
+    writeln!(out, "- **Kernel:** {}", r.system.kernel)?;
+    let sys = &r.system;
+    writeln!(
+        out,
+        "- **HW:** POPCNT={} CTZ={} PTSelect={} LZCNT={} AVX2={} AVX-512F={}",
+        sys.popcnt, sys.ctz, sys.ptselect, sys.lzcnt, sys.avx2, sys.avx512f
+    )?;
+    let p = &r.params;
+    writeln!(
+        out,
+        "- **Config:** {} workers{}, {} bg{}, {} idle, {} shadows/w",
+        p.n_workers,
+        worker_suffix(&p.worker_priorities, p.worker_nice),
+        p.n_background,
+        bg_suffix(p.bg_workload, p.n_background),
+        p.n_idle,
+        p.shadows_per_worker,
+    )?;
+    if p.cross_cpu {
+        writeln!(out, "- **Scenario:** cross-CPU wakeups")?;
+    }
+    match r.calibration {
+        Some(ref cal) => writeln!(
+            out,
+            "- **Calibration:** {} iterations, {} warmup (probe: mean={:.1}μs stddev={:.1}μs){}",
+            cal.iterations,
+            cal.warmup,
+            cal.probe_mean_us,
+            cal.probe_stddev_us,
+            if cal.cached { " [cached]" } else { "" },
+        )?,
+        None => writeln!(out, "- **Calibration:** none (fixed iteration count)")?,
+    }
+    if let Some(n) = r.iterations_per_round {
+        writeln!(out, "- **Rounds:** {} iterations each", n)?;
+    }
+    writeln!(out, "- **Seed:** {}", r.seed)?;
+    for warning in &r.warnings {
+        writeln!(out, "- **Warning:** {}", warning)?;
+    }
+    if let Some(ref err) = r.error {
+        writeln!(out, "- **Error:** {}", err)?;
+    }
+
+    let (cols, base) = r.columns();
+    if cols.iter().all(|(_, sr)| sr.is_none()) {
+        return Ok(());
+    }
+    let others: Vec<usize> = (0..cols.len()).filter(|&i| i != base).collect();
+    let delta_title = |i: usize| {
+        if others.len() == 1 {
+            "Δ%".to_string()
+        } else {
+            format!("Δ% {} vs {}", cols[i].0, cols[base].0)
+        }
+    };
+
+    writeln!(out)?;
+    let mut header = "| Metric |".to_string();
+    let mut rule = "|---|".to_string();
+    for (name, _) in &cols {
+        header += &format!(" {} |", name);
+        rule += "---:|";
+    }
+    for &i in &others {
+        header += &format!(" {} |", delta_title(i));
+        rule += "---:|";
+    }
+    writeln!(out, "{}", header)?;
+    writeln!(out, "{}", rule)?;
+    for (label, _) in summary_rows(app) {
+        let values: Vec<Option<f64>> = cols.iter().map(|(_, m)| m.map(|m| m.row(label))).collect();
+        let mut line = format!("| {} |", label);
+        for v in &values {
+            line += &format!(
+                " {} |",
+                v.map_or_else(|| "-".into(), |v| format_row(label, v))
+            );
+        }
+        for &i in &others {
+            let s = match (values[base], values[i]) {
+                (Some(b), Some(v)) if b != 0.0 => format!("{:+.1}%", (v - b) / b * 100.0),
+                _ => "-".into(),
+            };
+            line += &format!(" {} |", s);
+        }
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// Prometheus label value: backslash, double quote and newline escaped.
+fn prom_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// `--format prometheus`: gauges in the text exposition format, for the
+/// node_exporter textfile collector.
+pub fn write_prometheus(out: &mut dyn Write, app: &App) -> io::Result<()> {
+    let r = BenchReport::from_app(app);
+    let cpu = prom_escape(&r.system.cpu_model);
+    let modes: Vec<(String, &Metrics)> = if r.modes.is_empty() {
+        [("on", &r.poc_on), ("off", &r.poc_off)]
+            .into_iter()
+            .filter_map(|(mode, m)| m.as_ref().map(|m| (mode.to_string(), &m.metrics)))
+            .collect()
+    } else {
+        r.modes
+            .iter()
+            .filter_map(|m| {
+                m.result
+                    .as_ref()
+                    .map(|res| (m.value.to_string(), &res.metrics))
+            })
+            .collect()
+    };
+
+    writeln!(
+        out,
+        "# HELP poc_bench_latency_ns Wakeup latency quantiles in nanoseconds."
+    )?;
+    writeln!(out, "# TYPE poc_bench_latency_ns gauge")?;
+    for (mode, sr) in &modes {
+        for (q, v) in [
+            ("0.5", sr.p50),
+            ("0.9", sr.p90),
+            ("0.99", sr.p99),
+            ("0.999", sr.p999),
+        ] {
+            writeln!(
+                out,
+                "poc_bench_latency_ns{{mode=\"{}\",quantile=\"{}\",cpu_model=\"{}\"}} {}",
+                mode, q, cpu, v
+            )?;
+        }
+    }
+    type Gauge = (&'static str, &'static str, fn(&Metrics) -> f64);
+    let gauges: [Gauge; 3] = [
+        (
+            "latency_mean_ns",
+            "Mean wakeup latency in nanoseconds.",
+            |m| m.mean,
+        ),
+        (
+            "ops_per_second",
+            "Wakeups per second (1e9 / trimmed mean).",
+            |m| m.ops_per_sec,
+        ),
+        ("samples", "Latency samples measured.", |m| m.count as f64),
+    ];
+    for (name, help, value) in gauges {
+        writeln!(out, "# HELP poc_bench_{} {}", name, help)?;
+        writeln!(out, "# TYPE poc_bench_{} gauge", name)?;
+        for (mode, sr) in &modes {
+            writeln!(
+                out,
+                "poc_bench_{}{{mode=\"{}\",cpu_model=\"{}\"}} {}",
+                name,
+                mode,
+                cpu,
+                value(sr)
+            )?;
+        }
+    }
+    if let (Some(on), Some(off)) = (r.poc_on.as_ref(), r.poc_off.as_ref()) {
+        let (on, off) = (&on.metrics, &off.metrics);
+        writeln!(
+            out,
+            "# HELP poc_bench_delta_percent POC ON vs CFS change in percent (negative = POC faster)."
+        )?;
+        writeln!(out, "# TYPE poc_bench_delta_percent gauge")?;
+        for (label, _) in summary_rows(app) {
+            let (v_on, v_off) = (on.row(label), off.row(label));
+            if v_off == 0.0 {
+                continue;
+            }
+            writeln!(
+                out,
+                "poc_bench_delta_percent{{metric=\"{}\",cpu_model=\"{}\"}} {}",
+                label,
+                cpu,
+                (v_on - v_off) / v_off * 100.0
+            )?;
+        }
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64());
+    writeln!(
+        out,
+        "# HELP poc_bench_timestamp_seconds Unix time the results were written."
+    )?;
+    writeln!(out, "# TYPE poc_bench_timestamp_seconds gauge")?;
+    writeln!(out, "poc_bench_timestamp_seconds {:.3}", now)
+}
+
+/// `rows` of an ON/OFF pair with the change against CFS.
+fn write_compare_rows(
+    out: &mut dyn Write,
+    rows: &[(&str, bool)],
+    on: &Metrics,
+    off: &Metrics,
+) -> io::Result<()> {
+    for &(label, _lower_is_better) in rows {
+        let (v_on, v_off) = (on.row(label), off.row(label));
+        let delta = if v_off != 0.0 {
+            (v_on - v_off) / v_off * 100.0
+        } else {
+            0.0
+        };
+        let (on_s, off_s) = (format_row(label, v_on), format_row(label, v_off));
+        writeln!(
+            out,
+            "{:>12} {:>14} {:>14} {:>+8.1}%",
+            label, on_s, off_s, delta
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_summary(out: &mut dyn Write, app: &App) -> io::Result<()> {
+    writeln!(out)?;
+    writeln!(out, "=== POC Selector Benchmark Results ===")?;
+    writeln!(out, "CPU: {}", app.system.cpu_model)?;
+    writeln!(out, "Kernel: {}", app.system.kernel)?;
+    if let Some(banner) = app.poc_support.banner(&app.system.kernel) {
+        writeln!(out, "Warning: {}", banner)?;
+    }
+    if app.needs_root {
+        writeln!(out, "Warning: {}", ROOT_HINT)?;
+    }
+    writeln!(out, "Theme: {}", app.theme.name)?;
     let hw = &app.system.hw_features;
-    println!(
-        "HW:  POPCNT={} CTZ={} PTSelect={}",
-        hw.popcnt, hw.ctz, hw.ptselect
-    );
-    println!(
-        "Config: {} CPUs, {} workers, {} bg, {} idle, {} shadows/w",
+    writeln!(
+        out,
+        "HW:  POPCNT={} CTZ={} PTSelect={} LZCNT={} AVX2={} AVX-512F={}",
+        hw.popcnt, hw.ctz, hw.ptselect, hw.lzcnt, hw.avx2, hw.avx512f
+    )?;
+    writeln!(
+        out,
+        "Config: {} CPUs, {} workers{}, {} bg{}, {} idle, {} shadows/w",
         app.system.ncpus,
         app.params.n_workers,
+        worker_suffix(&app.params.worker_priorities, app.params.worker_nice),
         app.params.n_background,
+        bg_suffix(app.params.bg_workload.label(), app.params.n_background),
         app.params.n_idle,
         app.params.shadows_per_worker,
-    );
+    )?;
+    writeln!(out, "Seed: {} (replay with --seed {})", app.seed, app.seed)?;
+    writeln!(
+        out,
+        "SMT: {}{}",
+        app.system.smt_summary(),
+        if app.params.smt_cpus.is_empty() {
+            ""
+        } else {
+            "; workers on one thread per core (--no-smt)"
+        }
+    )?;
+    if app.system.numa_nodes.len() > 1 {
+        let nodes: Vec<String> = app
+            .system
+            .numa_nodes
+            .iter()
+            .map(|n| format!("node{}={}", n.id, system::format_cpu_list(&n.cpus)))
+            .collect();
+        writeln!(
+            out,
+            "NUMA: {} placement ({})",
+            app.params.numa.label(),
+            nodes.join(" ")
+        )?;
+    }
+    if app.params.oversubscribe > 0.0 {
+        writeln!(
+            out,
+            "Oversubscription: {:.1}x online CPUs ({} unpinned busy threads)",
+            app.params.oversubscribe,
+            app.params.oversubscribe_threads(app.system.ncpus),
+        )?;
+    }
+    if app.params.work_ns > 0 {
+        writeln!(
+            out,
+            "Work: {}μs busy-spin per wakeup",
+            app.params.work_ns / 1000
+        )?;
+    }
+    if let Some(warn) = app.system.freq.warning() {
+        writeln!(out, "Warning: {}", warn)?;
+    }
+    if let Some(d) = app.params.phase_duration {
+        writeln!(out, "Duration: {:.1}s per measured phase", d.as_secs_f64())?;
+    }
     if let Some(ref cal) = app.calibration {
-        println!(
-            "Calibrated: {} iterations (probe: mean={:.1}μs stddev={:.1}μs)",
-            cal.iterations, cal.probe_mean_us, cal.probe_stddev_us,
-        );
+        writeln!(
+            out,
+            "Calibrated: {} iterations (probe: mean={:.1}μs stddev={:.1}μs){}",
+            cal.iterations,
+            cal.probe_mean_us,
+            cal.probe_stddev_us,
+            if app.calibration_cached {
+                " [cached]"
+            } else {
+                ""
+            },
+        )?;
+        if cal.attempts > 1 {
+            writeln!(
+                out,
+                "Calibration: {} probe attempts, stddev/mean above {} (noisy system)",
+                cal.attempts,
+                calibrate::MAX_PROBE_CV,
+            )?;
+        }
+    }
+    if let Some(ns) = app.clock_cost_ns {
+        writeln!(
+            out,
+            "Clock: {:.1}ns per read{}",
+            ns,
+            if ns > bench::CLOCK_SYSCALL_THRESHOLD_NS {
+                " -- clock_gettime looks like a syscall (no vDSO?), all latencies are inflated"
+            } else {
+                ""
+            },
+        )?;
+    }
+    if let Some(hz) = app.tsc_hz {
+        writeln!(
+            out,
+            "Timer: TSC at {:.3} GHz (calibrated against CLOCK_MONOTONIC)",
+            hz / 1e9
+        )?;
+    }
+    writeln!(
+        out,
+        "Timer overhead: {}ns per timestamp{}",
+        app.timer_overhead_ns,
+        if app.params.subtract_overhead_ns > 0 {
+            " (subtracted from every sample)"
+        } else {
+            ""
+        }
+    )?;
+    if app.params.settle_ns != bench::DEFAULT_SETTLE_NS {
+        writeln!(out, "Settle: {}ns after each batch", app.params.settle_ns)?;
+    }
+    if app.worker_prio_denied {
+        writeln!(
+            out,
+            "Workers: priority/nice not applied in some phases (needs CAP_SYS_NICE), ran at the default"
+        )?;
+    }
+    if app.params.cross_cpu {
+        writeln!(
+            out,
+            "Scenario: cross-CPU wakeups (dispatcher moved off a worker's core {} times)",
+            app.dispatcher_moves
+        )?;
+    }
+    match app.quiesce {
+        Some(q) if q.settled => writeln!(out, "Quiet check: settled after {} probes", q.probes)?,
+        Some(q) => writeln!(
+            out,
+            "Warning: system not quiet after {} probes; measured anyway",
+            q.probes
+        )?,
+        None => {}
+    }
+    if let Some((requested, allowed)) = app.iterations_capped {
+        writeln!(
+            out,
+            "Memory: iterations reduced from {} to {} to fit --max-memory-mb",
+            requested, allowed,
+        )?;
+    }
+    if let (Some(target), Some(_)) = (app.rounds_precision, app.final_off.as_ref()) {
+        match app.round_delta_ci {
+            Some((delta, half)) => writeln!(
+                out,
+                "Rounds: adaptive, {} run; trimmed mean Δ {:+.2}% ± {:.2}% (target ± {:.2}%{})",
+                app.round_order.len(),
+                delta,
+                half,
+                target,
+                if half <= target {
+                    ", reached"
+                } else {
+                    ", not reached"
+                },
+            )?,
+            None => writeln!(
+                out,
+                "Rounds: adaptive, {} run; too few for a CI (target ± {:.2}%)",
+                app.round_order.len(),
+                target
+            )?,
+        }
+    }
+    if let Some(n) = app.iterations_per_round {
+        writeln!(
+            out,
+            "Rounds: {} iterations each (--iterations-per-round)",
+            n
+        )?;
+    }
+    if app.random_order {
+        let order: Vec<&str> = app
+            .round_order
+            .iter()
+            .map(|&on_first| if on_first { "ON/CFS" } else { "CFS/ON" })
+            .collect();
+        writeln!(out, "Round order: random: {}", order.join(" "))?;
+    }
+    match app.sysctl_path {
+        Some(ref p) => writeln!(out, "Sysctl: {}", p)?,
+        None => writeln!(out, "Sysctl: not found (single run)")?,
+    }
+    if let Some(ref pw) = app.power {
+        writeln!(
+            out,
+            "Power: {:.2} to detect a {:.1}% change at p<0.05 ({} samples/mode){}",
+            pw.power,
+            pw.effect_pct,
+            pw.samples_per_mode,
+            if pw.power < stats::MIN_POWER {
+                " -- underpowered, increase --iterations or --rounds"
+            } else {
+                ""
+            },
+        )?;
+    }
+    if let WarmupStrategy::Stable { tolerance } = app.params.warmup_strategy {
+        let d = &app.warmup_discarded;
+        if let (Some(min), Some(max)) = (d.iter().min(), d.iter().max()) {
+            writeln!(
+                out,
+                "Warmup: stable (p50 within {:.1}%), discarded {}..{} iterations (avg {})",
+                tolerance * 100.0,
+                min,
+                max,
+                d.iter().sum::<usize>() / d.len(),
+            )?;
+        }
+    }
+    if let Some(ref err) = app.error {
+        writeln!(out, "Error: {}", err)?;
+    }
+    if app.cpus_at_end != app.system.ncpus {
+        writeln!(
+            out,
+            "Warning: online CPU count changed during the run ({} -> {}); results are unreliable",
+            app.system.ncpus, app.cpus_at_end,
+        )?;
+    }
+    let min_p50 = [app.final_on.as_ref(), app.final_off.as_ref()]
+        .into_iter()
+        .flatten()
+        .filter(|sr| sr.count > 0)
+        .map(|sr| sr.p50)
+        .min_by(f64::total_cmp);
+    if let Some(p50) = min_p50 {
+        if app.clock_res_ns > 0 && p50 < (app.clock_res_ns * bench::CLOCK_RES_MULTIPLE) as f64 {
+            writeln!(
+                out,
+                "Warning: p50 {:.2}μs is within {}x the clock resolution ({}ns); latencies are quantized",
+                p50 / 1000.0,
+                bench::CLOCK_RES_MULTIPLE,
+                app.clock_res_ns,
+            )?;
+        }
     }
 
     if let (Some(on), Some(off)) = (app.final_on.as_ref(), app.final_off.as_ref()) {
-        println!();
-        println!("{:>12} {:>14} {:>14} {:>12}", "", "POC ON", "CFS", "Δ");
-        let rows: Vec<(&str, f64, f64, bool)> = vec![
-            ("mean", on.mean / 1000.0, off.mean / 1000.0, true),
-            (
-                "trimmed",
+        writeln!(out)?;
+        writeln!(out, "{:>12} {:>14} {:>14} {:>12}", "", "POC ON", "CFS", "Δ")?;
+        write_compare_rows(
+            out,
+            &summary_rows(app),
+            &Metrics::new(on),
+            &Metrics::new(off),
+        )?;
+        writeln!(
+            out,
+            "{:>12} POC ON {} · CFS {}",
+            "outliers:",
+            outlier_text(on),
+            outlier_text(off)
+        )?;
+        if let (Some(ci_on), Some(ci_off)) = (app.ci_on, app.ci_off) {
+            let half = |(lo, hi): (f64, f64)| (hi - lo) / 2.0 / 1000.0;
+            writeln!(out)?;
+            writeln!(
+                out,
+                "mean ({:.0}% CI): POC ON {:.2} ± {:.2} μs · CFS {:.2} ± {:.2} μs",
+                app.confidence * 100.0,
+                on.mean / 1000.0,
+                half(ci_on),
+                off.mean / 1000.0,
+                half(ci_off),
+            )?;
+        }
+        if let Some(p) = app.significance {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "Mann-Whitney U: p={:.4} ({} at p<{})",
+                p,
+                significance_verdict(p),
+                stats::SIGNIFICANCE_ALPHA,
+            )?;
+        }
+        if let (Some(b_on), Some(b_off)) = (app.between_on, app.between_off) {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "trimmed mean (between-round): POC ON {:.2} ± {:.2} μs · CFS {:.2} ± {:.2} μs",
                 on.trimmed_mean / 1000.0,
+                b_on / 1000.0,
                 off.trimmed_mean / 1000.0,
-                true,
+                b_off / 1000.0,
+            )?;
+        }
+        let deltas = stats::round_p99_deltas(&app.rounds_on, &app.rounds_off);
+        if deltas.len() > 1 {
+            writeln!(
+                out,
+                "p99 change per round: {} (stddev {:.1}% over {} rounds)",
+                deltas
+                    .iter()
+                    .map(|d| format!("{:+.1}%", d))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                stats::sample_stddev(&deltas),
+                deltas.len(),
+            )?;
+        }
+    }
+    if let Some(w) = app.watch.as_ref() {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "Watch: {} cycles, one every {}s; the table above is all-time",
+            w.cycles,
+            w.interval.as_secs()
+        )?;
+        if let Some((on, off)) = w.rolling() {
+            writeln!(out, "last {} cycles:", w.window.min(w.cycles))?;
+            write_compare_rows(
+                out,
+                &summary_rows(app),
+                &Metrics::new(&on),
+                &Metrics::new(&off),
+            )?;
+        }
+    }
+    if app.shadow_on.is_some() || app.shadow_off.is_some() {
+        let part = |label: &str, sr: &Option<StatResult>| {
+            sr.as_ref().map(|s| {
+                format!(
+                    "{} p50 {:.2} / p99 {:.2} μs",
+                    label,
+                    s.p50 / 1000.0,
+                    s.p99 / 1000.0
+                )
+            })
+        };
+        let parts: Vec<String> = [part("POC ON", &app.shadow_on), part("CFS", &app.shadow_off)]
+            .into_iter()
+            .flatten()
+            .collect();
+        writeln!(out)?;
+        writeln!(out, "shadow ack round trip: {}", parts.join(" · "))?;
+    }
+    if !app.modes.is_empty() {
+        write_mode_summary(out, app)?;
+    }
+    if !app.priority_classes.is_empty() {
+        write_priority_classes(out, app)?;
+    }
+    if app.params.perf {
+        write_perf_summary(out, app)?;
+    }
+    if app.idle_on.is_some() || app.idle_off.is_some() {
+        write_idle_summary(out, app)?;
+    }
+    if app.sched_on.is_some() || app.sched_off.is_some() {
+        write_sched_summary(out, app)?;
+    }
+    if !app.depth_sweep.is_empty() {
+        write_depth_sweep(out, app)?;
+    }
+    if let Some(ref cs) = app.cstate {
+        write_cstate_summary(out, app, cs)?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_mode_summary(out: &mut dyn Write, app: &App) -> io::Result<()> {
+    writeln!(out)?;
+    let base = app.modes[0].value;
+    let mut header = format!("{:>12}", "");
+    for m in &app.modes {
+        header += &format!(" {:>14}", format!("POC={}", m.value));
+    }
+    for m in &app.modes[1..] {
+        header += &format!(" {:>12}", format!("Δ{} vs {}", m.value, base));
+    }
+    writeln!(out, "{}", header)?;
+    let report = BenchReport::from_app(app);
+    let (cols, _) = report.columns();
+    for (label, _) in summary_rows(app) {
+        let values: Vec<Option<f64>> = cols.iter().map(|(_, m)| m.map(|m| m.row(label))).collect();
+        let mut line = format!("{:>12}", label);
+        for v in &values {
+            let s = v.map_or_else(|| "-".into(), |v| format_row(label, v));
+            line += &format!(" {:>14}", s);
+        }
+        for v in &values[1..] {
+            let s = match (values[0], v) {
+                (Some(b), Some(v)) if b != 0.0 => format!("{:>+8.1}%", (v - b) / b * 100.0),
+                _ => "-".into(),
+            };
+            line += &format!(" {:>12}", s);
+        }
+        writeln!(out, "{}", line)?;
+    }
+    let outliers: Vec<String> = app
+        .modes
+        .iter()
+        .filter_map(|m| {
+            let sr = m.stats.as_ref()?;
+            Some(format!("POC={} {}", m.value, outlier_text(sr)))
+        })
+        .collect();
+    writeln!(out, "{:>12} {}", "outliers:", outliers.join(" · "))?;
+    Ok(())
+}
+
+/// Samples left out of the trimmed mean, e.g. `12 (max 840.00 μs)`.
+fn outlier_text(sr: &StatResult) -> String {
+    if sr.outlier_count == 0 {
+        return "0".into();
+    }
+    format!(
+        "{} (max {})",
+        sr.outlier_count,
+        format_duration_ns(sr.max_outlier_ns as f64)
+    )
+}
+
+fn write_cstate_summary(out: &mut dyn Write, app: &App, cs: &CStateCompare) -> io::Result<()> {
+    writeln!(out)?;
+    if !cs.available {
+        writeln!(
+            out,
+            "C-state compare: unavailable (cannot open /dev/cpu_dma_latency)"
+        )?;
+        return Ok(());
+    }
+    if !cs.deep_pass {
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "C-state penalty (trimmed mean, C0 pinned -> deep C-states allowed):"
+    )?;
+    let modes = [
+        ("POC ON", &cs.pinned_on, &app.final_on),
+        ("CFS", &cs.pinned_off, &app.final_off),
+    ];
+    for (label, pinned, deep) in modes {
+        if let (Some(p), Some(d)) = (pinned, deep) {
+            writeln!(
+                out,
+                "{:>12} {:>11.2} μs -> {:>8.2} μs {:>+9.2} μs",
+                label,
+                p.trimmed_mean / 1000.0,
+                d.trimmed_mean / 1000.0,
+                (d.trimmed_mean - p.trimmed_mean) / 1000.0,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_depth_sweep(out: &mut dyn Write, app: &App) -> io::Result<()> {
+    writeln!(out)?;
+    writeln!(out, "Latency vs queue depth (trimmed mean / p99):")?;
+    writeln!(out, "{:>12} {:>22} {:>22}", "depth", "POC ON", "CFS")?;
+    for r in &app.depth_sweep {
+        let cell = |sr: &Option<StatResult>| match sr {
+            Some(sr) => format!(
+                "{:.2} / {:.2} μs",
+                sr.trimmed_mean / 1000.0,
+                sr.p99 / 1000.0
             ),
-            ("p50", on.p50 as f64 / 1000.0, off.p50 as f64 / 1000.0, true),
-            ("p99", on.p99 as f64 / 1000.0, off.p99 as f64 / 1000.0, true),
-            ("min", on.min as f64 / 1000.0, off.min as f64 / 1000.0, true),
-            ("max", on.max as f64 / 1000.0, off.max as f64 / 1000.0, true),
-            ("stddev", on.stddev / 1000.0, off.stddev / 1000.0, true),
-            ("ops/sec", on.ops_per_sec(), off.ops_per_sec(), false),
+            None => "-".into(),
+        };
+        let tag = if r.deep_cstates { " (deep C)" } else { "" };
+        writeln!(
+            out,
+            "{:>12} {:>22} {:>22}",
+            format!("{}{}", r.depth, tag),
+            cell(&r.on),
+            cell(&r.off)
+        )?;
+    }
+    Ok(())
+}
+
+fn write_perf_summary(out: &mut dyn Write, app: &App) -> io::Result<()> {
+    writeln!(out)?;
+    if app.perf_on.is_none() && app.perf_off.is_none() {
+        writeln!(
+            out,
+            "perf: counters unavailable (need CAP_PERFMON or a lower perf_event_paranoid)"
+        )?;
+        return Ok(());
+    }
+    writeln!(out, "perf counters (per 1k wakeups, measured phases):")?;
+    writeln!(out, "{:>12} {:>14} {:>14} {:>12}", "", "POC ON", "CFS", "Δ")?;
+    let per_k = |c: Option<u64>, sr: Option<&StatResult>| match (c, sr) {
+        (Some(c), Some(sr)) if sr.count > 0 => Some(c as f64 * 1000.0 / sr.count as f64),
+        _ => None,
+    };
+    let empty = PerfCounts::default();
+    let on_rows = app.perf_on.as_ref().unwrap_or(&empty).rows();
+    let off_rows = app.perf_off.as_ref().unwrap_or(&empty).rows();
+    for ((label, on), (_, off)) in on_rows.into_iter().zip(off_rows) {
+        let v_on = per_k(on, app.final_on.as_ref());
+        let v_off = per_k(off, app.final_off.as_ref());
+        let cell = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.2}", v));
+        let delta = match (v_on, v_off) {
+            (Some(a), Some(b)) if b != 0.0 => format!("{:>+8.1}%", (a - b) / b * 100.0),
+            _ => String::new(),
+        };
+        writeln!(
+            out,
+            "{:>12} {:>14} {:>14} {}",
+            label,
+            cell(v_on),
+            cell(v_off),
+            delta
+        )?;
+    }
+    Ok(())
+}
+
+fn write_idle_summary(out: &mut dyn Write, app: &App) -> io::Result<()> {
+    writeln!(out)?;
+    writeln!(
+        out,
+        "C-state residency (share of CPU time / entries per 1k wakeups, measured phases):"
+    )?;
+    writeln!(out, "{:>12} {:>22} {:>22}", "", "POC ON", "CFS")?;
+    let ncpus = app.system.ncpus as f64;
+    let cell = |r: Option<&IdleResidency>, sr: Option<&StatResult>, name: &str| {
+        let (Some(r), Some(sr)) = (r, sr) else {
+            return "-".to_string();
+        };
+        let Some(s) = r.states.iter().find(|s| s.name == name) else {
+            return "-".to_string();
+        };
+        let share = if r.wall_us > 0 {
+            s.time_us as f64 / (r.wall_us as f64 * ncpus) * 100.0
+        } else {
+            0.0
+        };
+        let per_k = if sr.count > 0 {
+            s.entries as f64 * 1000.0 / sr.count as f64
+        } else {
+            0.0
+        };
+        format!("{:.1}% / {:.1}", share, per_k)
+    };
+    let mut names: Vec<&str> = Vec::new();
+    for r in app.idle_on.iter().chain(app.idle_off.iter()) {
+        for s in &r.states {
+            if !names.contains(&s.name.as_str()) {
+                names.push(&s.name);
+            }
+        }
+    }
+    for name in names {
+        writeln!(
+            out,
+            "{:>12} {:>22} {:>22}",
+            name,
+            cell(app.idle_on.as_ref(), app.final_on.as_ref(), name),
+            cell(app.idle_off.as_ref(), app.final_off.as_ref(), name),
+        )?;
+    }
+    Ok(())
+}
+
+fn write_sched_summary(out: &mut dyn Write, app: &App) -> io::Result<()> {
+    writeln!(out)?;
+    writeln!(
+        out,
+        "Scheduler activity (/proc/schedstat, all CPUs, per 1k wakeups, measured phases):"
+    )?;
+    writeln!(out, "{:>12} {:>14} {:>14} {:>12}", "", "POC ON", "CFS", "Δ")?;
+    let per_k = |c: Option<u64>, sr: Option<&StatResult>| match (c, sr) {
+        (Some(c), Some(sr)) if sr.count > 0 => Some(c as f64 * 1000.0 / sr.count as f64),
+        _ => None,
+    };
+    let on_rows = app.sched_on.map(|d| d.rows());
+    let off_rows = app.sched_off.map(|d| d.rows());
+    for (i, (label, _)) in SchedDelta::default().rows().into_iter().enumerate() {
+        let v_on = per_k(on_rows.map(|r| r[i].1), app.final_on.as_ref());
+        let v_off = per_k(off_rows.map(|r| r[i].1), app.final_off.as_ref());
+        let cell = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.2}", v));
+        let delta = match (v_on, v_off) {
+            (Some(a), Some(b)) if b != 0.0 => format!("{:>+8.1}%", (a - b) / b * 100.0),
+            _ => String::new(),
+        };
+        writeln!(
+            out,
+            "{:>12} {:>14} {:>14} {}",
+            label,
+            cell(v_on),
+            cell(v_off),
+            delta
+        )?;
+    }
+    writeln!(
+        out,
+        "  (migrations: wakeups placing the task off the waker's CPU)"
+    )?;
+    Ok(())
+}
+
+fn write_priority_classes(out: &mut dyn Write, app: &App) -> io::Result<()> {
+    writeln!(out)?;
+    writeln!(out, "Latency by worker priority (trimmed mean / p99):")?;
+    writeln!(out, "{:>12} {:>22} {:>22}", "class", "POC ON", "CFS")?;
+    for class in &app.priority_classes {
+        let cell = |rounds: &[StatResult]| {
+            if rounds.is_empty() {
+                return "-".to_string();
+            }
+            let sr = StatResult::merge(rounds);
+            format!(
+                "{:.2} / {:.2} μs",
+                sr.trimmed_mean / 1000.0,
+                sr.p99 / 1000.0
+            )
+        };
+        let label = if class.priority > 0 {
+            format!("FIFO {}", class.priority)
+        } else {
+            "normal".to_string()
+        };
+        writeln!(
+            out,
+            "{:>12} {:>22} {:>22}",
+            label,
+            cell(&class.on),
+            cell(&class.off)
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_int_boundaries() {
+        assert_eq!(format_int(0.0), "0");
+        assert_eq!(format_int(999.0), "999");
+        assert_eq!(format_int(1_000.0), "1,000");
+        assert_eq!(format_int(999_999.0), "999,999");
+        assert_eq!(format_int(1_000_000.0), "1,000,000");
+        assert_eq!(format_int(1_234_567_890.0), "1,234,567,890");
+        // Past u32::MAX.
+        assert_eq!(format_int(12_345_678_901.0), "12,345,678,901");
+        assert_eq!(format_int(999.5), "1,000");
+        assert_eq!(format_int(-999.0), "-999");
+        assert_eq!(format_int(-1_000.0), "-1,000");
+        assert_eq!(format_int(-1_234_567.0), "-1,234,567");
+        // Rounds to -0: no sign on zero.
+        assert_eq!(format_int(-0.4), "0");
+    }
+
+    #[test]
+    fn center_pad_counts_chars() {
+        assert_eq!(center_pad("ON", 6), "  ON  ");
+        // Odd padding: the extra space goes right.
+        assert_eq!(center_pad("ON", 5), " ON  ");
+        // Multi-byte: padded by chars, not bytes.
+        assert_eq!(center_pad("\u{0394}", 3), " \u{0394} ");
+        assert_eq!(center_pad("\u{03bc}s", 6), "  \u{03bc}s  ");
+        assert_eq!(center_pad("\u{03bc}s", 6).chars().count(), 6);
+    }
+
+    #[test]
+    fn center_pad_truncates() {
+        assert_eq!(center_pad("POC ON", 6), "POC ON");
+        assert_eq!(center_pad("POC ON", 3), "POC");
+        assert_eq!(center_pad("anything", 0), "");
+        // Cut between chars, never inside one.
+        assert_eq!(center_pad("\u{0394}\u{03bc}s", 2), "\u{0394}\u{03bc}");
+        assert_eq!(center_pad("\u{03bc}s", 1), "\u{03bc}");
+    }
+
+    /// An app with results in every panel, so each draws real content.
+    fn finished_app() -> App {
+        let mut app = App::new(SystemInfo::detect(), BenchParams::calculate(8, 4));
+        let samples: Vec<u64> = (0..2_000).map(|i| 1_000 + i * 37 % 50_000).collect();
+        let result = StatResult::compute(&mut samples.clone(), stats::DEFAULT_TRIM_FRAC);
+        app.hist_on = Some(Histogram::from_samples(&samples, app.bucketing));
+        app.hist_off = app.hist_on.clone();
+        app.final_on = Some(result.clone());
+        app.final_off = Some(result.clone());
+        app.rounds_on = vec![result.clone(); 3];
+        app.rounds_off = vec![result; 3];
+        app.recent = samples;
+        app.phase = Phase::Done;
+        app
+    }
+
+    #[test]
+    fn draw_survives_tiny_terminals() {
+        use ratatui::backend::TestBackend;
+        use ratatui::Terminal;
+
+        let mut app = finished_app();
+        let sizes = [
+            (1, 1),
+            (1, 40),
+            (2, 2),
+            (40, 1),
+            (10, 3),
+            (29, 8),
+            (30, 5),
+            (80, 10),
         ];
-        for (label, v_on, v_off, _lower_is_better) in rows {
-            let delta = if v_off != 0.0 {
-                (v_on - v_off) / v_off * 100.0
-            } else {
-                0.0
-            };
-            let (on_s, off_s) = if label == "ops/sec" {
-                (format_int(v_on), format_int(v_off))
-            } else {
-                (format!("{:.2} μs", v_on), format!("{:.2} μs", v_off))
-            };
-            println!("{:>12} {:>14} {:>14} {:>+8.1}%", label, on_s, off_s, delta);
+        for (show_rounds, show_sparkline, hist_cdf) in [
+            (false, false, false),
+            (false, false, true),
+            (false, true, false),
+            (true, false, false),
+        ] {
+            app.show_rounds = show_rounds;
+            app.show_sparkline = show_sparkline;
+            app.hist_cdf = hist_cdf;
+            for (w, h) in sizes {
+                let mut terminal = Terminal::new(TestBackend::new(w, h)).unwrap();
+                terminal.draw(|f| draw(f, &app)).unwrap();
+            }
         }
     }
-    println!();
+
+    #[test]
+    fn format_duration_ns_unit_boundaries() {
+        assert_eq!(format_duration_ns(0.0), "0 ns");
+        assert_eq!(format_duration_ns(999.4), "999 ns");
+        assert_eq!(format_duration_ns(999.5), "1.00 \u{03bc}s");
+        assert_eq!(format_duration_ns(1_500.0), "1.50 \u{03bc}s");
+        assert_eq!(format_duration_ns(999_994.0), "999.99 \u{03bc}s");
+        assert_eq!(format_duration_ns(999_995.0), "1.00 ms");
+        assert_eq!(format_duration_ns(12_345_678.0), "12.35 ms");
+    }
+
+    #[test]
+    fn format_duration_ns_negatives() {
+        assert_eq!(format_duration_ns(-0.4), "0 ns");
+        assert_eq!(format_duration_ns(-999.4), "-999 ns");
+        assert_eq!(format_duration_ns(-999.5), "-1.00 \u{03bc}s");
+        assert_eq!(format_duration_ns(-999_995.0), "-1.00 ms");
+    }
 }
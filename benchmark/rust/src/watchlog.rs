@@ -0,0 +1,39 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::stats::StatResult;
+
+/// `--watch-log`: one CSV line per `--watch` cycle with that cycle's own
+/// results, appended and flushed as each cycle ends so a soak test can be
+/// followed with `tail -f` and survives being killed.
+pub struct WatchLog {
+    out: File,
+}
+
+impl WatchLog {
+    /// Open for appending; the header is written only to a new or empty file.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut out = OpenOptions::new().create(true).append(true).open(path)?;
+        if out.metadata()?.len() == 0 {
+            writeln!(
+                out,
+                "unix_time,cycle,on_mean_ns,on_p50_ns,on_p99_ns,off_mean_ns,off_p50_ns,off_p99_ns"
+            )?;
+        }
+        Ok(Self { out })
+    }
+
+    pub fn append(&mut self, cycle: usize, on: &StatResult, off: &StatResult) -> io::Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        writeln!(
+            self.out,
+            "{},{},{:.1},{:.1},{:.1},{:.1},{:.1},{:.1}",
+            now, cycle, on.mean, on.p50, on.p99, off.mean, off.p50, off.p99
+        )?;
+        self.out.flush()
+    }
+}